@@ -0,0 +1,217 @@
+//! Batch verification of interactive Chaum-Pedersen transcripts.
+//!
+//! Instead of checking r1 = g^s * y1^c and r2 = h^s * y2^c for every
+//! transcript, the verifier picks random weights a_i and b_i and checks the
+//! single equation
+//!
+//! g^(sum a_i * s_i) * h^(sum b_i * s_i) *
+//!     prod y1_i^(a_i * c_i) * y2_i^(b_i * c_i) * r1_i^(-a_i) * r2_i^(-b_i) = 1
+//!
+//! with one multi-exponentiation. It holds for valid transcripts, and for a
+//! batch with an invalid one it holds only with probability 2^-128 since the
+//! weights are unknown to the provers. When it fails the batch is split in
+//! halves until the invalid transcripts are found.
+
+use crate::{get_random_array, verify, CyclicGroup};
+use rand::{CryptoRng, RngCore};
+
+/// The bytes of the random weights, 128 bits are enough to make a forged
+/// batch pass with a negligible probability.
+const WEIGHT_BYTES: usize = 16;
+
+/// The public values of a run of the interactive protocol.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transcript<G: CyclicGroup> {
+    /// g^x registered by the prover.
+    pub y1: G::Element,
+    /// h^x registered by the prover.
+    pub y2: G::Element,
+    /// g^k sent by the prover.
+    pub r1: G::Element,
+    /// h^k sent by the prover.
+    pub r2: G::Element,
+    /// Challenge chosen by the verifier.
+    pub c: G::Scalar,
+    /// Answer of the prover.
+    pub s: G::Scalar,
+}
+
+impl<G: CyclicGroup> Transcript<G> {
+    /// Verifies this transcript alone, see [`verify`].
+    pub fn verify(&self, group: &G) -> bool {
+        verify(
+            group, &self.r1, &self.r2, &self.y1, &self.y2, &self.c, &self.s,
+        )
+    }
+}
+
+/// Verifies many transcripts at once and returns the indices of the invalid
+/// ones if there is any.
+///
+/// The elements of the transcripts must have been validated with
+/// [`CyclicGroup::decode`], an element outside of the group of order q could
+/// make the combined check meaningless.
+///
+/// * `group` - the cyclic group where `g` and `h` are defined.
+/// * `transcripts` - the transcripts to verify.
+/// * `rng` - the random generator of the weights, they must be unpredictable
+///   to the provers.
+pub fn verify_batch<G: CyclicGroup, R: RngCore + CryptoRng + ?Sized>(
+    group: &G,
+    transcripts: &[Transcript<G>],
+    rng: &mut R,
+) -> Result<(), Vec<usize>> {
+    let mut invalid = Vec::new();
+    find_invalid(group, transcripts, 0, rng, &mut invalid);
+
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        Err(invalid)
+    }
+}
+
+/// Pushes to `invalid` the indices, shifted by `offset`, of the invalid
+/// transcripts by splitting the batch while its combined check fails.
+fn find_invalid<G: CyclicGroup, R: RngCore + CryptoRng + ?Sized>(
+    group: &G,
+    transcripts: &[Transcript<G>],
+    offset: usize,
+    rng: &mut R,
+    invalid: &mut Vec<usize>,
+) {
+    match transcripts {
+        [] => {}
+        [transcript] => {
+            if !transcript.verify(group) {
+                invalid.push(offset);
+            }
+        }
+        _ => {
+            if batch_holds(group, transcripts, rng) {
+                return;
+            }
+            let (left, right) = transcripts.split_at(transcripts.len() / 2);
+            find_invalid(group, left, offset, rng, invalid);
+            find_invalid(group, right, offset + left.len(), rng, invalid);
+        }
+    }
+}
+
+/// The combined check of all the `transcripts` with fresh random weights.
+fn batch_holds<G: CyclicGroup, R: RngCore + CryptoRng + ?Sized>(
+    group: &G,
+    transcripts: &[Transcript<G>],
+    rng: &mut R,
+) -> bool {
+    let zero = G::Scalar::from(0);
+    let mut g_exp = zero.clone();
+    let mut h_exp = zero.clone();
+    let mut exps = Vec::with_capacity(4 * transcripts.len());
+
+    for transcript in transcripts {
+        let a = group.scalar_from_bytes(&get_random_array::<WEIGHT_BYTES, R>(rng));
+        let b = group.scalar_from_bytes(&get_random_array::<WEIGHT_BYTES, R>(rng));
+
+        g_exp = group.scalar_add(&g_exp, &group.scalar_mul(&a, &transcript.s));
+        h_exp = group.scalar_add(&h_exp, &group.scalar_mul(&b, &transcript.s));
+        exps.push(group.scalar_mul(&a, &transcript.c));
+        exps.push(group.scalar_mul(&b, &transcript.c));
+        exps.push(group.scalar_sub(&zero, &a));
+        exps.push(group.scalar_sub(&zero, &b));
+    }
+
+    let mut terms = vec![
+        (group.generator(), &g_exp),
+        (group.second_generator(), &h_exp),
+    ];
+    for (transcript, exps) in transcripts.iter().zip(exps.chunks(4)) {
+        terms.push((&transcript.y1, &exps[0]));
+        terms.push((&transcript.y2, &exps[1]));
+        terms.push((&transcript.r1, &exps[2]));
+        terms.push((&transcript.r2, &exps[3]));
+    }
+
+    group.multi_exponentiate(&terms) == group.identity()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{exponentiates_points, solve_zk_challenge_s};
+    use crate::{ModPGroup, P256Group, Ristretto255Group, Secp256k1Group};
+    use rand::thread_rng;
+
+    /// Runs the protocol `n` times, with a wrong answer at the `invalid`
+    /// indices.
+    fn transcripts<G: CyclicGroup>(group: &G, n: usize, invalid: &[usize]) -> Vec<Transcript<G>> {
+        let rng = &mut thread_rng();
+        (0..n)
+            .map(|i| {
+                let x = group.random_scalar(rng);
+                let k = group.random_scalar(rng);
+                let c = group.random_scalar(rng);
+                let (y1, y2) = exponentiates_points(group, &x);
+                let (r1, r2) = exponentiates_points(group, &k);
+
+                let mut s = solve_zk_challenge_s(group, &x, &k, &c);
+                if invalid.contains(&i) {
+                    s = group.scalar_add(&s, &G::Scalar::from(1));
+                }
+                Transcript {
+                    y1,
+                    y2,
+                    r1,
+                    r2,
+                    c,
+                    s,
+                }
+            })
+            .collect()
+    }
+
+    /// Verifies batches of `n` transcripts, all valid and with a wrong
+    /// answer at the `invalid` indices.
+    fn check_group<G: CyclicGroup>(group: &G, n: usize, invalid: &[usize]) {
+        let rng = &mut thread_rng();
+
+        assert_eq!(verify_batch(group, &[], rng), Ok(()));
+        assert_eq!(
+            verify_batch(group, &transcripts(group, n, &[]), rng),
+            Ok(())
+        );
+        assert_eq!(
+            verify_batch(group, &transcripts(group, n, invalid), rng),
+            Err(invalid.to_vec())
+        );
+        assert_eq!(
+            verify_batch(group, &transcripts(group, 1, &[0]), rng),
+            Err(vec![0])
+        );
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        // the exponentiations of the MODP groups are the slowest
+        check_group(&ModPGroup::rfc3526_2048(), 3, &[1]);
+        check_group(&Secp256k1Group::default(), 10, &[3, 7, 9]);
+        check_group(&P256Group::default(), 10, &[3, 7, 9]);
+        check_group(&Ristretto255Group::default(), 10, &[0, 4, 5]);
+    }
+
+    #[test]
+    fn test_verify_batch_swapped_answers() {
+        // two wrong answers whose errors cancel out in the sum of the
+        // answers are still caught thanks to the weights
+        let group = Ristretto255Group::default();
+        let mut batch = transcripts(&group, 2, &[]);
+        let one = <Ristretto255Group as CyclicGroup>::Scalar::from(1u64);
+        batch[0].s = group.scalar_add(&batch[0].s, &one);
+        batch[1].s = group.scalar_sub(&batch[1].s, &one);
+
+        assert_eq!(
+            verify_batch(&group, &batch, &mut thread_rng()),
+            Err(vec![0, 1])
+        );
+    }
+}
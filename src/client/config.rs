@@ -0,0 +1,310 @@
+//! Configuration of the client.
+//!
+//! Every option can be given, from the highest to the lowest priority, as a
+//! command line flag, as an environment variable `ZKP_CLIENT_<OPTION>` or in
+//! a TOML file selected with `--config`. The options given nowhere take their
+//! default value. Invalid values are reported as a [`ConfigError`].
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use clap::Parser;
+use serde::{Deserialize, Deserializer};
+
+use chaum_pedersen_zkp::{expand_group_flags, Group};
+
+use crate::tls::{TlsOptions, DEFAULT_TLS_DOMAIN};
+
+/// Default host of the server.
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1";
+
+/// Default port of the server.
+pub const DEFAULT_PORT: u16 = 50051;
+
+/// The options as they are read from the command line, the environment and
+/// the config file. They are all optional so the sources can be merged.
+#[derive(Debug, Default, Parser, Deserialize)]
+#[command(
+    name = "client",
+    version,
+    about = "Chaum-Pedersen zero knowledge proof authentication client",
+    after_help = "The groups can also be selected with a flag, e.g. --elliptic or --p256."
+)]
+#[serde(default, deny_unknown_fields)]
+struct Options {
+    /// TOML file with the options not given as flags or environment variables
+    #[arg(long, env = "ZKP_CLIENT_CONFIG", value_name = "PATH")]
+    #[serde(skip)]
+    config: Option<PathBuf>,
+
+    /// Host name or address of the server [default: 127.0.0.1]
+    #[arg(long, env = "ZKP_CLIENT_ADDRESS")]
+    address: Option<String>,
+
+    /// Port of the server [default: 50051]
+    #[arg(long, env = "ZKP_CLIENT_PORT")]
+    port: Option<u16>,
+
+    /// Cyclic group the server must use: scalar, elliptic, modp2048,
+    /// modp3072, modp4096, ffdhe2048, ffdhe3072, ffdhe4096, p256 or
+    /// ristretto255 [default: the group of the server]
+    #[arg(long, env = "ZKP_CLIENT_GROUP")]
+    #[serde(deserialize_with = "parse_option")]
+    group: Option<Group>,
+
+    /// PEM CA the certificate of the server must be signed by, enables TLS
+    #[arg(long, env = "ZKP_CLIENT_TLS_CA", value_name = "PATH")]
+    tls_ca: Option<PathBuf>,
+
+    /// PEM certificate of the client, for mutual TLS
+    #[arg(long, env = "ZKP_CLIENT_TLS_CERT", value_name = "PATH")]
+    tls_cert: Option<PathBuf>,
+
+    /// PEM private key of the client certificate
+    #[arg(long, env = "ZKP_CLIENT_TLS_KEY", value_name = "PATH")]
+    tls_key: Option<PathBuf>,
+
+    /// Name the certificate of the server must be issued for
+    /// [default: localhost]
+    #[arg(long, env = "ZKP_CLIENT_TLS_DOMAIN")]
+    tls_domain: Option<String>,
+}
+
+impl Options {
+    /// Takes the options missing in `self` from `other`.
+    fn or(self, other: Options) -> Options {
+        Options {
+            config: self.config.or(other.config),
+            address: self.address.or(other.address),
+            port: self.port.or(other.port),
+            group: self.group.or(other.group),
+            tls_ca: self.tls_ca.or(other.tls_ca),
+            tls_cert: self.tls_cert.or(other.tls_cert),
+            tls_key: self.tls_key.or(other.tls_key),
+            tls_domain: self.tls_domain.or(other.tls_domain),
+        }
+    }
+}
+
+/// Deserializes an option from its string representation, like it is given in
+/// the command line.
+fn parse_option<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| value.parse().map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// The errors found reading the configuration.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// Invalid flag or environment variable, or the help or the version was
+    /// asked.
+    Cli(clap::Error),
+    /// The config file can't be read.
+    Io(PathBuf, io::Error),
+    /// The config file is not valid TOML or has unknown options.
+    File(PathBuf, toml::de::Error),
+    /// An option has an invalid value.
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Cli(error) => write!(f, "{}", error),
+            ConfigError::Io(path, error) => {
+                write!(
+                    f,
+                    "can't read the config file {}: {}",
+                    path.display(),
+                    error
+                )
+            }
+            ConfigError::File(path, error) => {
+                write!(f, "invalid config file {}: {}", path.display(), error)
+            }
+            ConfigError::Invalid(message) => write!(f, "invalid configuration: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// The configuration of the client with every default applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientConfig {
+    /// URL of the server, `https` with TLS.
+    pub server_url: String,
+    /// Group pinned by the configuration, the one of the server if `None`.
+    pub group: Option<Group>,
+    /// CA of the server and optional client certificate, plaintext if `None`.
+    pub tls: Option<TlsOptions>,
+}
+
+impl ClientConfig {
+    /// Reads the configuration from the command line `args`, the environment
+    /// and the config file.
+    pub fn load<I: IntoIterator<Item = String>>(args: I) -> Result<Self, ConfigError> {
+        let options =
+            Options::try_parse_from(expand_group_flags(args)).map_err(ConfigError::Cli)?;
+
+        let options = match &options.config {
+            Some(path) => {
+                let file = fs::read_to_string(path)
+                    .map_err(|error| ConfigError::Io(path.clone(), error))?;
+                let file = toml::from_str(&file)
+                    .map_err(|error| ConfigError::File(path.clone(), error))?;
+                options.or(file)
+            }
+            None => options,
+        };
+
+        ClientConfig::resolve(options)
+    }
+
+    /// Applies the defaults and checks the values.
+    fn resolve(options: Options) -> Result<Self, ConfigError> {
+        let identity = match (options.tls_cert, options.tls_key) {
+            (Some(cert), Some(key)) => Some((cert, key)),
+            (None, None) => None,
+            _ => {
+                return Err(ConfigError::Invalid(
+                    "mutual TLS needs both tls-cert and tls-key".to_string(),
+                ))
+            }
+        };
+
+        let tls = match options.tls_ca {
+            Some(ca) => Some(TlsOptions {
+                ca,
+                identity,
+                domain: options
+                    .tls_domain
+                    .unwrap_or_else(|| DEFAULT_TLS_DOMAIN.to_string()),
+            }),
+            None if identity.is_none() && options.tls_domain.is_none() => None,
+            None => {
+                return Err(ConfigError::Invalid(
+                    "TLS needs the CA of the server tls-ca".to_string(),
+                ))
+            }
+        };
+
+        let address = options
+            .address
+            .unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+        if address.is_empty() || address.contains(['/', ' ']) {
+            return Err(ConfigError::Invalid(format!(
+                "invalid server address `{}`",
+                address
+            )));
+        }
+
+        let scheme = if tls.is_some() { "https" } else { "http" };
+        Ok(ClientConfig {
+            server_url: format!(
+                "{}://{}:{}",
+                scheme,
+                address,
+                options.port.unwrap_or(DEFAULT_PORT)
+            ),
+            group: options.group,
+            tls,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(args: &[&str]) -> Result<ClientConfig, ConfigError> {
+        ClientConfig::load(
+            std::iter::once("client")
+                .chain(args.iter().copied())
+                .map(String::from),
+        )
+    }
+
+    #[test]
+    fn test_defaults() {
+        assert_eq!(
+            load(&[]).unwrap(),
+            ClientConfig {
+                server_url: "http://127.0.0.1:50051".to_string(),
+                group: None,
+                tls: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_command_line() {
+        assert_eq!(
+            load(&[
+                "--address=zkp.example.com",
+                "--port=50052",
+                "--elliptic",
+                "--tls-ca=ca.pem",
+                "--tls-cert=client.pem",
+                "--tls-key=client.key",
+                "--tls-domain=zkp.example.com",
+            ])
+            .unwrap(),
+            ClientConfig {
+                server_url: "https://zkp.example.com:50052".to_string(),
+                group: Some(Group::EllipticCurve),
+                tls: Some(TlsOptions {
+                    ca: "ca.pem".into(),
+                    identity: Some(("client.pem".into(), "client.key".into())),
+                    domain: "zkp.example.com".to_string(),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("client.toml");
+        fs::write(&path, "group = \"p256\"\ntls_ca = \"ca.pem\"\n").unwrap();
+        let config_arg = format!("--config={}", path.display());
+
+        let config = load(&[&config_arg]).unwrap();
+        assert_eq!(config.server_url, "https://127.0.0.1:50051");
+        assert_eq!(config.group, Some(Group::P256));
+
+        // the flags win over the file
+        let config = load(&[&config_arg, "--group=ristretto255"]).unwrap();
+        assert_eq!(config.group, Some(Group::Ristretto255));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            load(&["--tls-cert=client.pem", "--tls-key=client.key"]),
+            Err(ConfigError::Invalid(_))
+        ));
+        assert!(matches!(
+            load(&["--tls-ca=ca.pem", "--tls-cert=client.pem"]),
+            Err(ConfigError::Invalid(_))
+        ));
+        assert!(matches!(
+            load(&["--address=http://localhost"]),
+            Err(ConfigError::Invalid(_))
+        ));
+        assert!(matches!(load(&["--group=p384"]), Err(ConfigError::Cli(_))));
+        assert!(matches!(
+            load(&["--config=/nonexistent/client.toml"]),
+            Err(ConfigError::Io(_, _))
+        ));
+    }
+}
@@ -1,43 +1,200 @@
-use num::BigUint;
-use num::traits::One;
-use std::io::{stdin, stdout, Write};
+use rand::rngs::OsRng;
 use std::env;
+use std::io::{stdin, stdout, IsTerminal, Write};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tonic::transport::{Channel, Endpoint};
+use tonic::Code;
 
 pub mod zkp_auth {
     include!("../zkp_auth.rs");
 }
 
+mod config;
+mod tls;
+
+use config::{ClientConfig, ConfigError};
+
 use zkp_auth::auth_client::AuthClient;
-use zkp_auth::{AuthenticationAnswerRequest, AuthenticationChallengeRequest, RegisterRequest};
+use zkp_auth::{
+    AuthenticationAnswerRequest, AuthenticationChallengeRequest, GetParametersRequest,
+    GetParametersResponse, GetSaltRequest, LoginRequest, LogoutRequest, RegisterRequest,
+    RotateKeyRequest, ValidateSessionRequest,
+};
 
+use chaum_pedersen_zkp::fiat_shamir::{login_context, prove_non_interactive, rotate_key_context};
+use chaum_pedersen_zkp::password::{derive_secret, generate_salt, KdfParams};
+use chaum_pedersen_zkp::protocol::{Challenge, Prover, Witness};
 use chaum_pedersen_zkp::{
-    parse_group_from_command_line, get_constants, get_random_number, exponentiates_points,
-    solve_zk_challenge_s,
+    exponentiates_points_secret, CyclicGroup, Error, Group, ModPGroup, P256Group,
+    Ristretto255Group, Secp256k1Group,
 };
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = env::args().collect();
-    let group = parse_group_from_command_line(args);
+async fn main() {
+    let config = match ClientConfig::load(env::args()) {
+        Ok(config) => config,
+        Err(ConfigError::Cli(error)) => error.exit(),
+        Err(error) => {
+            eprintln!("error: {}", error);
+            process::exit(2);
+        }
+    };
 
-    let server_addr = "http://127.0.0.1:50051";
+    if let Err(error) = connect(config).await {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
+}
 
-    println!(
-        "Running client connecting to {} ZKP: {:?}",
-        server_addr, group
-    );
+/// Connects to the server of the configuration and runs the client loop.
+async fn connect(config: ClientConfig) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Running client connecting to {}", config.server_url);
 
-    let mut client = AuthClient::connect(server_addr).await?;
+    let mut endpoint = Endpoint::from_shared(config.server_url.clone())?;
+    match config.tls {
+        Some(tls) => {
+            println!("[CLIENT] TLS: {:?}", tls);
+            let tls = tls
+                .load()
+                .map_err(|error| format!("can't read the TLS files: {}", error))?;
+            endpoint = endpoint.tls_config(tls)?;
+        }
+        None => println!("[CLIENT] Warning: no TLS, the traffic is not encrypted"),
+    }
 
-    let (p, q, g, h) = get_constants(&group);
+    let channel = endpoint.connect().await.map_err(|error| {
+        format!(
+            "can't connect to the server {}: {}",
+            config.server_url, error
+        )
+    })?;
+    let mut client = AuthClient::new(channel);
 
-    'main_loop: loop {
-        let x = get_random_number();
-        println!("Your new password is: {:?}", x);
+    // the client uses the group of the server unless one is pinned
+    let parameters = client
+        .get_parameters(GetParametersRequest {})
+        .await
+        .map_err(|status| {
+            format!(
+                "can't get the parameters of the server: {}",
+                status.message()
+            )
+        })?
+        .into_inner();
+    let server_group: Group = parameters
+        .group
+        .parse()
+        .map_err(|error| format!("the server uses an unsupported group: {}", error))?;
+
+    let group = match config.group {
+        Some(pinned) if pinned != server_group => {
+            return Err(Error::GroupMismatch {
+                expected: pinned,
+                found: server_group,
+            }
+            .into())
+        }
+        _ => server_group,
+    };
+    println!("[CLIENT] ZKP: {:?}", group);
+
+    match group {
+        Group::Scalar => start(client, ModPGroup::default(), &parameters).await,
+        Group::EllipticCurve => start(client, Secp256k1Group::default(), &parameters).await,
+        Group::Modp2048 => start(client, ModPGroup::rfc3526_2048(), &parameters).await,
+        Group::Modp3072 => start(client, ModPGroup::rfc3526_3072(), &parameters).await,
+        Group::Modp4096 => start(client, ModPGroup::rfc3526_4096(), &parameters).await,
+        Group::Ffdhe2048 => start(client, ModPGroup::ffdhe2048(), &parameters).await,
+        Group::Ffdhe3072 => start(client, ModPGroup::ffdhe3072(), &parameters).await,
+        Group::Ffdhe4096 => start(client, ModPGroup::ffdhe4096(), &parameters).await,
+        Group::P256 => start(client, P256Group::default(), &parameters).await,
+        Group::Ristretto255 => start(client, Ristretto255Group::default(), &parameters).await,
+    }
+}
+
+/// Fails if the parameters announced by the server are not exactly the ones
+/// of `group`.
+fn check_parameters<G: CyclicGroup>(
+    group: &G,
+    parameters: &GetParametersResponse,
+) -> Result<(), String> {
+    let different: Vec<&str> = [
+        ("p", group.modulus().to_bytes_be() == parameters.p),
+        ("q", group.order().to_bytes_be() == parameters.q),
+        ("g", group.encode(group.generator()) == parameters.g),
+        ("h", group.encode(group.second_generator()) == parameters.h),
+    ]
+    .iter()
+    .filter(|(_, equal)| !equal)
+    .map(|(name, _)| *name)
+    .collect();
+
+    if different.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "the parameters of the server don't match the group {}: different {}",
+            parameters.group,
+            different.join(", ")
+        ))
+    }
+}
+
+/// Checks the parameters of the server and runs the client loop.
+async fn start<G: CyclicGroup>(
+    client: AuthClient<Channel>,
+    group: G,
+    parameters: &GetParametersResponse,
+) -> Result<(), Box<dyn std::error::Error>> {
+    check_parameters(&group, parameters)?;
+    run(client, group).await
+}
+
+/// Reads the password without echoing it. When the input is not a terminal
+/// (e.g. piped from a script) it is read as a plain line.
+fn read_password() -> std::io::Result<String> {
+    if stdin().is_terminal() {
+        rpassword::prompt_password("Enter your password: ")
+    } else {
+        println!("Enter your password");
+        let mut password = String::new();
+        stdin().read_line(&mut password)?;
+        Ok(password.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+/// Reads a yes or no answer, an empty line or the end of the input selects
+/// `default`.
+fn read_yes_no(default: bool) -> bool {
+    loop {
+        let mut stdin_string = String::new();
+        let _ = stdout().flush();
+        stdin()
+            .read_line(&mut stdin_string)
+            .expect("Did not enter a correct string");
 
-        let (y1, y2) = exponentiates_points(&x, &g, &h, &p).unwrap();
+        match stdin_string.trim() {
+            "y" | "Y" | "yes" | "Yes" => return true,
+            "n" | "N" | "no" | "No" => return false,
+            "" => return default,
+            _ => {
+                println!("Entered option should be yes or no: (y, Y, yes, Yes, n, N, no, No or simply `Enter`)");
+            }
+        }
+    }
+}
+
+/// Runs the interactive registration and login loop using the cyclic group
+/// `group` for the ZK protocol.
+async fn run<G: CyclicGroup>(
+    mut client: AuthClient<Channel>,
+    group: G,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut rng = OsRng;
 
-        println!("Enter your name to register");
+    'main_loop: loop {
+        println!("Enter your name to login or register");
 
         let mut stdin_string = String::new();
         let _ = stdout().flush();
@@ -46,37 +203,73 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .expect("Did not enter a correct string");
         let user_name = stdin_string.trim().to_string();
 
-        // (y1, y2) = (g^x, h^x) secret x
-        println!("sending register request");
+        let password = read_password()?;
 
+        // registered users get back their salt, new users pick a fresh one
         let server_response = client
-            .register(RegisterRequest {
+            .get_salt(GetSaltRequest {
                 user: user_name.clone(),
-                y1: y1.serialize(),
-                y2: y2.serialize(),
             })
             .await;
 
-        if let Err(registration_response) = &server_response {
-            println!(
-                "[CLIENT] Error occurred during registration: {:?}",
-                registration_response.message()
-            );
-            continue 'main_loop;
+        let (salt, new_user) = match server_response {
+            Ok(salt_response) => (salt_response.into_inner().salt, false),
+            Err(salt_response) if salt_response.code() == Code::NotFound => {
+                (generate_salt(&mut rng).to_vec(), true)
+            }
+            Err(salt_response) => {
+                println!(
+                    "[CLIENT] Error occurred during salt request: {:?}",
+                    salt_response.message()
+                );
+                continue 'main_loop;
+            }
+        };
+
+        // x = Argon2id(password, salt) mod q
+        let x = match derive_secret(&group, password.as_bytes(), &salt, &KdfParams::default()) {
+            Ok(x) => x,
+            Err(error) => {
+                println!("[CLIENT] Error deriving the secret: {:?}", error);
+                continue 'main_loop;
+            }
+        };
+
+        let prover = Prover::new(&group, Witness::new(x.clone()));
+
+        if new_user {
+            // (y1, y2) = (g^x, h^x) secret x
+            let statement = prover.statement();
+            println!("sending register request");
+
+            let server_response = client
+                .register(RegisterRequest {
+                    user: user_name.clone(),
+                    y1: group.encode(&statement.y1),
+                    y2: group.encode(&statement.y2),
+                    salt,
+                })
+                .await;
+
+            if let Err(registration_response) = &server_response {
+                println!(
+                    "[CLIENT] Error occurred during registration: {:?}",
+                    registration_response.message()
+                );
+                continue 'main_loop;
+            }
         }
 
         // (r1, r2) = (g^k, h^k) random k
         println!("Sending authentication challenge request");
 
-        let k = get_random_number();
-
-        let (r1, r2) = exponentiates_points(&k, &g, &h, &p).unwrap();
+        let (prover, commitment) = prover.commit(&group, &mut rng);
 
         let server_response = client
             .create_authentication_challenge(AuthenticationChallengeRequest {
-                user: user_name,
-                r1: r1.serialize(),
-                r2: r2.serialize(),
+                user: user_name.clone(),
+                r1: group.encode(&commitment.r1),
+                r2: group.encode(&commitment.r2),
             })
             .await;
 
@@ -90,41 +283,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         println!("Solving challenge, would you like to solve it right?\nIf `no` we add 1 to the solution which is wrong and see what happens [Y/n]");
 
-        let solve_challenge_right;
-
-        'option_loop: loop {
-            let mut stdin_string = String::new();
-            let _ = stdout().flush();
-            stdin()
-                .read_line(&mut stdin_string)
-                .expect("Did not enter a correct string");
-
-            match stdin_string.trim() {
-                "y" | "Y" | "yes" | "Yes" | "" => {
-                    solve_challenge_right = true;
-                    break 'option_loop;
-                }
-                "n" | "N" | "no" | "No" => {
-                    solve_challenge_right = false;
-                    break 'option_loop;
-                }
-                _ => {
-                    println!("Entered option should be yes or no: (y, Y, yes, Yes, n, N, no, No or simply `Enter`)");
-                    continue 'option_loop;
-                }
-            }
-        }
+        let solve_challenge_right = read_yes_no(true);
 
         let response = server_response?.into_inner();
         let auth_id = response.auth_id;
         println!("[CLIENT] Auth ID received: {}", auth_id);
 
-        let c = response.c;
-        let c = BigUint::from_bytes_be(&c);
-        let mut s = solve_zk_challenge_s(&x, &k, &c, &q);
+        let challenge = Challenge::from_bytes(&group, &response.c)?;
+        let mut answer = prover.respond(&group, &challenge);
 
         if !solve_challenge_right {
-            s += BigUint::one();
+            answer.s = group.scalar_add(&answer.s, &G::Scalar::from(1));
         }
 
         println!("[CLIENT] Solve and send challenge solution");
@@ -132,7 +301,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let server_response = client
             .verify_authentication(AuthenticationAnswerRequest {
                 auth_id,
-                s: s.to_bytes_be(),
+                s: answer.to_bytes(&group),
             })
             .await;
 
@@ -150,5 +319,149 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 )
             }
         }
+
+        // the same login in a single round trip with a non-interactive proof
+        println!("[CLIENT] Login with a non-interactive proof");
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let context = login_context(&user_name, timestamp);
+        let mut proof = prove_non_interactive(&group, &x, &context, &mut rng);
+
+        if !solve_challenge_right {
+            proof.s = group.scalar_add(&proof.s, &G::Scalar::from(1));
+        }
+
+        let server_response = client
+            .login(LoginRequest {
+                user: user_name.clone(),
+                timestamp,
+                c: group.scalar_to_bytes(&proof.c),
+                s: group.scalar_to_bytes(&proof.s),
+            })
+            .await;
+
+        let session_id = match server_response {
+            Ok(login_response) => {
+                let session_id = login_response.into_inner().session_id;
+                println!("[CLIENT] Session ID: {:?}", session_id);
+                session_id
+            }
+            Err(login_response) => {
+                println!(
+                    "[CLIENT] Error occurred (server response): {:?}\n",
+                    login_response.message()
+                );
+                continue 'main_loop;
+            }
+        };
+
+        // check the session like any other service would and close it
+        match client
+            .validate_session(ValidateSessionRequest {
+                session_id: session_id.clone(),
+            })
+            .await
+        {
+            Ok(session) => {
+                let session = session.into_inner();
+                println!(
+                    "[CLIENT] Session of {:?} valid until {}",
+                    session.user, session.expires_at
+                )
+            }
+            Err(session) => println!(
+                "[CLIENT] Error occurred validating the session: {:?}",
+                session.message()
+            ),
+        }
+
+        match client.logout(LogoutRequest { session_id }).await {
+            Ok(_) => println!("[CLIENT] Logged out\n"),
+            Err(logout) => println!(
+                "[CLIENT] Error occurred during logout: {:?}\n",
+                logout.message()
+            ),
+        }
+
+        println!("Would you like to change your password? [y/N]");
+        if !read_yes_no(false) {
+            continue 'main_loop;
+        }
+
+        let new_password = read_password()?;
+        let new_salt = generate_salt(&mut rng).to_vec();
+        let new_x = match derive_secret(
+            &group,
+            new_password.as_bytes(),
+            &new_salt,
+            &KdfParams::default(),
+        ) {
+            Ok(new_x) => new_x,
+            Err(error) => {
+                println!("[CLIENT] Error deriving the secret: {:?}", error);
+                continue 'main_loop;
+            }
+        };
+
+        let (y1, y2) = exponentiates_points_secret(&group, &new_x);
+        let (y1, y2) = (group.encode(&y1), group.encode(&y2));
+
+        // the current secret proves the ownership of the account and signs
+        // the new keys
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let context = rotate_key_context(&user_name, timestamp, &y1, &y2, &new_salt);
+        let proof = prove_non_interactive(&group, &x, &context, &mut rng);
+
+        match client
+            .rotate_key(RotateKeyRequest {
+                user: user_name,
+                timestamp,
+                c: group.scalar_to_bytes(&proof.c),
+                s: group.scalar_to_bytes(&proof.s),
+                y1,
+                y2,
+                salt: new_salt,
+            })
+            .await
+        {
+            Ok(_) => println!("[CLIENT] Password changed\n"),
+            Err(rotate_response) => println!(
+                "[CLIENT] Error occurred changing the password: {:?}\n",
+                rotate_response.message()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameters_of<G: CyclicGroup>(group: &G, name: &str) -> GetParametersResponse {
+        GetParametersResponse {
+            group: name.to_string(),
+            p: group.modulus().to_bytes_be(),
+            q: group.order().to_bytes_be(),
+            g: group.encode(group.generator()),
+            h: group.encode(group.second_generator()),
+        }
+    }
+
+    #[test]
+    fn test_check_parameters() {
+        let group = P256Group::default();
+        let parameters = parameters_of(&group, "p256");
+        assert_eq!(check_parameters(&group, &parameters), Ok(()));
+
+        // a server with its own second generator
+        let mut parameters = parameters_of(&group, "p256");
+        parameters.h = group.encode(&group.hash_to_element(b"another seed"));
+        assert_eq!(
+            check_parameters(&group, &parameters),
+            Err("the parameters of the server don't match the group p256: different h".to_string())
+        );
+
+        let parameters = parameters_of(&ModPGroup::rfc3526_2048(), "modp2048");
+        assert!(check_parameters(&ModPGroup::ffdhe2048(), &parameters).is_err());
     }
 }
@@ -0,0 +1,55 @@
+//! TLS of the gRPC client.
+//!
+//! The client connects in plaintext unless it is given the CA that signed the
+//! certificate of the server. With a certificate and its private key it also
+//! authenticates itself to servers requiring mutual TLS.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use tonic::transport::{Certificate, ClientTlsConfig, Identity};
+
+/// Name checked against the certificate of the server by default.
+pub const DEFAULT_TLS_DOMAIN: &str = "localhost";
+
+/// The PEM files of the TLS configuration of the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsOptions {
+    /// CA the certificate of the server must be signed by.
+    pub ca: PathBuf,
+    /// Certificate and private key of the client, for mutual TLS.
+    pub identity: Option<(PathBuf, PathBuf)>,
+    /// Name the certificate of the server must be issued for.
+    pub domain: String,
+}
+
+impl TlsOptions {
+    /// Reads the PEM files.
+    pub fn load(&self) -> io::Result<ClientTlsConfig> {
+        let mut config = ClientTlsConfig::new()
+            .ca_certificate(Certificate::from_pem(fs::read(&self.ca)?))
+            .domain_name(self.domain.clone());
+
+        if let Some((cert, key)) = &self.identity {
+            config = config.identity(Identity::from_pem(fs::read(cert)?, fs::read(key)?));
+        }
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_missing_file() {
+        let options = TlsOptions {
+            ca: PathBuf::from("/nonexistent/ca.pem"),
+            identity: None,
+            domain: DEFAULT_TLS_DOMAIN.to_string(),
+        };
+        assert!(options.load().is_err());
+    }
+}
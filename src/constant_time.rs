@@ -0,0 +1,159 @@
+//! Helpers to exponentiate secret scalars (passwords `x` and nonces `k`)
+//! without branches or memory accesses that depend on their bits.
+//!
+//! Note that the big integer arithmetic of `num-bigint` underneath is not
+//! guaranteed to run in constant time, these helpers only remove the secret
+//! dependent control flow of the exponentiation algorithms.
+
+use num_bigint::BigUint;
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+/// Swaps `a` and `b` if `swap` is set, touching the same bytes in both cases.
+///
+/// * `len` - the byte length used to represent both numbers, it should be the
+///   length of the modulus they are reduced by.
+pub(crate) fn conditional_swap(a: &mut BigUint, b: &mut BigUint, swap: Choice, len: usize) {
+    let mut a_bytes = to_fixed_bytes(a, len);
+    let mut b_bytes = to_fixed_bytes(b, len);
+
+    for (a_byte, b_byte) in a_bytes.iter_mut().zip(b_bytes.iter_mut()) {
+        u8::conditional_swap(a_byte, b_byte, swap);
+    }
+
+    *a = BigUint::from_bytes_be(&a_bytes);
+    *b = BigUint::from_bytes_be(&b_bytes);
+}
+
+fn to_fixed_bytes(number: &BigUint, len: usize) -> Vec<u8> {
+    let bytes = number.to_bytes_le();
+    let mut fixed = vec![0u8; len];
+    fixed[..bytes.len()].copy_from_slice(&bytes);
+    fixed.reverse();
+    fixed
+}
+
+/// Returns an scalar equivalent to `k` mod `n` whose bit length is always
+/// `n.bits() + 1`, so the ladder runs the same number of steps for every
+/// secret and starts from a known top bit.
+pub(crate) fn blind_scalar(k: &BigUint, n: &BigUint) -> BigUint {
+    let k = k % n + n;
+    let k_plus_n = &k + n;
+    let top = Choice::from(k.bit(n.bits()) as u8);
+
+    let mut blinded = k;
+    let mut other = k_plus_n;
+    let len = ((n.bits() + 2) as usize).div_ceil(8);
+    // keep `k + n` unless `k + n` already has the top bit set
+    conditional_swap(&mut blinded, &mut other, !top, len);
+    blinded
+}
+
+/// Computes base^exp mod modulus with a Montgomery ladder: every step does one
+/// multiplication and one squaring regardless of the bit of the exponent.
+///
+/// * `order` - the order of `base`, used to blind the exponent.
+pub(crate) fn modpow_ladder(
+    base: &BigUint,
+    exp: &BigUint,
+    modulus: &BigUint,
+    order: &BigUint,
+) -> BigUint {
+    let exp = blind_scalar(exp, order);
+    let len = (modulus.bits() as usize).div_ceil(8);
+
+    let mut r0 = base % modulus;
+    let mut r1 = (&r0 * &r0) % modulus;
+
+    for i in (0..exp.bits() - 1).rev() {
+        let bit = Choice::from(exp.bit(i) as u8);
+        conditional_swap(&mut r0, &mut r1, bit, len);
+        r1 = (&r0 * &r1) % modulus;
+        r0 = (&r0 * &r0) % modulus;
+        conditional_swap(&mut r0, &mut r1, bit, len);
+    }
+
+    r0
+}
+
+/// Returns `entries[index]` reading every entry, so the memory accesses don't
+/// depend on a secret `index`.
+pub(crate) fn select<T: ConditionallySelectable>(entries: &[T], index: usize) -> T {
+    let mut selected = entries[0];
+    for (i, entry) in entries.iter().enumerate().skip(1) {
+        selected.conditional_assign(entry, (i as u64).ct_eq(&(index as u64)));
+    }
+    selected
+}
+
+/// `select` for entries of several words of the same length, like the numbers
+/// in the Montgomery form or the serialized points.
+pub(crate) fn select_words<T, E>(entries: &[E], index: usize) -> Vec<T>
+where
+    T: ConditionallySelectable,
+    E: AsRef<[T]>,
+{
+    let mut selected = entries[0].as_ref().to_vec();
+    for (i, entry) in entries.iter().enumerate().skip(1) {
+        let choice = (i as u64).ct_eq(&(index as u64));
+        for (word, entry_word) in selected.iter_mut().zip(entry.as_ref()) {
+            word.conditional_assign(entry_word, choice);
+        }
+    }
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::traits::One;
+
+    #[test]
+    fn test_conditional_swap() {
+        let mut a = BigUint::from(1234u32);
+        let mut b = BigUint::from(56u32);
+
+        conditional_swap(&mut a, &mut b, Choice::from(0), 2);
+        assert_eq!((a.clone(), b.clone()), (1234u32.into(), 56u32.into()));
+
+        conditional_swap(&mut a, &mut b, Choice::from(1), 2);
+        assert_eq!((a, b), (56u32.into(), 1234u32.into()));
+    }
+
+    #[test]
+    fn test_select() {
+        let entries = [3u64, 1, 4, 1, 5];
+        for (index, entry) in entries.iter().enumerate() {
+            assert_eq!(select(&entries, index), *entry);
+        }
+
+        let entries = [vec![1u8, 2], vec![3, 4], vec![5, 6]];
+        for (index, entry) in entries.iter().enumerate() {
+            assert_eq!(select_words(&entries, index), *entry);
+        }
+    }
+
+    #[test]
+    fn test_blind_scalar() {
+        let n = BigUint::from(5003u32);
+        for k in [0u32, 1, 2, 1000, 5002, 5003, 9000] {
+            let k = BigUint::from(k);
+            let blinded = blind_scalar(&k, &n);
+            assert_eq!(blinded.bits(), n.bits() + 1);
+            assert_eq!(&blinded % &n, &k % &n);
+        }
+    }
+
+    #[test]
+    fn test_modpow_ladder() {
+        let p = BigUint::from(10007u32);
+        let q = BigUint::from(5003u32);
+        // 4 is a quadratic residue, so it has order q
+        let g = BigUint::from(4u32);
+        assert_eq!(g.modpow(&q, &p), BigUint::one());
+
+        for exp in [0u32, 1, 2, 3, 300, 5002, 5003, 123456] {
+            let exp = BigUint::from(exp);
+            assert_eq!(modpow_ladder(&g, &exp, &p, &q), g.modpow(&exp, &p));
+        }
+    }
+}
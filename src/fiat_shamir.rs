@@ -0,0 +1,446 @@
+//! Non-interactive Chaum-Pedersen proofs using the Fiat-Shamir heuristic.
+//!
+//! Instead of waiting for the verifier to choose the challenge `c`, the prover
+//! derives it by hashing the transcript of the protocol:
+//!
+//! c = H(g, h, y1, y2, r1, r2, context) mod q
+//!
+//! The resulting proof `(c, s)` can be verified by anyone knowing `y1` and
+//! `y2` without interacting with the prover. The `context` binds the proof to
+//! its purpose (e.g. the user name and a timestamp for a login) so it can't be
+//! reused somewhere else.
+
+use crate::fixed_base::FixedBase;
+use crate::hash::expand;
+use crate::{exponentiates_points_secret, solve_zk_challenge_s, CyclicGroup, Error};
+use rand::{CryptoRng, RngCore};
+
+/// Domain separation tag hashed at the beginning of every transcript.
+const DOMAIN_SEPARATOR: &[u8] = b"chaum-pedersen-zkp/fiat-shamir/v1";
+
+/// A non-interactive proof of knowledge of `x` such that y1 = g^x and
+/// y2 = h^x.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NonInteractiveProof<G: CyclicGroup> {
+    pub c: G::Scalar,
+    pub s: G::Scalar,
+}
+
+impl<G: CyclicGroup> NonInteractiveProof<G> {
+    /// Serializes the proof as `c || s`, each scalar taking `scalar_len`
+    /// bytes.
+    pub fn to_bytes(&self, group: &G) -> Vec<u8> {
+        let mut bytes = group.scalar_to_bytes(&self.c);
+        bytes.extend(group.scalar_to_bytes(&self.s));
+        bytes
+    }
+
+    /// Deserializes a proof serialized with `to_bytes`, both scalars must be
+    /// lower than q.
+    pub fn from_bytes(group: &G, bytes: &[u8]) -> Result<Self, Error> {
+        let len = group.scalar_len();
+        if bytes.len() != 2 * len {
+            return Err(Error::InvalidLength);
+        }
+        Ok(NonInteractiveProof {
+            c: group.decode_scalar(&bytes[..len])?,
+            s: group.decode_scalar(&bytes[len..])?,
+        })
+    }
+}
+
+/// Computes the challenge c = H(g, h, y1, y2, r1, r2, context) mod q.
+///
+/// Every element of the transcript is prefixed with its length so that
+/// different transcripts can't produce the same hash input. The hash is
+/// expanded with a counter to 16 bytes more than the size of `q` so the
+/// reduction mod q is unbiased for every group.
+pub fn challenge<G: CyclicGroup>(
+    group: &G,
+    y1: &G::Element,
+    y2: &G::Element,
+    r1: &G::Element,
+    r2: &G::Element,
+    context: &[u8],
+) -> G::Scalar {
+    let mut transcript = Vec::new();
+    for item in [
+        group.encode(group.generator()),
+        group.encode(group.second_generator()),
+        group.encode(y1),
+        group.encode(y2),
+        group.encode(r1),
+        group.encode(r2),
+        context.to_vec(),
+    ] {
+        transcript.extend((item.len() as u64).to_be_bytes());
+        transcript.extend(item);
+    }
+
+    let bytes = expand(DOMAIN_SEPARATOR, &transcript, group.scalar_len() + 16);
+    group.scalar_from_bytes(&bytes)
+}
+
+/// Proves the knowledge of `x_secret` for y1 = g^x and y2 = h^x.
+///
+/// * `group` - the cyclic group where `g` and `h` are defined.
+/// * `x_secret` - secret password.
+/// * `context` - public data the proof is bound to.
+/// * `rng` - the random generator used to pick the nonce `k`.
+pub fn prove_non_interactive<G: CyclicGroup, R: RngCore + CryptoRng + ?Sized>(
+    group: &G,
+    x_secret: &G::Scalar,
+    context: &[u8],
+    rng: &mut R,
+) -> NonInteractiveProof<G> {
+    let (y1, y2) = exponentiates_points_secret(group, x_secret);
+
+    let k = group.random_scalar(rng);
+    let (r1, r2) = exponentiates_points_secret(group, &k);
+
+    let c = challenge(group, &y1, &y2, &r1, &r2, context);
+    let s = solve_zk_challenge_s(group, x_secret, &k, &c);
+
+    NonInteractiveProof { c, s }
+}
+
+/// Verifies a non-interactive proof by recomputing the commitments
+/// r1 = g^s * y1^c and r2 = h^s * y2^c and checking that they hash back to
+/// the challenge `c`.
+///
+/// * `group` - the cyclic group where `g` and `h` are defined.
+/// * `y1` - g^x registered by the prover.
+/// * `y2` - h^x registered by the prover.
+/// * `proof` - the proof `(c, s)`.
+/// * `context` - the same public data used to create the proof.
+pub fn verify_non_interactive<G: CyclicGroup>(
+    group: &G,
+    y1: &G::Element,
+    y2: &G::Element,
+    proof: &NonInteractiveProof<G>,
+    context: &[u8],
+) -> bool {
+    let r1 = group.multi_exponentiate(&[(group.generator(), &proof.s), (y1, &proof.c)]);
+    let r2 = group.multi_exponentiate(&[(group.second_generator(), &proof.s), (y2, &proof.c)]);
+
+    challenge(group, y1, y2, &r1, &r2, context) == proof.c
+}
+
+/// Like `verify_non_interactive` with the precomputed tables of the keys `y1`
+/// and `y2` and of the generators, several times faster for a verifier
+/// checking many proofs of the same prover.
+pub fn verify_non_interactive_precomputed<G: CyclicGroup>(
+    group: &G,
+    y1: &FixedBase<G>,
+    y2: &FixedBase<G>,
+    proof: &NonInteractiveProof<G>,
+    context: &[u8],
+) -> bool {
+    let [g, h] = group.generator_tables();
+    let r1 = group.operate(
+        &g.exponentiate(group, &proof.s),
+        &y1.exponentiate(group, &proof.c),
+    );
+    let r2 = group.operate(
+        &h.exponentiate(group, &proof.s),
+        &y2.exponentiate(group, &proof.c),
+    );
+
+    challenge(group, y1.base(), y2.base(), &r1, &r2, context) == proof.c
+}
+
+/// The context used by the single round login: the proof is bound to the user
+/// name and to the time (unix seconds) it was created.
+pub fn login_context(user: &str, timestamp: u64) -> Vec<u8> {
+    let mut context = b"login".to_vec();
+    context.extend((user.len() as u64).to_be_bytes());
+    context.extend(user.as_bytes());
+    context.extend(timestamp.to_be_bytes());
+    context
+}
+
+/// The context used to rotate the keys of a user: the proof under the current
+/// keys is bound to the user name, the time (unix seconds) it was created and
+/// the new encoded keys and salt, so it can't install any other keys.
+pub fn rotate_key_context(
+    user: &str,
+    timestamp: u64,
+    new_y1: &[u8],
+    new_y2: &[u8],
+    new_salt: &[u8],
+) -> Vec<u8> {
+    let mut context = b"rotate-key".to_vec();
+    for field in [user.as_bytes(), new_y1, new_y2, new_salt] {
+        context.extend((field.len() as u64).to_be_bytes());
+        context.extend(field);
+    }
+    context.extend(timestamp.to_be_bytes());
+    context
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixed_base::{FixedBase, GeneratorTables};
+    use crate::group::modp::ModPTable;
+    use crate::{exponentiates_points, ModPGroup, P256Group, Secp256k1Group};
+    use num_bigint::BigUint;
+    use rand::rngs::StdRng;
+    use rand::{thread_rng, SeedableRng};
+
+    #[test]
+    fn test_prove_verify_scalar() {
+        let group = ModPGroup::default();
+
+        let x = BigUint::from(300u32);
+        let (y1, y2) = exponentiates_points(&group, &x);
+
+        let proof = prove_non_interactive(&group, &x, b"context", &mut thread_rng());
+        assert!(verify_non_interactive(&group, &y1, &y2, &proof, b"context"));
+
+        // a different context doesn't verify
+        assert!(!verify_non_interactive(
+            &group, &y1, &y2, &proof, b"another"
+        ));
+
+        // a different secret doesn't verify
+        let proof = prove_non_interactive(
+            &group,
+            &BigUint::from(301u32),
+            b"context",
+            &mut thread_rng(),
+        );
+        assert!(!verify_non_interactive(
+            &group, &y1, &y2, &proof, b"context"
+        ));
+    }
+
+    #[test]
+    fn test_prove_verify_elliptic_curve() {
+        let group = Secp256k1Group::default();
+
+        let x = group.random_scalar(&mut thread_rng());
+        let (y1, y2) = exponentiates_points(&group, &x);
+
+        let context = login_context("alice", 1_700_000_000);
+        let proof = prove_non_interactive(&group, &x, &context, &mut thread_rng());
+        assert!(verify_non_interactive(&group, &y1, &y2, &proof, &context));
+
+        let wrong = NonInteractiveProof {
+            c: proof.c,
+            s: group.scalar_add(&proof.s, &1.into()),
+        };
+        assert!(!verify_non_interactive(&group, &y1, &y2, &wrong, &context));
+
+        let context = login_context("alice", 1_700_000_001);
+        assert!(!verify_non_interactive(&group, &y1, &y2, &proof, &context));
+    }
+
+    #[test]
+    fn test_verify_precomputed() {
+        let group = P256Group::default();
+
+        let x = group.random_scalar(&mut thread_rng());
+        let (y1, y2) = exponentiates_points(&group, &x);
+        let (t1, t2) = (FixedBase::new(&group, &y1), FixedBase::new(&group, &y2));
+
+        let context = login_context("alice", 1_700_000_000);
+        let proof = prove_non_interactive(&group, &x, &context, &mut thread_rng());
+        assert!(verify_non_interactive_precomputed(
+            &group, &t1, &t2, &proof, &context
+        ));
+        assert!(!verify_non_interactive_precomputed(
+            &group, &t2, &t1, &proof, &context
+        ));
+
+        let wrong = NonInteractiveProof {
+            c: proof.c,
+            s: group.scalar_add(&proof.s, &1u64.into()),
+        };
+        assert!(!verify_non_interactive_precomputed(
+            &group, &t1, &t2, &wrong, &context
+        ));
+    }
+
+    #[test]
+    fn test_rotate_key_context() {
+        let group = ModPGroup::default();
+
+        let x = group.random_scalar(&mut thread_rng());
+        let (y1, y2) = exponentiates_points(&group, &x);
+
+        let context = rotate_key_context("alice", 1_700_000_000, b"y1", b"y2", b"salt");
+        let proof = prove_non_interactive(&group, &x, &context, &mut thread_rng());
+        assert!(verify_non_interactive(&group, &y1, &y2, &proof, &context));
+
+        // the proof can't be used for other keys, nor to login
+        let context = rotate_key_context("alice", 1_700_000_000, b"y1", b"y3", b"salt");
+        assert!(!verify_non_interactive(&group, &y1, &y2, &proof, &context));
+        let context = login_context("alice", 1_700_000_000);
+        assert!(!verify_non_interactive(&group, &y1, &y2, &proof, &context));
+    }
+
+    #[test]
+    fn test_prove_with_seeded_rng() {
+        let group = ModPGroup::rfc3526_2048();
+        let x = BigUint::from(300u32);
+
+        let a = prove_non_interactive(&group, &x, b"context", &mut StdRng::seed_from_u64(7));
+        let b = prove_non_interactive(&group, &x, b"context", &mut StdRng::seed_from_u64(7));
+        assert_eq!(a, b);
+
+        let c = prove_non_interactive(&group, &x, b"context", &mut StdRng::seed_from_u64(8));
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_proof_serialization() {
+        let group = Secp256k1Group::default();
+
+        let proof = prove_non_interactive(&group, &300.into(), b"", &mut thread_rng());
+        let bytes = proof.to_bytes(&group);
+
+        assert_eq!(bytes.len(), 64);
+        assert_eq!(
+            NonInteractiveProof::from_bytes(&group, &bytes).unwrap(),
+            proof
+        );
+        assert!(NonInteractiveProof::from_bytes(&group, &bytes[1..]).is_err());
+    }
+
+    #[test]
+    fn test_challenge_is_deterministic() {
+        let group = ModPGroup::default();
+
+        let (y1, y2) = exponentiates_points(&group, &BigUint::from(300u32));
+        let (r1, r2) = exponentiates_points(&group, &BigUint::from(10u32));
+
+        let c = challenge(&group, &y1, &y2, &r1, &r2, b"context");
+        assert_eq!(c, challenge(&group, &y1, &y2, &r1, &r2, b"context"));
+        assert_ne!(c, challenge(&group, &y1, &y2, &r2, &r1, b"context"));
+        assert!(c < group.q);
+    }
+
+    /// A MODP group whose public exponentiations panic, to check that the
+    /// prover only exponentiates its secrets in constant time.
+    #[derive(Default)]
+    struct SecretOnlyGroup {
+        inner: ModPGroup,
+        tables: GeneratorTables<SecretOnlyGroup>,
+    }
+
+    impl CyclicGroup for SecretOnlyGroup {
+        type Element = BigUint;
+        type Scalar = BigUint;
+        type Table = ModPTable;
+
+        fn identity(&self) -> BigUint {
+            self.inner.identity()
+        }
+
+        fn generator(&self) -> &BigUint {
+            self.inner.generator()
+        }
+
+        fn second_generator(&self) -> &BigUint {
+            self.inner.second_generator()
+        }
+
+        fn modulus(&self) -> BigUint {
+            self.inner.modulus()
+        }
+
+        fn order(&self) -> BigUint {
+            self.inner.order()
+        }
+
+        fn hash_to_element(&self, seed: &[u8]) -> BigUint {
+            self.inner.hash_to_element(seed)
+        }
+
+        fn operate(&self, a: &BigUint, b: &BigUint) -> BigUint {
+            self.inner.operate(a, b)
+        }
+
+        fn exponentiate(&self, _base: &BigUint, _exp: &BigUint) -> BigUint {
+            panic!("public exponentiation");
+        }
+
+        fn exponentiate_secret(&self, base: &BigUint, exp: &BigUint) -> BigUint {
+            self.inner.exponentiate_secret(base, exp)
+        }
+
+        fn precompute(&self, base: &BigUint) -> ModPTable {
+            self.inner.precompute(base)
+        }
+
+        fn exponentiate_precomputed(&self, _table: &ModPTable, _exp: &BigUint) -> BigUint {
+            panic!("public exponentiation");
+        }
+
+        fn exponentiate_precomputed_secret(&self, table: &ModPTable, exp: &BigUint) -> BigUint {
+            self.inner.exponentiate_precomputed_secret(table, exp)
+        }
+
+        fn generator_tables(&self) -> &[FixedBase<SecretOnlyGroup>; 2] {
+            self.tables.get(self)
+        }
+
+        fn encode(&self, element: &BigUint) -> Vec<u8> {
+            self.inner.encode(element)
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<BigUint, Error> {
+            self.inner.decode(bytes)
+        }
+
+        fn scalar_add(&self, a: &BigUint, b: &BigUint) -> BigUint {
+            self.inner.scalar_add(a, b)
+        }
+
+        fn scalar_sub(&self, a: &BigUint, b: &BigUint) -> BigUint {
+            self.inner.scalar_sub(a, b)
+        }
+
+        fn scalar_mul(&self, a: &BigUint, b: &BigUint) -> BigUint {
+            self.inner.scalar_mul(a, b)
+        }
+
+        fn random_scalar<R: RngCore + CryptoRng + ?Sized>(&self, rng: &mut R) -> BigUint {
+            self.inner.random_scalar(rng)
+        }
+
+        fn scalar_len(&self) -> usize {
+            self.inner.scalar_len()
+        }
+
+        fn scalar_to_bytes(&self, scalar: &BigUint) -> Vec<u8> {
+            self.inner.scalar_to_bytes(scalar)
+        }
+
+        fn scalar_from_bytes(&self, bytes: &[u8]) -> BigUint {
+            self.inner.scalar_from_bytes(bytes)
+        }
+    }
+
+    #[test]
+    fn test_prove_uses_secret_exponentiation() {
+        let group = SecretOnlyGroup::default();
+        let x = BigUint::from(300u32);
+
+        let proof = prove_non_interactive(&group, &x, b"context", &mut thread_rng());
+        let proof = NonInteractiveProof::<ModPGroup> {
+            c: proof.c,
+            s: proof.s,
+        };
+
+        let (y1, y2) = exponentiates_points(&group.inner, &x);
+        assert!(verify_non_interactive(
+            &group.inner,
+            &y1,
+            &y2,
+            &proof,
+            b"context"
+        ));
+    }
+}
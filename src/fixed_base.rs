@@ -0,0 +1,336 @@
+//! Exponentiation of fixed bases with precomputed comb tables.
+//!
+//! The generators g and h are exponentiated by every proof, and a verifier
+//! checking many proofs of the same prover exponentiates the same keys y1 and
+//! y2 again and again. For a base b known in advance, the comb method splits
+//! the bits of the exponent in `TEETH` rows of `spacing` bits and precomputes
+//! the products of b^(2^(j * spacing)) for every subset of the rows. b^e then
+//! costs `spacing` squarings and multiplications, one for every column of
+//! bits, instead of a squaring for every bit of e.
+//!
+//! A [`FixedBase`] holds the table of a base in the representation chosen by
+//! its group, see [`CyclicGroup::precompute`]. The groups build the tables of
+//! their generators on first use and `exponentiate` and `exponentiate_secret`
+//! use them automatically.
+
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+
+use crate::CyclicGroup;
+
+/// Number of rows of the comb tables: a table holds 2^TEETH entries.
+pub(crate) const TEETH: usize = 8;
+
+/// A base with the table precomputed to exponentiate it.
+pub struct FixedBase<G: CyclicGroup> {
+    base: G::Element,
+    table: G::Table,
+}
+
+impl<G: CyclicGroup> FixedBase<G> {
+    /// Precomputes the table of `base`, an element of the group of order q.
+    pub fn new(group: &G, base: &G::Element) -> Self {
+        FixedBase {
+            base: base.clone(),
+            table: group.precompute(base),
+        }
+    }
+
+    pub fn base(&self) -> &G::Element {
+        &self.base
+    }
+
+    /// base^exp for a public `exp`, its running time depends on `exp`.
+    pub fn exponentiate(&self, group: &G, exp: &G::Scalar) -> G::Element {
+        group.exponentiate_precomputed(&self.table, exp)
+    }
+
+    /// base^exp for a secret `exp` (the password `x` or the nonce `k`)
+    /// without branches or table lookups depending on its bits.
+    pub fn exponentiate_secret(&self, group: &G, exp: &G::Scalar) -> G::Element {
+        group.exponentiate_precomputed_secret(&self.table, exp)
+    }
+}
+
+impl<G: CyclicGroup> Clone for FixedBase<G> {
+    fn clone(&self) -> Self {
+        FixedBase {
+            base: self.base.clone(),
+            table: self.table.clone(),
+        }
+    }
+}
+
+impl<G: CyclicGroup> fmt::Debug for FixedBase<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FixedBase")
+            .field("base", &self.base)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The tables of the generators g and h of a group, built on first use.
+///
+/// It is a cache: clones share the tables and it is ignored when the groups
+/// are compared. The tables are looked up by their base, so a group whose `g`
+/// or `h` is changed afterwards exponentiates the new generator without
+/// table.
+pub(crate) struct GeneratorTables<G: CyclicGroup>(OnceLock<Arc<[FixedBase<G>; 2]>>);
+
+impl<G: CyclicGroup> GeneratorTables<G> {
+    /// The tables of g and h, building them if needed.
+    pub(crate) fn get(&self, group: &G) -> &[FixedBase<G>; 2] {
+        self.0.get_or_init(|| {
+            Arc::new([
+                FixedBase::new(group, group.generator()),
+                FixedBase::new(group, group.second_generator()),
+            ])
+        })
+    }
+}
+
+impl<G: CyclicGroup> Default for GeneratorTables<G> {
+    fn default() -> Self {
+        GeneratorTables(OnceLock::new())
+    }
+}
+
+impl<G: CyclicGroup> Clone for GeneratorTables<G> {
+    fn clone(&self) -> Self {
+        GeneratorTables(self.0.clone())
+    }
+}
+
+impl<G: CyclicGroup> PartialEq for GeneratorTables<G> {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl<G: CyclicGroup> fmt::Debug for GeneratorTables<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.0.get().is_some() {
+            "built"
+        } else {
+            "empty"
+        };
+        write!(f, "GeneratorTables({})", state)
+    }
+}
+
+/// The comb table of a base b for exponents of up to `TEETH * spacing` bits:
+/// entry v is the product of b^(2^(j * spacing)) for every bit j set in v.
+#[derive(Debug, Clone)]
+pub struct Comb<E> {
+    entries: Vec<E>,
+    spacing: usize,
+}
+
+impl<E: Clone> Comb<E> {
+    /// Precomputes the table of `base` for exponents of up to `bits` bits.
+    ///
+    /// * `one` - the identity of the group, entry 0.
+    /// * `mul` - the group operation.
+    /// * `square` - x * x.
+    pub(crate) fn new(
+        base: &E,
+        bits: usize,
+        one: E,
+        mul: impl Fn(&E, &E) -> E,
+        square: impl Fn(&E) -> E,
+    ) -> Self {
+        let spacing = bits.div_ceil(TEETH).max(1);
+
+        let mut entries = Vec::with_capacity(1 << TEETH);
+        entries.push(one);
+        let mut row = base.clone();
+        for j in 0..TEETH {
+            if j > 0 {
+                for _ in 0..spacing {
+                    row = square(&row);
+                }
+            }
+            // the entries with the highest bit j are the previous ones * row
+            for v in 0..1 << j {
+                let entry = mul(&entries[v], &row);
+                entries.push(entry);
+            }
+        }
+
+        Comb { entries, spacing }
+    }
+
+    pub(crate) fn entries(&self) -> &[E] {
+        &self.entries
+    }
+
+    /// Applies `f` to every entry, e.g. to change their representation.
+    pub(crate) fn map<F>(self, f: impl Fn(E) -> F) -> Comb<F> {
+        Comb {
+            entries: self.entries.into_iter().map(f).collect(),
+            spacing: self.spacing,
+        }
+    }
+
+    /// The number of columns of bits, the exponents have at most
+    /// `TEETH * columns` bits.
+    pub(crate) fn columns(&self) -> usize {
+        self.spacing
+    }
+
+    /// The column `i` of bits of `exp` (big endian), an index of the table.
+    fn column(&self, exp: &[u8], i: usize) -> usize {
+        (0..TEETH).fold(0, |column, j| {
+            column | (bit(exp, i + j * self.spacing) << j)
+        })
+    }
+
+    /// b^exp for a public `exp` (big endian) of at most `TEETH * columns`
+    /// bits, skipping the columns of zeros.
+    pub(crate) fn exponentiate(
+        &self,
+        exp: &[u8],
+        one: E,
+        mul: impl Fn(&E, &E) -> E,
+        square: impl Fn(&E) -> E,
+    ) -> E {
+        // None until the first multiplication, squaring the identity is useless
+        let mut product: Option<E> = None;
+        for i in (0..self.spacing).rev() {
+            if let Some(value) = product.as_mut() {
+                *value = square(value);
+            }
+
+            let column = self.column(exp, i);
+            if column != 0 {
+                let entry = &self.entries[column];
+                product = Some(match &product {
+                    Some(value) => mul(value, entry),
+                    None => entry.clone(),
+                });
+            }
+        }
+
+        product.unwrap_or(one)
+    }
+
+    /// b^exp for an `exp` (big endian) of at most `TEETH * columns` bits doing
+    /// the same squarings and multiplications for every exponent, the columns
+    /// of zeros included.
+    ///
+    /// * `select` - returns the entry of a column. For a secret `exp` it must
+    ///   read the whole table, e.g. with `constant_time::select`, so the
+    ///   memory accesses don't depend on the column.
+    pub(crate) fn exponentiate_secret(
+        &self,
+        exp: &[u8],
+        select: impl Fn(usize) -> E,
+        mul: impl Fn(&E, &E) -> E,
+        square: impl Fn(&E) -> E,
+    ) -> E {
+        let mut product = select(self.column(exp, self.spacing - 1));
+        for i in (0..self.spacing - 1).rev() {
+            product = square(&product);
+            product = mul(&product, &select(self.column(exp, i)));
+        }
+        product
+    }
+}
+
+/// The bit `i`, from the least significant, of the big endian `exp`.
+fn bit(exp: &[u8], i: usize) -> usize {
+    match exp.len().checked_sub(i / 8 + 1) {
+        Some(index) => ((exp[index] >> (i % 8)) & 1) as usize,
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ModPGroup, P256Group, Ristretto255Group, Secp256k1Group};
+    use num::traits::One;
+    use num_bigint::BigUint;
+    use rand::thread_rng;
+
+    #[test]
+    fn test_comb() {
+        let p = BigUint::from(10009u32);
+        let mul = |a: &BigUint, b: &BigUint| (a * b) % &p;
+        let square = |a: &BigUint| (a * a) % &p;
+
+        for bits in [1, 8, 13, 32] {
+            let comb = Comb::new(&BigUint::from(3u32), bits, BigUint::one(), mul, square);
+            assert!(comb.columns() * TEETH >= bits);
+
+            for exp in [0u32, 1, 2, 255, 0x1234, u32::MAX] {
+                let exp = BigUint::from(exp) % (BigUint::one() << bits);
+                let expected = BigUint::from(3u32).modpow(&exp, &p);
+                let bytes = exp.to_bytes_be();
+
+                assert_eq!(
+                    comb.exponentiate(&bytes, BigUint::one(), mul, square),
+                    expected
+                );
+                let select = |column: usize| comb.entries()[column].clone();
+                assert_eq!(
+                    comb.exponentiate_secret(&bytes, select, mul, square),
+                    expected
+                );
+            }
+        }
+    }
+
+    fn check_fixed_base<G: CyclicGroup>(group: &G) {
+        let rng = &mut thread_rng();
+        let y = group.exponentiate(group.generator(), &group.random_scalar(rng));
+        let table = FixedBase::new(group, &y);
+        assert_eq!(*table.base(), y);
+
+        for exp in [
+            G::Scalar::from(0),
+            G::Scalar::from(1),
+            group.scalar_sub(&G::Scalar::from(0), &G::Scalar::from(1)),
+            group.random_scalar(rng),
+        ] {
+            // y is not a generator so it is exponentiated without tables
+            let expected = group.exponentiate(&y, &exp);
+            assert_eq!(table.exponentiate(group, &exp), expected);
+            assert_eq!(table.exponentiate_secret(group, &exp), expected);
+
+            // the generators are, check them against Straus' method
+            for base in [group.generator(), group.second_generator()] {
+                let expected = group.multi_exponentiate(&[(base, &exp)]);
+                assert_eq!(group.exponentiate(base, &exp), expected);
+                assert_eq!(group.exponentiate_secret(base, &exp), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fixed_base() {
+        check_fixed_base(&ModPGroup::default());
+        check_fixed_base(&ModPGroup::rfc3526_2048());
+        check_fixed_base(&Secp256k1Group::default());
+        check_fixed_base(&P256Group::default());
+        check_fixed_base(&Ristretto255Group::default());
+    }
+
+    #[test]
+    fn test_changed_generators() {
+        let mut group = ModPGroup::default();
+        let x = BigUint::from(300u32);
+        assert_eq!(group.exponentiate(&group.g, &x), BigUint::from(6419u32));
+
+        // the tables are found by their base
+        std::mem::swap(&mut group.g, &mut group.h);
+        assert_eq!(group.exponentiate(&group.h, &x), BigUint::from(6419u32));
+
+        // and ignored for a new generator
+        group.g = BigUint::from(4u32);
+        assert_eq!(
+            group.exponentiate(&group.g, &x),
+            BigUint::from(4u32).modpow(&x, &group.p)
+        );
+    }
+}
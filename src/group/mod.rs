@@ -0,0 +1,183 @@
+//! Cyclic groups in which the Chaum-Pedersen protocol can be run.
+//!
+//! Every group implements the [`CyclicGroup`] trait so that the prover and
+//! the verifier functions of this library can be written once and used with
+//! any of them.
+
+pub mod modp;
+pub mod p256;
+pub mod ristretto255;
+pub mod secp256k1;
+
+use crate::fixed_base::FixedBase;
+use crate::multi_exp::straus;
+use crate::Error;
+use num_bigint::BigUint;
+use rand::{CryptoRng, RngCore};
+use std::fmt::Debug;
+
+/// A cyclic group of prime order `q` together with the two generators `g` and
+/// `h` used by the Chaum-Pedersen protocol.
+///
+/// The group operation is written multiplicatively in the documentation
+/// (`a * b`, `g^x`) even though for elliptic curves it is the point addition
+/// (`a + b`, `x * g`).
+pub trait CyclicGroup {
+    /// An element of the group: an integer mod p, a point of a curve, etc.
+    type Element: Clone + Debug + PartialEq + Send + Sync;
+
+    /// An exponent of the group elements, i.e. an integer mod q.
+    type Scalar: Clone + Debug + PartialEq + From<u64> + Send + Sync;
+
+    /// The precomputed powers of a fixed base, see [`FixedBase`].
+    type Table: Clone + Send + Sync;
+
+    /// The neutral element of the group.
+    fn identity(&self) -> Self::Element;
+
+    /// The first generator `g`.
+    fn generator(&self) -> &Self::Element;
+
+    /// The second generator `h`.
+    fn second_generator(&self) -> &Self::Element;
+
+    /// The prime `p` of the field the elements are defined over: the modulus
+    /// of the integers or of the coordinates of the points.
+    fn modulus(&self) -> BigUint;
+
+    /// The prime order `q` of the group.
+    fn order(&self) -> BigUint;
+
+    /// Hashes `seed` into an element of the subgroup of order q whose
+    /// discrete logarithm to any base is unknown.
+    fn hash_to_element(&self, seed: &[u8]) -> Self::Element;
+
+    /// The group operation: a * b.
+    fn operate(&self, a: &Self::Element, b: &Self::Element) -> Self::Element;
+
+    /// The exponentiation: base^exp. It is faster than `exponentiate_secret`
+    /// but its running time depends on `exp`, use it only for public scalars.
+    fn exponentiate(&self, base: &Self::Element, exp: &Self::Scalar) -> Self::Element;
+
+    /// The exponentiation base^exp for a secret `exp` (the password `x` or
+    /// the nonce `k`) without branches depending on its bits. `base` must be
+    /// an element of the group of order q.
+    fn exponentiate_secret(&self, base: &Self::Element, exp: &Self::Scalar) -> Self::Element;
+
+    /// The product of the exponentiations b1^e1 * b2^e2 * ... of the `terms`
+    /// (base, exp) with Straus' method, faster than computing them one by
+    /// one. Like `exponentiate` its running time depends on the exponents,
+    /// use it only for public scalars.
+    fn multi_exponentiate(&self, terms: &[(&Self::Element, &Self::Scalar)]) -> Self::Element {
+        let terms: Vec<(Self::Element, Vec<u8>)> = terms
+            .iter()
+            .map(|(base, exp)| ((*base).clone(), self.scalar_to_bytes(exp)))
+            .collect();
+        straus(
+            &terms,
+            self.identity(),
+            |a, b| self.operate(a, b),
+            |a| self.operate(a, a),
+        )
+    }
+
+    /// Precomputes the table of `base`, an element of the group of order q,
+    /// to exponentiate it faster. Use [`FixedBase::new`] rather than calling
+    /// it directly.
+    fn precompute(&self, base: &Self::Element) -> Self::Table;
+
+    /// base^exp with the table of `base`. Like `exponentiate` its running
+    /// time depends on `exp`, use it only for public scalars.
+    fn exponentiate_precomputed(&self, table: &Self::Table, exp: &Self::Scalar) -> Self::Element;
+
+    /// base^exp with the table of `base` for a secret `exp`, without branches
+    /// or table lookups depending on its bits.
+    fn exponentiate_precomputed_secret(
+        &self,
+        table: &Self::Table,
+        exp: &Self::Scalar,
+    ) -> Self::Element;
+
+    /// The tables of the generators g and h, built on first use.
+    fn generator_tables(&self) -> &[FixedBase<Self>; 2]
+    where
+        Self: Sized;
+
+    /// The table of `base` if it is one of the generators, used by
+    /// `exponentiate` and `exponentiate_secret`.
+    fn generator_table(&self, base: &Self::Element) -> Option<&FixedBase<Self>>
+    where
+        Self: Sized,
+    {
+        if base != self.generator() && base != self.second_generator() {
+            return None;
+        }
+        self.generator_tables()
+            .iter()
+            .find(|table| table.base() == base)
+    }
+
+    /// Serializes an element to an array of bytes to transfer it through the
+    /// network.
+    fn encode(&self, element: &Self::Element) -> Vec<u8>;
+
+    /// Deserializes an element from an array of bytes and validates it: the
+    /// element must belong to the subgroup of order q and can't be the
+    /// identity, otherwise the proofs involving it would be meaningless.
+    fn decode(&self, bytes: &[u8]) -> Result<Self::Element, Error>;
+
+    /// (a + b) mod q
+    fn scalar_add(&self, a: &Self::Scalar, b: &Self::Scalar) -> Self::Scalar;
+
+    /// (a - b) mod q
+    fn scalar_sub(&self, a: &Self::Scalar, b: &Self::Scalar) -> Self::Scalar;
+
+    /// (a * b) mod q
+    fn scalar_mul(&self, a: &Self::Scalar, b: &Self::Scalar) -> Self::Scalar;
+
+    /// Generates a random scalar in [0, q) with the random generator `rng`.
+    fn random_scalar<R: RngCore + CryptoRng + ?Sized>(&self, rng: &mut R) -> Self::Scalar;
+
+    /// The number of bytes of a serialized scalar.
+    fn scalar_len(&self) -> usize;
+
+    /// Serializes a scalar to an array of `scalar_len` bytes (big endian).
+    fn scalar_to_bytes(&self, scalar: &Self::Scalar) -> Vec<u8>;
+
+    /// Deserializes a scalar from an array of bytes (big endian) of any length
+    /// reducing it mod q.
+    fn scalar_from_bytes(&self, bytes: &[u8]) -> Self::Scalar;
+
+    /// Deserializes a scalar received from the network: it must take exactly
+    /// `scalar_len` bytes and be lower than q, so every scalar has a single
+    /// encoding.
+    fn decode_scalar(&self, bytes: &[u8]) -> Result<Self::Scalar, Error> {
+        if bytes.len() != self.scalar_len() {
+            return Err(Error::InvalidLength);
+        }
+        if BigUint::from_bytes_be(bytes) >= self.order() {
+            return Err(Error::ScalarOutOfRange);
+        }
+        Ok(self.scalar_from_bytes(bytes))
+    }
+}
+
+/// Serializes `scalar` left-padded with zeros to the byte length of `q`.
+pub(crate) fn biguint_to_fixed_bytes(scalar: &BigUint, q: &BigUint) -> Vec<u8> {
+    let len = q.bits().div_ceil(8) as usize;
+    let bytes = scalar.to_bytes_be();
+    let mut padded = vec![0u8; len.saturating_sub(bytes.len())];
+    padded.extend_from_slice(&bytes);
+    padded
+}
+
+/// (a - b) mod q for groups whose scalars are plain `BigUint`s.
+pub(crate) fn biguint_sub_mod(a: &BigUint, b: &BigUint, q: &BigUint) -> BigUint {
+    let a = a % q;
+    let b = b % q;
+    if a >= b {
+        a - b
+    } else {
+        q - (b - a)
+    }
+}
@@ -0,0 +1,483 @@
+//! The multiplicative group of integers modulo a prime `p`, restricted to its
+//! subgroup of prime order `q`.
+//!
+//! Besides the toy default parameters, the standard safe primes `p = 2q + 1`
+//! of RFC 3526 (MODP groups) and RFC 7919 (ffdhe groups) are available.
+
+use super::{biguint_sub_mod, biguint_to_fixed_bytes, CyclicGroup};
+use crate::constant_time::{modpow_ladder, select_words};
+use crate::fixed_base::{Comb, FixedBase, GeneratorTables};
+use crate::hash::expand;
+use crate::montgomery::Montgomery;
+use crate::multi_exp::straus;
+use crate::{get_random_number_below, Error, SECOND_GENERATOR_SEED};
+use num::traits::{One, Zero};
+use num_bigint::BigUint;
+use rand::{CryptoRng, RngCore};
+
+const RFC3526_2048_P: &str = concat!(
+    "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74",
+    "020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437",
+    "4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+    "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05",
+    "98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB",
+    "9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+    "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718",
+    "3995497CEA956AE515D2261898FA051015728E5A8AACAA68FFFFFFFFFFFFFFFF",
+);
+
+const RFC3526_3072_P: &str = concat!(
+    "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74",
+    "020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437",
+    "4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+    "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05",
+    "98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB",
+    "9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+    "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718",
+    "3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33",
+    "A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7",
+    "ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864",
+    "D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2",
+    "08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A93AD2CAFFFFFFFFFFFFFFFF",
+);
+
+const RFC3526_4096_P: &str = concat!(
+    "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74",
+    "020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437",
+    "4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+    "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05",
+    "98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB",
+    "9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+    "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718",
+    "3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33",
+    "A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7",
+    "ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864",
+    "D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2",
+    "08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A92108011A723C12A787E6D7",
+    "88719A10BDBA5B2699C327186AF4E23C1A946834B6150BDA2583E9CA2AD44CE8",
+    "DBBBC2DB04DE8EF92E8EFC141FBECAA6287C59474E6BC05D99B2964FA090C3A2",
+    "233BA186515BE7ED1F612970CEE2D7AFB81BDD762170481CD0069127D5B05AA9",
+    "93B4EA988D8FDDC186FFB7DC90A6C08F4DF435C934063199FFFFFFFFFFFFFFFF",
+);
+
+const FFDHE2048_P: &str = concat!(
+    "FFFFFFFFFFFFFFFFADF85458A2BB4A9AAFDC5620273D3CF1D8B9C583CE2D3695",
+    "A9E13641146433FBCC939DCE249B3EF97D2FE363630C75D8F681B202AEC4617A",
+    "D3DF1ED5D5FD65612433F51F5F066ED0856365553DED1AF3B557135E7F57C935",
+    "984F0C70E0E68B77E2A689DAF3EFE8721DF158A136ADE73530ACCA4F483A797A",
+    "BC0AB182B324FB61D108A94BB2C8E3FBB96ADAB760D7F4681D4F42A3DE394DF4",
+    "AE56EDE76372BB190B07A7C8EE0A6D709E02FCE1CDF7E2ECC03404CD28342F61",
+    "9172FE9CE98583FF8E4F1232EEF28183C3FE3B1B4C6FAD733BB5FCBC2EC22005",
+    "C58EF1837D1683B2C6F34A26C1B2EFFA886B423861285C97FFFFFFFFFFFFFFFF",
+);
+
+const FFDHE3072_P: &str = concat!(
+    "FFFFFFFFFFFFFFFFADF85458A2BB4A9AAFDC5620273D3CF1D8B9C583CE2D3695",
+    "A9E13641146433FBCC939DCE249B3EF97D2FE363630C75D8F681B202AEC4617A",
+    "D3DF1ED5D5FD65612433F51F5F066ED0856365553DED1AF3B557135E7F57C935",
+    "984F0C70E0E68B77E2A689DAF3EFE8721DF158A136ADE73530ACCA4F483A797A",
+    "BC0AB182B324FB61D108A94BB2C8E3FBB96ADAB760D7F4681D4F42A3DE394DF4",
+    "AE56EDE76372BB190B07A7C8EE0A6D709E02FCE1CDF7E2ECC03404CD28342F61",
+    "9172FE9CE98583FF8E4F1232EEF28183C3FE3B1B4C6FAD733BB5FCBC2EC22005",
+    "C58EF1837D1683B2C6F34A26C1B2EFFA886B4238611FCFDCDE355B3B6519035B",
+    "BC34F4DEF99C023861B46FC9D6E6C9077AD91D2691F7F7EE598CB0FAC186D91C",
+    "AEFE130985139270B4130C93BC437944F4FD4452E2D74DD364F2E21E71F54BFF",
+    "5CAE82AB9C9DF69EE86D2BC522363A0DABC521979B0DEADA1DBF9A42D5C4484E",
+    "0ABCD06BFA53DDEF3C1B20EE3FD59D7C25E41D2B66C62E37FFFFFFFFFFFFFFFF",
+);
+
+const FFDHE4096_P: &str = concat!(
+    "FFFFFFFFFFFFFFFFADF85458A2BB4A9AAFDC5620273D3CF1D8B9C583CE2D3695",
+    "A9E13641146433FBCC939DCE249B3EF97D2FE363630C75D8F681B202AEC4617A",
+    "D3DF1ED5D5FD65612433F51F5F066ED0856365553DED1AF3B557135E7F57C935",
+    "984F0C70E0E68B77E2A689DAF3EFE8721DF158A136ADE73530ACCA4F483A797A",
+    "BC0AB182B324FB61D108A94BB2C8E3FBB96ADAB760D7F4681D4F42A3DE394DF4",
+    "AE56EDE76372BB190B07A7C8EE0A6D709E02FCE1CDF7E2ECC03404CD28342F61",
+    "9172FE9CE98583FF8E4F1232EEF28183C3FE3B1B4C6FAD733BB5FCBC2EC22005",
+    "C58EF1837D1683B2C6F34A26C1B2EFFA886B4238611FCFDCDE355B3B6519035B",
+    "BC34F4DEF99C023861B46FC9D6E6C9077AD91D2691F7F7EE598CB0FAC186D91C",
+    "AEFE130985139270B4130C93BC437944F4FD4452E2D74DD364F2E21E71F54BFF",
+    "5CAE82AB9C9DF69EE86D2BC522363A0DABC521979B0DEADA1DBF9A42D5C4484E",
+    "0ABCD06BFA53DDEF3C1B20EE3FD59D7C25E41D2B669E1EF16E6F52C3164DF4FB",
+    "7930E9E4E58857B6AC7D5F42D69F6D187763CF1D5503400487F55BA57E31CC7A",
+    "7135C886EFB4318AED6A1E012D9E6832A907600A918130C46DC778F971AD0038",
+    "092999A333CB8B7A1A1DB93D7140003C2A4ECEA9F98D0ACC0A8291CDCEC97DCF",
+    "8EC9B55A7F88A46B4DB5A851F44182E1C68A007E5E655F6AFFFFFFFFFFFFFFFF",
+);
+
+/// Domain separation tag to hash seeds into the group.
+const HASH_TO_ELEMENT_DOMAIN: &[u8] = b"chaum-pedersen-zkp/modp/hash-to-element/v1";
+
+/// Integer cyclic group: elements are integers mod `p` and the operation is
+/// the multiplication mod `p`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModPGroup {
+    pub p: BigUint,
+    pub q: BigUint,
+    pub g: BigUint,
+    pub h: BigUint,
+    tables: GeneratorTables<ModPGroup>,
+}
+
+/// The comb table of a fixed base, in the Montgomery form.
+#[derive(Debug, Clone)]
+pub struct ModPTable {
+    montgomery: Montgomery,
+    comb: Comb<Vec<u64>>,
+}
+
+impl ModPGroup {
+    /// Creates a new group from its prime `p`, the order `q` of the subgroup
+    /// and the two generators `g` and `h` of that subgroup.
+    pub fn new(p: BigUint, q: BigUint, g: BigUint, h: BigUint) -> Self {
+        ModPGroup {
+            p,
+            q,
+            g,
+            h,
+            tables: GeneratorTables::default(),
+        }
+    }
+
+    /// Creates a group with the generator `g` and derives `h` by hashing the
+    /// public seed `SECOND_GENERATOR_SEED` into the group.
+    pub fn with_derived_h(p: BigUint, q: BigUint, g: BigUint) -> Self {
+        let mut group = ModPGroup::new(p, q, g, BigUint::one());
+        group.h = group.hash_to_element(SECOND_GENERATOR_SEED);
+        group
+    }
+
+    /// Creates the group of the quadratic residues modulo the safe prime `p`
+    /// given in hexadecimal. Its order is q = (p - 1) / 2 and, as all the
+    /// standard primes satisfy p = 7 mod 8, `2` is a quadratic residue and
+    /// generates the whole subgroup. We use g = 2 and derive `h`.
+    fn from_safe_prime(p_hex: &str) -> Self {
+        let p = BigUint::parse_bytes(p_hex.as_bytes(), 16).unwrap();
+        let q = (&p - BigUint::one()) >> 1;
+        ModPGroup::with_derived_h(p, q, BigUint::from(2u32))
+    }
+
+    /// 2048-bit MODP group (id 14) from RFC 3526.
+    pub fn rfc3526_2048() -> Self {
+        Self::from_safe_prime(RFC3526_2048_P)
+    }
+
+    /// 3072-bit MODP group (id 15) from RFC 3526.
+    pub fn rfc3526_3072() -> Self {
+        Self::from_safe_prime(RFC3526_3072_P)
+    }
+
+    /// 4096-bit MODP group (id 16) from RFC 3526.
+    pub fn rfc3526_4096() -> Self {
+        Self::from_safe_prime(RFC3526_4096_P)
+    }
+
+    /// ffdhe2048 group from RFC 7919.
+    pub fn ffdhe2048() -> Self {
+        Self::from_safe_prime(FFDHE2048_P)
+    }
+
+    /// ffdhe3072 group from RFC 7919.
+    pub fn ffdhe3072() -> Self {
+        Self::from_safe_prime(FFDHE3072_P)
+    }
+
+    /// ffdhe4096 group from RFC 7919.
+    pub fn ffdhe4096() -> Self {
+        Self::from_safe_prime(FFDHE4096_P)
+    }
+}
+
+impl Default for ModPGroup {
+    /// Toy parameters, these numbers are very small and shouldn't be used in
+    /// production. `h` is derived from the public seed.
+    fn default() -> Self {
+        ModPGroup::with_derived_h(
+            BigUint::from(10009u32),
+            BigUint::from(5004u32),
+            BigUint::from(3u32),
+        )
+    }
+}
+
+impl CyclicGroup for ModPGroup {
+    type Element = BigUint;
+    type Scalar = BigUint;
+    type Table = ModPTable;
+
+    fn identity(&self) -> BigUint {
+        BigUint::one()
+    }
+
+    fn generator(&self) -> &BigUint {
+        &self.g
+    }
+
+    fn second_generator(&self) -> &BigUint {
+        &self.h
+    }
+
+    fn modulus(&self) -> BigUint {
+        self.p.clone()
+    }
+
+    fn order(&self) -> BigUint {
+        self.q.clone()
+    }
+
+    /// Hashes the seed to an integer mod p and squares it (clearing the
+    /// cofactor (p - 1) / q) so the result is a quadratic residue. The hash is
+    /// repeated with an increasing counter in the unlikely case of getting 0
+    /// or 1.
+    fn hash_to_element(&self, seed: &[u8]) -> BigUint {
+        let len = (self.p.bits() as usize).div_ceil(8) + 16;
+        let cofactor = (&self.p - BigUint::one()) / &self.q;
+
+        for counter in 0u32.. {
+            let mut data = seed.to_vec();
+            data.extend(counter.to_be_bytes());
+
+            let element = BigUint::from_bytes_be(&expand(HASH_TO_ELEMENT_DOMAIN, &data, len));
+            let element = element.modpow(&cofactor, &self.p);
+            if element > BigUint::one() {
+                return element;
+            }
+        }
+        unreachable!()
+    }
+
+    fn operate(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        (a * b) % &self.p
+    }
+
+    fn exponentiate(&self, base: &BigUint, exp: &BigUint) -> BigUint {
+        if let Some(table) = self.generator_table(base) {
+            return table.exponentiate(self, exp);
+        }
+        base.modpow(exp, &self.p)
+    }
+
+    fn exponentiate_secret(&self, base: &BigUint, exp: &BigUint) -> BigUint {
+        if let Some(table) = self.generator_table(base) {
+            return table.exponentiate_secret(self, exp);
+        }
+        modpow_ladder(base, exp, &self.p, &self.q)
+    }
+
+    fn precompute(&self, base: &BigUint) -> ModPTable {
+        let montgomery = Montgomery::new(&self.p);
+        let comb = Comb::new(
+            &montgomery.to_montgomery(base),
+            self.q.bits() as usize,
+            montgomery.to_montgomery(&BigUint::one()),
+            |a, b| montgomery.mul(a, b),
+            |a| montgomery.mul(a, a),
+        );
+        ModPTable { montgomery, comb }
+    }
+
+    fn exponentiate_precomputed(&self, table: &ModPTable, exp: &BigUint) -> BigUint {
+        let montgomery = &table.montgomery;
+        let product = table.comb.exponentiate(
+            &(exp % &self.q).to_bytes_be(),
+            montgomery.to_montgomery(&BigUint::one()),
+            |a, b| montgomery.mul(a, b),
+            |a| montgomery.mul(a, a),
+        );
+        montgomery.to_biguint(&product)
+    }
+
+    fn exponentiate_precomputed_secret(&self, table: &ModPTable, exp: &BigUint) -> BigUint {
+        let montgomery = &table.montgomery;
+        let product = table.comb.exponentiate_secret(
+            &(exp % &self.q).to_bytes_be(),
+            |column| select_words(table.comb.entries(), column),
+            |a, b| montgomery.mul(a, b),
+            |a| montgomery.mul(a, a),
+        );
+        montgomery.to_biguint(&product)
+    }
+
+    fn generator_tables(&self) -> &[FixedBase<ModPGroup>; 2] {
+        self.tables.get(self)
+    }
+
+    /// Straus' method with the products in the Montgomery form.
+    fn multi_exponentiate(&self, terms: &[(&BigUint, &BigUint)]) -> BigUint {
+        let montgomery = Montgomery::new(&self.p);
+        let terms: Vec<(Vec<u64>, Vec<u8>)> = terms
+            .iter()
+            .map(|(base, exp)| (montgomery.to_montgomery(base), exp.to_bytes_be()))
+            .collect();
+
+        let product = straus(
+            &terms,
+            montgomery.to_montgomery(&BigUint::one()),
+            |a, b| montgomery.mul(a, b),
+            |a| montgomery.mul(a, a),
+        );
+        montgomery.to_biguint(&product)
+    }
+
+    /// Big endian on the fixed width of p, so every element has a single
+    /// encoding.
+    fn encode(&self, element: &BigUint) -> Vec<u8> {
+        biguint_to_fixed_bytes(element, &self.p)
+    }
+
+    /// Checks that the encoding has the width of p, that the element is in
+    /// [2, p) and that element^q = 1.
+    fn decode(&self, bytes: &[u8]) -> Result<BigUint, Error> {
+        let len = (self.p.bits() as usize).div_ceil(8);
+        if bytes.is_empty() || bytes.len() > len {
+            return Err(Error::InvalidLength);
+        }
+        if bytes.len() < len {
+            return Err(Error::NonCanonical);
+        }
+
+        let element = BigUint::from_bytes_be(bytes);
+        if element >= self.p {
+            return Err(Error::OutOfRange);
+        }
+        if element.is_one() {
+            return Err(Error::IdentityElement);
+        }
+        if element.is_zero() || !element.modpow(&self.q, &self.p).is_one() {
+            return Err(Error::NotInSubgroup);
+        }
+
+        Ok(element)
+    }
+
+    fn scalar_add(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        (a + b) % &self.q
+    }
+
+    fn scalar_sub(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        biguint_sub_mod(a, b, &self.q)
+    }
+
+    fn scalar_mul(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        (a * b) % &self.q
+    }
+
+    fn random_scalar<R: RngCore + CryptoRng + ?Sized>(&self, rng: &mut R) -> BigUint {
+        get_random_number_below(&self.q, rng)
+    }
+
+    fn scalar_len(&self) -> usize {
+        self.q.bits().div_ceil(8) as usize
+    }
+
+    fn scalar_to_bytes(&self, scalar: &BigUint) -> Vec<u8> {
+        biguint_to_fixed_bytes(scalar, &self.q)
+    }
+
+    fn scalar_from_bytes(&self, bytes: &[u8]) -> BigUint {
+        BigUint::from_bytes_be(bytes) % &self.q
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_generators_of_order_q(group: &ModPGroup) {
+        assert_eq!(group.p.bits(), group.q.bits() + 1);
+        assert_eq!(group.exponentiate(&group.g, &group.q), BigUint::one());
+        assert_eq!(group.exponentiate(&group.h, &group.q), BigUint::one());
+        assert_ne!(group.g, BigUint::one());
+        assert_ne!(group.h, BigUint::one());
+    }
+
+    #[test]
+    fn test_standard_groups_generators() {
+        assert_generators_of_order_q(&ModPGroup::rfc3526_2048());
+        assert_generators_of_order_q(&ModPGroup::rfc3526_3072());
+        assert_generators_of_order_q(&ModPGroup::rfc3526_4096());
+        assert_generators_of_order_q(&ModPGroup::ffdhe2048());
+        assert_generators_of_order_q(&ModPGroup::ffdhe3072());
+        assert_generators_of_order_q(&ModPGroup::ffdhe4096());
+    }
+
+    #[test]
+    fn test_standard_groups_primes() {
+        // the primes are 2^n - 2^(n - 64) + ... - 1, i.e. the first and last
+        // 64 bits are all set.
+        for group in [ModPGroup::rfc3526_2048(), ModPGroup::ffdhe4096()] {
+            let bytes = group.p.to_bytes_be();
+            assert!(bytes[..8].iter().all(|b| *b == 0xff));
+            assert!(bytes[bytes.len() - 8..].iter().all(|b| *b == 0xff));
+        }
+
+        assert_eq!(ModPGroup::rfc3526_2048().p.bits(), 2048);
+        assert_eq!(ModPGroup::rfc3526_3072().p.bits(), 3072);
+        assert_eq!(ModPGroup::rfc3526_4096().p.bits(), 4096);
+        assert_eq!(ModPGroup::ffdhe2048().p.bits(), 2048);
+        assert_eq!(ModPGroup::ffdhe3072().p.bits(), 3072);
+        assert_eq!(ModPGroup::ffdhe4096().p.bits(), 4096);
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let group = ModPGroup::default();
+
+        let element = BigUint::from(65256u32);
+        assert_eq!(group.encode(&element), vec![0xfe, 0xe8]);
+
+        // 6419 = 3^300 mod 10009 = 0x1913
+        let element = BigUint::from(6419u32);
+        assert_eq!(group.encode(&element), vec![0x19, 0x13]);
+        assert_eq!(group.decode(&[0x19, 0x13]).unwrap(), element);
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        let group = ModPGroup::default();
+
+        assert_eq!(group.decode(&[]), Err(Error::InvalidLength));
+        assert_eq!(group.decode(&[0x00, 0x00, 0x01]), Err(Error::InvalidLength));
+        // 65256 >= 10009
+        assert_eq!(group.decode(&[0xfe, 0xe8]), Err(Error::OutOfRange));
+        assert_eq!(group.decode(&[0x00, 0x01]), Err(Error::IdentityElement));
+        assert_eq!(group.decode(&[0x00, 0x00]), Err(Error::NotInSubgroup));
+        // 7 is not a quadratic residue mod 10009
+        assert_eq!(group.decode(&[0x00, 0x07]), Err(Error::NotInSubgroup));
+    }
+
+    #[test]
+    fn test_decode_non_canonical() {
+        let group = ModPGroup::default();
+
+        // 4 = 2^2 is in the subgroup, it is encoded on the 2 bytes of p
+        let element = BigUint::from(4u32);
+        assert_eq!(group.encode(&element), vec![0x00, 0x04]);
+        assert_eq!(group.decode(&[0x00, 0x04]), Ok(element));
+        assert_eq!(group.decode(&[0x04]), Err(Error::NonCanonical));
+
+        let group = ModPGroup::rfc3526_2048();
+        let encoded = group.encode(&group.g);
+        assert_eq!(encoded.len(), 256);
+        assert_eq!(group.decode(&encoded), Ok(group.g.clone()));
+        assert_eq!(
+            group.decode(&encoded[encoded.len() - 1..]),
+            Err(Error::NonCanonical)
+        );
+    }
+
+    #[test]
+    fn test_decode_scalar() {
+        let group = ModPGroup::default();
+        let q = group.q.to_bytes_be();
+
+        let scalar = BigUint::from(1234u32);
+        assert_eq!(
+            group.decode_scalar(&group.scalar_to_bytes(&scalar)),
+            Ok(scalar)
+        );
+        assert_eq!(group.decode_scalar(&q), Err(Error::ScalarOutOfRange));
+        assert_eq!(group.decode_scalar(&[0x01]), Err(Error::InvalidLength));
+        assert_eq!(group.decode_scalar(&[0, 0, 1]), Err(Error::InvalidLength));
+    }
+}
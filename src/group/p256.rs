@@ -0,0 +1,329 @@
+//! The group of points of the NIST P-256 (secp256r1) elliptic curve, using the
+//! constant time arithmetic of the `p256` crate.
+
+use super::{biguint_to_fixed_bytes, CyclicGroup};
+use crate::constant_time::select;
+use crate::fixed_base::{Comb, FixedBase, GeneratorTables};
+use crate::hash::expand;
+use crate::multi_exp::straus;
+use crate::{Error, SECOND_GENERATOR_SEED};
+use num_bigint::BigUint;
+use p256::elliptic_curve::group::Group;
+use p256::elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint};
+use p256::elliptic_curve::{Field, PrimeField};
+use p256::{AffinePoint, EncodedPoint, FieldBytes, ProjectivePoint, Scalar};
+use rand::{CryptoRng, RngCore};
+
+/// The prime of the field of the curve.
+const PRIME: &str = "ffffffff00000001000000000000000000000000ffffffffffffffffffffffff";
+
+/// The order of the generator of the curve.
+const ORDER: &str = "ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551";
+
+/// Domain separation tag to hash seeds into the curve.
+const HASH_TO_ELEMENT_DOMAIN: &[u8] = b"chaum-pedersen-zkp/p256/hash-to-element/v1";
+
+/// Elliptic curve cyclic group: elements are points of P-256 and the
+/// operation is the point addition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct P256Group {
+    pub g: ProjectivePoint,
+    pub h: ProjectivePoint,
+    tables: GeneratorTables<P256Group>,
+}
+
+impl P256Group {
+    pub fn prime() -> BigUint {
+        BigUint::parse_bytes(PRIME.as_bytes(), 16).unwrap()
+    }
+
+    pub fn n() -> BigUint {
+        BigUint::parse_bytes(ORDER.as_bytes(), 16).unwrap()
+    }
+}
+
+impl Default for P256Group {
+    /// Uses the standard generator of the curve as `g` and derives `h` by
+    /// hashing the public seed `SECOND_GENERATOR_SEED` into the curve.
+    fn default() -> Self {
+        let mut group = P256Group {
+            g: ProjectivePoint::GENERATOR,
+            h: ProjectivePoint::GENERATOR,
+            tables: GeneratorTables::default(),
+        };
+        group.h = group.hash_to_element(SECOND_GENERATOR_SEED);
+        group
+    }
+}
+
+impl CyclicGroup for P256Group {
+    type Element = ProjectivePoint;
+    type Scalar = Scalar;
+    type Table = Comb<ProjectivePoint>;
+
+    fn identity(&self) -> ProjectivePoint {
+        ProjectivePoint::IDENTITY
+    }
+
+    fn generator(&self) -> &ProjectivePoint {
+        &self.g
+    }
+
+    fn second_generator(&self) -> &ProjectivePoint {
+        &self.h
+    }
+
+    fn modulus(&self) -> BigUint {
+        Self::prime()
+    }
+
+    fn order(&self) -> BigUint {
+        Self::n()
+    }
+
+    /// Try-and-increment: hashes the seed and a counter to a candidate x
+    /// coordinate until it belongs to a point of the curve, then takes the
+    /// point with even y. As the cofactor is 1 the point belongs to the
+    /// subgroup of order n.
+    fn hash_to_element(&self, seed: &[u8]) -> ProjectivePoint {
+        for counter in 0u32.. {
+            let mut data = seed.to_vec();
+            data.extend(counter.to_be_bytes());
+
+            let mut encoded = vec![0x02];
+            encoded.extend(expand(HASH_TO_ELEMENT_DOMAIN, &data, 32));
+            if let Ok(point) = self.decode(&encoded) {
+                return point;
+            }
+        }
+        unreachable!()
+    }
+
+    fn operate(&self, a: &ProjectivePoint, b: &ProjectivePoint) -> ProjectivePoint {
+        a + b
+    }
+
+    fn exponentiate(&self, base: &ProjectivePoint, exp: &Scalar) -> ProjectivePoint {
+        if let Some(table) = self.generator_table(base) {
+            return table.exponentiate(self, exp);
+        }
+        base * exp
+    }
+
+    /// The scalar multiplication of the `p256` crate is already constant
+    /// time.
+    fn exponentiate_secret(&self, base: &ProjectivePoint, exp: &Scalar) -> ProjectivePoint {
+        if let Some(table) = self.generator_table(base) {
+            return table.exponentiate_secret(self, exp);
+        }
+        base * exp
+    }
+
+    fn precompute(&self, base: &ProjectivePoint) -> Comb<ProjectivePoint> {
+        Comb::new(
+            base,
+            Scalar::NUM_BITS as usize,
+            ProjectivePoint::IDENTITY,
+            |a, b| a + b,
+            |a| a.double(),
+        )
+    }
+
+    fn exponentiate_precomputed(
+        &self,
+        table: &Comb<ProjectivePoint>,
+        exp: &Scalar,
+    ) -> ProjectivePoint {
+        table.exponentiate(
+            &exp.to_bytes(),
+            ProjectivePoint::IDENTITY,
+            |a, b| a + b,
+            |a| a.double(),
+        )
+    }
+
+    /// The additions of the `p256` crate handle the identity without
+    /// branches, so the columns of zeros take the same time.
+    fn exponentiate_precomputed_secret(
+        &self,
+        table: &Comb<ProjectivePoint>,
+        exp: &Scalar,
+    ) -> ProjectivePoint {
+        table.exponentiate_secret(
+            &exp.to_bytes(),
+            |column| select(table.entries(), column),
+            |a, b| a + b,
+            |a| a.double(),
+        )
+    }
+
+    fn generator_tables(&self) -> &[FixedBase<P256Group>; 2] {
+        self.tables.get(self)
+    }
+
+    fn multi_exponentiate(&self, terms: &[(&ProjectivePoint, &Scalar)]) -> ProjectivePoint {
+        let terms: Vec<(ProjectivePoint, Vec<u8>)> = terms
+            .iter()
+            .map(|(base, exp)| (**base, exp.to_bytes().to_vec()))
+            .collect();
+        straus(
+            &terms,
+            ProjectivePoint::IDENTITY,
+            |a, b| a + b,
+            |a| a.double(),
+        )
+    }
+
+    /// Uses the SEC1 compressed encoding (33 bytes). The point at infinity is
+    /// encoded as the single byte `0x00`.
+    fn encode(&self, element: &ProjectivePoint) -> Vec<u8> {
+        element
+            .to_affine()
+            .to_encoded_point(true)
+            .as_bytes()
+            .to_vec()
+    }
+
+    /// Accepts both the SEC1 compressed and uncompressed encodings.
+    fn decode(&self, bytes: &[u8]) -> Result<ProjectivePoint, Error> {
+        match (bytes.first(), bytes.len()) {
+            (Some(0x00), 1) => return Err(Error::IdentityElement),
+            (Some(0x02 | 0x03), 33) | (Some(0x04), 65) => {}
+            (Some(0x00 | 0x02 | 0x03 | 0x04), _) | (None, _) => return Err(Error::InvalidLength),
+            _ => return Err(Error::InvalidPrefix),
+        }
+
+        let prime = Self::prime();
+        if bytes[1..]
+            .chunks(32)
+            .any(|coordinate| BigUint::from_bytes_be(coordinate) >= prime)
+        {
+            return Err(Error::OutOfRange);
+        }
+
+        let encoded = EncodedPoint::from_bytes(bytes).map_err(|_| Error::InvalidLength)?;
+        Option::<AffinePoint>::from(AffinePoint::from_encoded_point(&encoded))
+            .map(ProjectivePoint::from)
+            .ok_or(Error::NotOnCurve)
+    }
+
+    fn scalar_add(&self, a: &Scalar, b: &Scalar) -> Scalar {
+        a + b
+    }
+
+    fn scalar_sub(&self, a: &Scalar, b: &Scalar) -> Scalar {
+        a - b
+    }
+
+    fn scalar_mul(&self, a: &Scalar, b: &Scalar) -> Scalar {
+        a * b
+    }
+
+    fn random_scalar<R: RngCore + CryptoRng + ?Sized>(&self, rng: &mut R) -> Scalar {
+        Scalar::random(rng)
+    }
+
+    fn scalar_len(&self) -> usize {
+        32
+    }
+
+    fn scalar_to_bytes(&self, scalar: &Scalar) -> Vec<u8> {
+        scalar.to_repr().to_vec()
+    }
+
+    fn scalar_from_bytes(&self, bytes: &[u8]) -> Scalar {
+        let n = Self::n();
+        let reduced = BigUint::from_bytes_be(bytes) % &n;
+        let repr = FieldBytes::clone_from_slice(&biguint_to_fixed_bytes(&reduced, &n));
+        Scalar::from_repr(repr).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{check_second_generator, exponentiates_points, solve_zk_challenge_s, verify};
+    use rand::thread_rng;
+
+    #[test]
+    fn test_encode_decode() {
+        let group = P256Group::default();
+
+        let g = group.generator();
+        let encoded = group.encode(g);
+
+        assert_eq!(
+            hex::encode(&encoded),
+            "036b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296"
+        );
+        assert_eq!(group.decode(&encoded).unwrap(), *g);
+        assert_eq!(
+            group
+                .decode(g.to_affine().to_encoded_point(false).as_bytes())
+                .unwrap(),
+            *g
+        );
+
+        let h = group.second_generator();
+        assert_eq!(group.decode(&group.encode(h)).unwrap(), *h);
+        assert_ne!(g, h);
+        assert!(check_second_generator(&group));
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        let group = P256Group::default();
+
+        assert_eq!(group.decode(&[0x00]), Err(Error::IdentityElement));
+        assert_eq!(group.decode(&[0x02, 0x03]), Err(Error::InvalidLength));
+        assert_eq!(group.decode(&[0x05; 33]), Err(Error::InvalidPrefix));
+
+        let mut bytes = vec![0x02];
+        bytes.extend(P256Group::prime().to_bytes_be());
+        assert_eq!(group.decode(&bytes), Err(Error::OutOfRange));
+
+        let mut bytes = group
+            .g
+            .to_affine()
+            .to_encoded_point(false)
+            .as_bytes()
+            .to_vec();
+        bytes[64] ^= 1;
+        assert_eq!(group.decode(&bytes), Err(Error::NotOnCurve));
+    }
+
+    #[test]
+    fn test_scalars() {
+        let group = P256Group::default();
+
+        let n = P256Group::n();
+        assert_eq!(group.scalar_from_bytes(&n.to_bytes_be()), Scalar::ZERO);
+        assert_eq!(
+            group.scalar_from_bytes(&(n + BigUint::from(5u32)).to_bytes_be()),
+            Scalar::from(5u64)
+        );
+
+        let scalar = group.random_scalar(&mut thread_rng());
+        assert_eq!(
+            group.scalar_from_bytes(&group.scalar_to_bytes(&scalar)),
+            scalar
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let group = P256Group::default();
+
+        let x = group.random_scalar(&mut thread_rng());
+        let (y1, y2) = exponentiates_points(&group, &x);
+
+        let k = group.random_scalar(&mut thread_rng());
+        let (r1, r2) = exponentiates_points(&group, &k);
+
+        let c = group.random_scalar(&mut thread_rng());
+        let s = solve_zk_challenge_s(&group, &x, &k, &c);
+
+        assert!(verify(&group, &r1, &r2, &y1, &y2, &c, &s));
+        assert!(!verify(&group, &r1, &r2, &y1, &y2, &c, &(s + Scalar::ONE)));
+    }
+}
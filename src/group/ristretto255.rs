@@ -0,0 +1,282 @@
+//! The Ristretto255 group built on top of Curve25519 with the
+//! `curve25519-dalek` crate. Ristretto removes the cofactor of the curve, so
+//! every valid encoding is an element of the prime order group.
+
+use super::{biguint_to_fixed_bytes, CyclicGroup};
+use crate::fixed_base::{FixedBase, GeneratorTables};
+use crate::hash::expand;
+use crate::{Error, SECOND_GENERATOR_SEED};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoBasepointTable, RistrettoPoint};
+use curve25519_dalek::traits::{Identity, VartimeMultiscalarMul};
+use curve25519_dalek::Scalar;
+use num_bigint::BigUint;
+use rand::{CryptoRng, RngCore};
+
+/// The order of the group: 2^252 + 27742317777372353535851937790883648493.
+const ORDER: &str = "1000000000000000000000000000000014def9dea2f79cd65812631a5cf5d3ed";
+
+/// Domain separation tag to hash seeds into the group.
+const HASH_TO_ELEMENT_DOMAIN: &[u8] = b"chaum-pedersen-zkp/ristretto255/hash-to-element/v1";
+
+/// Ristretto255 cyclic group: elements are Ristretto points and the operation
+/// is the point addition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ristretto255Group {
+    pub g: RistrettoPoint,
+    pub h: RistrettoPoint,
+    tables: GeneratorTables<Ristretto255Group>,
+}
+
+impl Ristretto255Group {
+    pub fn l() -> BigUint {
+        BigUint::parse_bytes(ORDER.as_bytes(), 16).unwrap()
+    }
+}
+
+impl Default for Ristretto255Group {
+    /// Uses the standard Ristretto basepoint as `g` and derives `h` by hashing
+    /// the public seed `SECOND_GENERATOR_SEED` into the group.
+    fn default() -> Self {
+        let mut group = Ristretto255Group {
+            g: RISTRETTO_BASEPOINT_POINT,
+            h: RISTRETTO_BASEPOINT_POINT,
+            tables: GeneratorTables::default(),
+        };
+        group.h = group.hash_to_element(SECOND_GENERATOR_SEED);
+        group
+    }
+}
+
+impl CyclicGroup for Ristretto255Group {
+    type Element = RistrettoPoint;
+    type Scalar = Scalar;
+    type Table = RistrettoBasepointTable;
+
+    fn identity(&self) -> RistrettoPoint {
+        RistrettoPoint::identity()
+    }
+
+    fn generator(&self) -> &RistrettoPoint {
+        &self.g
+    }
+
+    fn second_generator(&self) -> &RistrettoPoint {
+        &self.h
+    }
+
+    /// 2^255 - 19, the prime of Curve25519.
+    fn modulus(&self) -> BigUint {
+        (BigUint::from(1u32) << 255usize) - 19u32
+    }
+
+    fn order(&self) -> BigUint {
+        Self::l()
+    }
+
+    /// Maps 64 uniform bytes derived from the seed into the group with the
+    /// Ristretto one-way map (Elligator).
+    fn hash_to_element(&self, seed: &[u8]) -> RistrettoPoint {
+        let mut bytes = [0u8; 64];
+        bytes.copy_from_slice(&expand(HASH_TO_ELEMENT_DOMAIN, seed, 64));
+        RistrettoPoint::from_uniform_bytes(&bytes)
+    }
+
+    fn operate(&self, a: &RistrettoPoint, b: &RistrettoPoint) -> RistrettoPoint {
+        a + b
+    }
+
+    fn exponentiate(&self, base: &RistrettoPoint, exp: &Scalar) -> RistrettoPoint {
+        if let Some(table) = self.generator_table(base) {
+            return table.exponentiate(self, exp);
+        }
+        base * exp
+    }
+
+    /// The scalar multiplication of `curve25519-dalek` is already constant
+    /// time.
+    fn exponentiate_secret(&self, base: &RistrettoPoint, exp: &Scalar) -> RistrettoPoint {
+        if let Some(table) = self.generator_table(base) {
+            return table.exponentiate_secret(self, exp);
+        }
+        base * exp
+    }
+
+    /// Uses the fixed-base tables of `curve25519-dalek` rather than a comb.
+    fn precompute(&self, base: &RistrettoPoint) -> RistrettoBasepointTable {
+        RistrettoBasepointTable::create(base)
+    }
+
+    fn exponentiate_precomputed(
+        &self,
+        table: &RistrettoBasepointTable,
+        exp: &Scalar,
+    ) -> RistrettoPoint {
+        table * exp
+    }
+
+    /// The multiplication by a table is constant time too.
+    fn exponentiate_precomputed_secret(
+        &self,
+        table: &RistrettoBasepointTable,
+        exp: &Scalar,
+    ) -> RistrettoPoint {
+        table * exp
+    }
+
+    fn generator_tables(&self) -> &[FixedBase<Ristretto255Group>; 2] {
+        self.tables.get(self)
+    }
+
+    fn multi_exponentiate(&self, terms: &[(&RistrettoPoint, &Scalar)]) -> RistrettoPoint {
+        RistrettoPoint::vartime_multiscalar_mul(
+            terms.iter().map(|(_, exp)| *exp),
+            terms.iter().map(|(base, _)| *base),
+        )
+    }
+
+    /// Uses the canonical 32 bytes Ristretto encoding.
+    fn encode(&self, element: &RistrettoPoint) -> Vec<u8> {
+        element.compress().to_bytes().to_vec()
+    }
+
+    /// Rejects non canonical encodings, every decoded point belongs to the
+    /// group of prime order.
+    fn decode(&self, bytes: &[u8]) -> Result<RistrettoPoint, Error> {
+        let compressed =
+            CompressedRistretto::from_slice(bytes).map_err(|_| Error::InvalidLength)?;
+        let point = compressed.decompress().ok_or(Error::NotOnCurve)?;
+        if point == RistrettoPoint::identity() {
+            return Err(Error::IdentityElement);
+        }
+        Ok(point)
+    }
+
+    fn scalar_add(&self, a: &Scalar, b: &Scalar) -> Scalar {
+        a + b
+    }
+
+    fn scalar_sub(&self, a: &Scalar, b: &Scalar) -> Scalar {
+        a - b
+    }
+
+    fn scalar_mul(&self, a: &Scalar, b: &Scalar) -> Scalar {
+        a * b
+    }
+
+    /// Reduces 64 random bytes mod l, so the scalar is uniform.
+    fn random_scalar<R: RngCore + CryptoRng + ?Sized>(&self, rng: &mut R) -> Scalar {
+        let mut bytes = [0u8; 64];
+        rng.fill_bytes(&mut bytes);
+        Scalar::from_bytes_mod_order_wide(&bytes)
+    }
+
+    fn scalar_len(&self) -> usize {
+        32
+    }
+
+    /// Big-endian like the other groups, `curve25519-dalek` itself uses
+    /// little-endian.
+    fn scalar_to_bytes(&self, scalar: &Scalar) -> Vec<u8> {
+        let mut bytes = scalar.to_bytes().to_vec();
+        bytes.reverse();
+        bytes
+    }
+
+    fn scalar_from_bytes(&self, bytes: &[u8]) -> Scalar {
+        let l = Self::l();
+        let reduced = BigUint::from_bytes_be(bytes) % &l;
+        let mut le = [0u8; 32];
+        le.copy_from_slice(&biguint_to_fixed_bytes(&reduced, &l));
+        le.reverse();
+        Scalar::from_bytes_mod_order(le)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{check_second_generator, exponentiates_points, solve_zk_challenge_s, verify};
+    use rand::thread_rng;
+
+    #[test]
+    fn test_parameters() {
+        let group = Ristretto255Group::default();
+
+        assert_eq!(
+            hex::encode(group.modulus().to_bytes_be()),
+            "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffed"
+        );
+        assert_eq!(
+            hex::encode(group.order().to_bytes_be()),
+            "1000000000000000000000000000000014def9dea2f79cd65812631a5cf5d3ed"
+        );
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let group = Ristretto255Group::default();
+
+        let g = group.generator();
+        let encoded = group.encode(g);
+
+        assert_eq!(
+            hex::encode(&encoded),
+            "e2f2ae0a6abc4e71a884a961c500515f58e30b6aa582dd8db6a65945e08d2d76"
+        );
+        assert_eq!(group.decode(&encoded).unwrap(), *g);
+
+        let h = group.second_generator();
+        assert_eq!(group.decode(&group.encode(h)).unwrap(), *h);
+        assert_ne!(g, h);
+        assert!(check_second_generator(&group));
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        let group = Ristretto255Group::default();
+
+        assert_eq!(group.decode(&[0u8; 32]), Err(Error::IdentityElement));
+        assert_eq!(group.decode(&[0u8; 33]), Err(Error::InvalidLength));
+        assert_eq!(group.decode(&[]), Err(Error::InvalidLength));
+        // negative field elements are not canonical
+        assert_eq!(group.decode(&[1u8; 32]), Err(Error::NotOnCurve));
+        assert_eq!(group.decode(&[0xffu8; 32]), Err(Error::NotOnCurve));
+    }
+
+    #[test]
+    fn test_scalars() {
+        let group = Ristretto255Group::default();
+
+        let l = Ristretto255Group::l();
+        assert_eq!(group.scalar_from_bytes(&l.to_bytes_be()), Scalar::ZERO);
+        assert_eq!(
+            group.scalar_from_bytes(&(l + BigUint::from(5u32)).to_bytes_be()),
+            Scalar::from(5u64)
+        );
+        assert_eq!(group.scalar_to_bytes(&Scalar::from(1u64))[31..], [1u8]);
+
+        let scalar = group.random_scalar(&mut thread_rng());
+        assert_eq!(
+            group.scalar_from_bytes(&group.scalar_to_bytes(&scalar)),
+            scalar
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let group = Ristretto255Group::default();
+
+        let x = group.random_scalar(&mut thread_rng());
+        let (y1, y2) = exponentiates_points(&group, &x);
+
+        let k = group.random_scalar(&mut thread_rng());
+        let (r1, r2) = exponentiates_points(&group, &k);
+
+        let c = group.random_scalar(&mut thread_rng());
+        let s = solve_zk_challenge_s(&group, &x, &k, &c);
+
+        assert!(verify(&group, &r1, &r2, &y1, &y2, &c, &s));
+        assert!(!verify(&group, &r1, &r2, &y1, &y2, &c, &(s + Scalar::ONE)));
+    }
+}
@@ -0,0 +1,205 @@
+//! The group of points of the secp256k1 elliptic curve.
+
+use super::CyclicGroup;
+use crate::fixed_base::{FixedBase, GeneratorTables};
+use crate::hash::expand;
+use crate::secp256k1::{CombTable, Scalar, Secp256k1Point};
+use crate::{Error, SECOND_GENERATOR_SEED};
+use num_bigint::BigUint;
+use rand::{CryptoRng, RngCore};
+
+/// Elliptic curve cyclic group: elements are points of secp256k1 and the
+/// operation is the point addition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Secp256k1Group {
+    pub g: Secp256k1Point,
+    pub h: Secp256k1Point,
+    tables: GeneratorTables<Secp256k1Group>,
+}
+
+/// Domain separation tag to hash seeds into the curve.
+const HASH_TO_ELEMENT_DOMAIN: &[u8] = b"chaum-pedersen-zkp/secp256k1/hash-to-element/v1";
+
+/// Seed hashed into the point added to the entries of the comb tables, see
+/// [`CombTable`].
+const COMB_OFFSET_SEED: &[u8] = b"chaum-pedersen-zkp/secp256k1/comb-offset/v1";
+
+impl Secp256k1Group {
+    /// Creates a group with the two generators `g` and `h`.
+    pub fn new(g: Secp256k1Point, h: Secp256k1Point) -> Self {
+        Secp256k1Group {
+            g,
+            h,
+            tables: GeneratorTables::default(),
+        }
+    }
+}
+
+impl Default for Secp256k1Group {
+    /// Uses the standard generator of the curve as `g` and derives `h` by
+    /// hashing the public seed `SECOND_GENERATOR_SEED` into the curve.
+    fn default() -> Self {
+        let g = Secp256k1Point::generator();
+        let mut group = Secp256k1Group::new(g, g);
+        group.h = group.hash_to_element(SECOND_GENERATOR_SEED);
+        group
+    }
+}
+
+impl CyclicGroup for Secp256k1Group {
+    type Element = Secp256k1Point;
+    type Scalar = Scalar;
+    type Table = CombTable;
+
+    fn identity(&self) -> Secp256k1Point {
+        Secp256k1Point::Zero
+    }
+
+    fn generator(&self) -> &Secp256k1Point {
+        &self.g
+    }
+
+    fn second_generator(&self) -> &Secp256k1Point {
+        &self.h
+    }
+
+    fn modulus(&self) -> BigUint {
+        Secp256k1Point::prime()
+    }
+
+    fn order(&self) -> BigUint {
+        Secp256k1Point::n()
+    }
+
+    /// Try-and-increment: hashes the seed and a counter to a candidate x
+    /// coordinate until x^3 + 7 is a square, then takes the point with even y.
+    /// As the cofactor is 1 the point belongs to the subgroup of order n.
+    fn hash_to_element(&self, seed: &[u8]) -> Secp256k1Point {
+        for counter in 0u32.. {
+            let mut data = seed.to_vec();
+            data.extend(counter.to_be_bytes());
+
+            let mut encoded = vec![0x02];
+            encoded.extend(expand(HASH_TO_ELEMENT_DOMAIN, &data, 32));
+            if let Ok(point) = Secp256k1Point::from_sec1(&encoded) {
+                return point;
+            }
+        }
+        unreachable!()
+    }
+
+    fn operate(&self, a: &Secp256k1Point, b: &Secp256k1Point) -> Secp256k1Point {
+        *a + *b
+    }
+
+    fn exponentiate(&self, base: &Secp256k1Point, exp: &Scalar) -> Secp256k1Point {
+        if let Some(table) = self.generator_table(base) {
+            return table.exponentiate(self, exp);
+        }
+        base.scale(exp)
+    }
+
+    fn exponentiate_secret(&self, base: &Secp256k1Point, exp: &Scalar) -> Secp256k1Point {
+        if let Some(table) = self.generator_table(base) {
+            return table.exponentiate_secret(self, exp);
+        }
+        base.scale_secret(exp)
+    }
+
+    fn precompute(&self, base: &Secp256k1Point) -> CombTable {
+        CombTable::new(base, &self.hash_to_element(COMB_OFFSET_SEED))
+    }
+
+    fn exponentiate_precomputed(&self, table: &CombTable, exp: &Scalar) -> Secp256k1Point {
+        table.scale(exp)
+    }
+
+    fn exponentiate_precomputed_secret(&self, table: &CombTable, exp: &Scalar) -> Secp256k1Point {
+        table.scale_secret(exp)
+    }
+
+    fn generator_tables(&self) -> &[FixedBase<Secp256k1Group>; 2] {
+        self.tables.get(self)
+    }
+
+    fn multi_exponentiate(&self, terms: &[(&Secp256k1Point, &Scalar)]) -> Secp256k1Point {
+        Secp256k1Point::multi_scale(terms)
+    }
+
+    /// Uses the SEC1 compressed encoding (33 bytes). The point at infinity is
+    /// encoded as the single byte `0x00`.
+    fn encode(&self, element: &Secp256k1Point) -> Vec<u8> {
+        element.to_sec1(true)
+    }
+
+    /// Accepts both the SEC1 compressed and uncompressed encodings.
+    fn decode(&self, bytes: &[u8]) -> Result<Secp256k1Point, Error> {
+        match Secp256k1Point::from_sec1(bytes)? {
+            Secp256k1Point::Zero => Err(Error::IdentityElement),
+            point => Ok(point),
+        }
+    }
+
+    fn scalar_add(&self, a: &Scalar, b: &Scalar) -> Scalar {
+        *a + *b
+    }
+
+    fn scalar_sub(&self, a: &Scalar, b: &Scalar) -> Scalar {
+        *a - *b
+    }
+
+    fn scalar_mul(&self, a: &Scalar, b: &Scalar) -> Scalar {
+        *a * *b
+    }
+
+    fn random_scalar<R: RngCore + CryptoRng + ?Sized>(&self, rng: &mut R) -> Scalar {
+        Scalar::random(rng)
+    }
+
+    fn scalar_len(&self) -> usize {
+        32
+    }
+
+    fn scalar_to_bytes(&self, scalar: &Scalar) -> Vec<u8> {
+        scalar.to_bytes().to_vec()
+    }
+
+    fn scalar_from_bytes(&self, bytes: &[u8]) -> Scalar {
+        Scalar::from_bytes_reduced(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let group = Secp256k1Group::default();
+
+        let g = group.generator();
+        let encoded = group.encode(g);
+
+        assert_eq!(
+            hex::encode(&encoded),
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+        );
+        assert_eq!(group.decode(&encoded).unwrap(), *g);
+        assert_eq!(group.decode(&g.to_sec1(false)).unwrap(), *g);
+
+        let h = group.second_generator();
+        assert_eq!(group.decode(&group.encode(h)).unwrap(), *h);
+
+        assert_eq!(group.encode(&group.identity()), vec![0x00]);
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        let group = Secp256k1Group::default();
+
+        assert_eq!(group.decode(&[0x01, 0x02, 0x03]), Err(Error::InvalidPrefix));
+        assert_eq!(group.decode(&[0x02, 0x03]), Err(Error::InvalidLength));
+        assert_eq!(group.decode(&[]), Err(Error::InvalidLength));
+        assert_eq!(group.decode(&[0x00]), Err(Error::IdentityElement));
+    }
+}
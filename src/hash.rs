@@ -0,0 +1,38 @@
+//! Hashing helpers shared by the Fiat-Shamir challenges and the derivation of
+//! the generators.
+
+use sha2::{Digest, Sha256};
+
+/// Expands `data` to `len` pseudo random bytes:
+///
+/// SHA-256(domain || 0) || SHA-256(domain || 1) || ... truncated to `len`
+///
+/// where every block hashes `domain`, a 4-byte big endian counter and `data`.
+pub(crate) fn expand(domain: &[u8], data: &[u8], len: usize) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(len);
+    let mut counter = 0u32;
+    while bytes.len() < len {
+        let mut hasher = Sha256::new();
+        hasher.update(domain);
+        hasher.update(counter.to_be_bytes());
+        hasher.update(data);
+        bytes.extend(hasher.finalize());
+        counter += 1;
+    }
+    bytes.truncate(len);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let bytes = expand(b"domain", b"data", 80);
+        assert_eq!(bytes.len(), 80);
+        assert_eq!(bytes[..40], expand(b"domain", b"data", 40)[..]);
+        assert_ne!(bytes, expand(b"other", b"data", 80));
+        assert_ne!(bytes, expand(b"domain", b"other", 80));
+    }
+}
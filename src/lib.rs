@@ -1,347 +1,318 @@
-mod secp256k1;
+pub mod batch;
+mod constant_time;
+pub mod fiat_shamir;
+pub mod fixed_base;
+pub mod group;
+mod hash;
+mod montgomery;
+mod multi_exp;
+pub mod password;
+pub mod protocol;
+pub mod secp256k1;
+
+pub use group::modp::ModPGroup;
+pub use group::p256::P256Group;
+pub use group::ristretto255::Ristretto255Group;
+pub use group::secp256k1::Secp256k1Group;
+pub use group::CyclicGroup;
 
-use num::traits::One;
 use num_bigint::BigUint;
-use rand::{thread_rng, distributions::Alphanumeric, Rng};
-use secp256k1::Secp256k1Point;
+use num_bigint::RandBigInt;
+use rand::{distributions::Alphanumeric, CryptoRng, Rng, RngCore};
 
 /// The possible kind of errors returned by this library.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Error {
+    /// The arguments of a function are invalid, e.g. the cost parameters of
+    /// the key derivation.
     InvalidArguments,
+    /// The two parties don't use the same cyclic group.
+    GroupMismatch { expected: Group, found: Group },
+    /// The serialized element or proof doesn't have a valid length.
+    InvalidLength,
+    /// The serialized point has an unknown SEC1 prefix.
+    InvalidPrefix,
+    /// The point doesn't satisfy the equation of the curve.
+    NotOnCurve,
+    /// An integer or a coordinate of a point is not lower than the prime `p`.
+    OutOfRange,
+    /// The element is the identity of the group.
+    IdentityElement,
+    /// The element doesn't belong to the subgroup of prime order `q`.
+    NotInSubgroup,
+    /// The statement doesn't use the generators `g` and `h` of the group.
+    WrongGenerators,
+    /// The value is valid but serialized differently from its canonical
+    /// encoding.
+    NonCanonical,
+    /// A serialized scalar is not lower than the order `q`.
+    ScalarOutOfRange,
+    /// The challenge or the proof is too old to be accepted.
+    ChallengeExpired,
+    /// The user is not registered.
+    UnknownUser,
+    /// The proof doesn't prove the knowledge of the secret.
+    InvalidProof,
 }
 
+impl Error {
+    /// Whether the error comes from a malformed or invalid encoding of an
+    /// element or a scalar.
+    pub fn is_encoding(&self) -> bool {
+        matches!(
+            self,
+            Error::InvalidLength
+                | Error::InvalidPrefix
+                | Error::NotOnCurve
+                | Error::OutOfRange
+                | Error::IdentityElement
+                | Error::NotInSubgroup
+                | Error::NonCanonical
+                | Error::ScalarOutOfRange
+        )
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidArguments => f.write_str("invalid arguments"),
+            Error::GroupMismatch { expected, found } => write!(
+                f,
+                "group mismatch: expected {} but found {}",
+                expected, found
+            ),
+            Error::InvalidLength => f.write_str("malformed encoding: bad length"),
+            Error::InvalidPrefix => f.write_str("malformed encoding: unknown prefix"),
+            Error::NotOnCurve => f.write_str("point not on the curve"),
+            Error::OutOfRange => f.write_str("integer or coordinate not lower than p"),
+            Error::IdentityElement => f.write_str("identity element"),
+            Error::NotInSubgroup => f.write_str("element not in the prime order subgroup"),
+            Error::WrongGenerators => f.write_str("statement not over the group generators"),
+            Error::NonCanonical => f.write_str("malformed encoding: not canonical"),
+            Error::ScalarOutOfRange => f.write_str("scalar not lower than q"),
+            Error::ChallengeExpired => f.write_str("challenge expired"),
+            Error::UnknownUser => f.write_str("unknown user"),
+            Error::InvalidProof => f.write_str("invalid proof"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 /// An enum use to select from the beginning of the program execution which
 /// cyclic group is going to be used.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum Group {
     #[default]
     Scalar,
     EllipticCurve,
+    /// 2048-bit MODP group from RFC 3526.
+    Modp2048,
+    /// 3072-bit MODP group from RFC 3526.
+    Modp3072,
+    /// 4096-bit MODP group from RFC 3526.
+    Modp4096,
+    /// ffdhe2048 group from RFC 7919.
+    Ffdhe2048,
+    /// ffdhe3072 group from RFC 7919.
+    Ffdhe3072,
+    /// ffdhe4096 group from RFC 7919.
+    Ffdhe4096,
+    /// NIST P-256 elliptic curve.
+    P256,
+    /// Ristretto255 group over Curve25519.
+    Ristretto255,
 }
 
-/// Structure to represent the cyclic group field.
-#[derive(Debug, Clone, PartialEq)]
-pub enum Point {
-    Scalar(BigUint),
-    ECPoint(BigUint, BigUint),
-}
-
-/// Detects if any argument is --scalar or --elliptic and returns the
-/// corresponding cyclic group to use.
-///
-/// * `args` - Vector of command line arguments.
-pub fn parse_group_from_command_line(args: Vec<String>) -> Group {
-    match args.len() {
-        2 => match args[1].trim() {
-            "--elliptic" => Group::EllipticCurve,
-            "--scalar" | "" => Group::Scalar,
-            _ => panic!("Invalid argument [--scalar(default)|--elliptic] available."),
-        },
-        _ => Group::Scalar,
+impl Group {
+    /// Every group with the name used to select it.
+    pub const ALL: [(Group, &'static str); 10] = [
+        (Group::Scalar, "scalar"),
+        (Group::EllipticCurve, "elliptic"),
+        (Group::Modp2048, "modp2048"),
+        (Group::Modp3072, "modp3072"),
+        (Group::Modp4096, "modp4096"),
+        (Group::Ffdhe2048, "ffdhe2048"),
+        (Group::Ffdhe3072, "ffdhe3072"),
+        (Group::Ffdhe4096, "ffdhe4096"),
+        (Group::P256, "p256"),
+        (Group::Ristretto255, "ristretto255"),
+    ];
+
+    /// The name used to select the group, e.g. `elliptic` or `p256`.
+    pub fn name(&self) -> &'static str {
+        Group::ALL
+            .iter()
+            .find(|(group, _)| group == self)
+            .map(|(_, name)| *name)
+            .unwrap()
     }
 }
 
-/// Returns the default constants to use in both server and clients. Note that
-/// they should be the same for both for the ZK algorithm to work.
-///
-/// * `group` - The cyclic group to use.
-pub fn get_constants(group: &Group) -> (BigUint, BigUint, Point, Point) {
-    match group {
-        Group::Scalar => get_constants_scalar(),
-        Group::EllipticCurve => get_constants_elliptic_curve(),
+impl std::fmt::Display for Group {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
     }
 }
 
-pub fn get_constants_scalar() -> (BigUint, BigUint, Point, Point) {
-    (
-        BigUint::from(10009u32),
-        BigUint::from(5004u32),
-        Point::Scalar(BigUint::from(3u32)),
-        Point::Scalar(BigUint::from(2892u32)),
-    )
-}
-
-pub fn get_constants_elliptic_curve() -> (BigUint, BigUint, Point, Point) {
-    let g = Secp256k1Point::generator();
-    let h = g.clone().scale(BigUint::from(13u32));
-    (
-        Secp256k1Point::prime(),
-        Secp256k1Point::n(),
-        Point::from_secp256k1(&g),
-        Point::from_secp256k1(&h),
-    )
+impl std::str::FromStr for Group {
+    type Err = String;
+
+    /// Parses the name of a group, see [`Group::ALL`].
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Group::ALL
+            .iter()
+            .find(|(_, group_name)| group_name.eq_ignore_ascii_case(name.trim()))
+            .map(|(group, _)| *group)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Group::ALL.iter().map(|(_, name)| *name).collect();
+                format!(
+                    "unknown group `{}`, expected one of: {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
 }
 
-impl Point {
-    /// Serializes the Point structure to an array of bytes to transferring it
-    /// through the network.
-    pub fn serialize(self: &Self) -> Vec<u8> {
-        match self {
-            Point::Scalar(x) => x.to_bytes_be(),
-            Point::ECPoint(x, y) => {
-                let mut x = x.to_bytes_be();
-                let mut y = y.to_bytes_be();
-                let diff = (x.len() as i32) - (y.len() as i32);
-                if diff > 0 {
-                    y.resize(y.len() + diff as usize, 0);
-                    y.rotate_right(diff as usize);
-                } else {
-                    x.resize(x.len() + (-diff as usize), 0);
-                    x.rotate_right((-diff) as usize);
-                }
-                x.append(&mut y);
-                x
+/// Rewrites the flags selecting a group by its name, like `--elliptic` or
+/// `--p256`, into `--group=<name>` so the command line parsers only deal with
+/// the `--group` option.
+///
+/// * `args` - Vector of command line arguments.
+pub fn expand_group_flags<I: IntoIterator<Item = String>>(args: I) -> Vec<String> {
+    args.into_iter()
+        .map(|arg| {
+            match arg
+                .strip_prefix("--")
+                .and_then(|name| name.parse::<Group>().ok())
+            {
+                Some(group) => format!("--group={}", group),
+                None => arg,
             }
-        }
-    }
-
-    /// Deserializes the Point structure from an array of bytes and transforms
-    /// it into an actual Point structure.
-    pub fn deserialize(v: Vec<u8>, group: &Group) -> Point {
-        match group {
-            Group::Scalar => Point::deserialize_into_scalar(v),
-            Group::EllipticCurve => Point::deserialize_into_ecpoint(v),
-        }
-    }
-
-    pub fn deserialize_into_scalar(v: Vec<u8>) -> Point {
-        Point::Scalar(BigUint::from_bytes_be(&v))
-    }
+        })
+        .collect()
+}
 
-    pub fn deserialize_into_ecpoint(v: Vec<u8>) -> Point {
-        let len = v.len();
+/// The public seed hashed into every group to derive its second generator
+/// `h`, so nobody knows log_g(h).
+pub const SECOND_GENERATOR_SEED: &[u8] = b"chaum-pedersen-zkp/second-generator/v1";
 
-        assert!(
-            len % 2 == 0,
-            "The length of the serialized object should be even"
-        );
-
-        Point::ECPoint(
-            BigUint::from_bytes_be(&v[..len / 2]),
-            BigUint::from_bytes_be(&v[len / 2..]),
-        )
-    }
+/// Recomputes the second generator `h` of a group from the public seed.
+pub fn derive_second_generator<G: CyclicGroup>(group: &G) -> G::Element {
+    group.hash_to_element(SECOND_GENERATOR_SEED)
+}
 
-    /// Converts a point from the `secp256k1` library into a Point
-    pub fn from_secp256k1(point: &Secp256k1Point) -> Point {
-        match point {
-            Secp256k1Point::Coor { x, y, .. } => Point::ECPoint(x.number.clone(), y.number.clone()),
-            _ => panic!("elliptic_curves::Point::Zero not convertible into a point"),
-        }
-    }
+/// Lets anyone audit that the second generator `h` of a group was derived from
+/// the public seed and not chosen as a known power of `g`.
+pub fn check_second_generator<G: CyclicGroup>(group: &G) -> bool {
+    *group.second_generator() == derive_second_generator(group)
 }
 
-/// Exponenciates two points g & h:
+/// Exponenciates the two generators g & h of the group:
 ///  - For the integer or scalar group the new ones are: g^exp & h^exp
 ///  - For the elliptic curve group the new ones are: exp * g & exp * h
-pub fn exponentiates_points(
-    exp: &BigUint,
-    g: &Point,
-    h: &Point,
-    p: &BigUint,
-) -> Result<(Point, Point), Error> {
-    match (g, h) {
-        (Point::Scalar(g), Point::Scalar(h)) => Ok(exponentiates_points_scalar(exp, g, h, p)),
-        (Point::ECPoint(gx, gy), Point::ECPoint(hx, hy)) => {
-            Ok(exponentiates_points_elliptic_curve(exp, gx, gy, hx, hy))
-        }
-        _ => Err(Error::InvalidArguments),
-    }
-}
-
-pub fn exponentiates_points_scalar(
-    exp: &BigUint,
-    g: &BigUint,
-    h: &BigUint,
-    p: &BigUint,
-) -> (Point, Point) {
+///
+/// Its running time depends on `exp`, use it only for public scalars and
+/// `exponentiates_points_secret` for the password `x` or the nonce `k`.
+pub fn exponentiates_points<G: CyclicGroup>(
+    group: &G,
+    exp: &G::Scalar,
+) -> (G::Element, G::Element) {
     (
-        Point::Scalar(g.modpow(exp, p)),
-        Point::Scalar(h.modpow(exp, p)),
+        group.exponentiate(group.generator(), exp),
+        group.exponentiate(group.second_generator(), exp),
     )
 }
 
-pub fn exponentiates_points_elliptic_curve(
-    exp: &BigUint,
-    gx: &BigUint,
-    gy: &BigUint,
-    hx: &BigUint,
-    hy: &BigUint,
-) -> (Point, Point) {
-    let g = Secp256k1Point::from_bigint(&gx, &gy);
-    let h = Secp256k1Point::from_bigint(&hx, &hy);
-
-    let g = g.scale(exp.clone());
-    let h = h.scale(exp.clone());
-
-    let g_new = match g {
-        secp256k1::Point::Coor { x, y, .. } => Point::ECPoint(x.number, y.number),
-        _ => panic!("You reach the Zero in elliptic curve multiplication"),
-    };
-
-    let h_new = match h {
-        secp256k1::Point::Coor { x, y, .. } => Point::ECPoint(x.number, y.number),
-        _ => panic!("You reach the Zero in elliptic curve multiplication"),
-    };
-
-    (g_new, h_new)
+/// Same as `exponentiates_points` for a secret `exp`, with
+/// `exponentiate_secret`.
+pub fn exponentiates_points_secret<G: CyclicGroup>(
+    group: &G,
+    exp: &G::Scalar,
+) -> (G::Element, G::Element) {
+    (
+        group.exponentiate_secret(group.generator(), exp),
+        group.exponentiate_secret(group.second_generator(), exp),
+    )
 }
 
 /// This function solves the ZK challenge `s` proposed by the verifier.
 ///
 /// s = (k - c * x) mod q
 ///
+/// * `group` - the cyclic group, `q` is its order.
 /// * `x_secret` - secret password.
 /// * `k` - random number selected by the prover.
 /// * `c` - random number selected by the verifier.
-/// * `q` - the order of the cyclic group
-pub fn solve_zk_challenge_s(x_secret: &BigUint, k: &BigUint, c: &BigUint, q: &BigUint) -> BigUint {
-    let cx = c * x_secret;
-    if *k > cx {
-        (k - cx).modpow(&BigUint::one(), q)
-    } else {
-        q - (cx - k).modpow(&BigUint::one(), q)
-    }
-}
-
-pub fn verify(
-    r1: &Point,
-    r2: &Point,
-    y1: &Point,
-    y2: &Point,
-    g: &Point,
-    h: &Point,
-    c: &BigUint,
-    s: &BigUint,
-    p: &BigUint,
-) -> Result<bool, Error> {
-    match (r1, r2, y1, y2, g, h) {
-        (
-            Point::Scalar(r1),
-            Point::Scalar(r2),
-            Point::Scalar(y1),
-            Point::Scalar(y2),
-            Point::Scalar(g),
-            Point::Scalar(h),
-        ) => Ok(verify_scalar(r1, r2, y1, y2, g, h, c, s, p)),
-        (
-            Point::ECPoint(r1x, r1y),
-            Point::ECPoint(r2x, r2y),
-            Point::ECPoint(y1x, y1y),
-            Point::ECPoint(y2x, y2y),
-            Point::ECPoint(gx, gy),
-            Point::ECPoint(hx, hy),
-        ) => Ok(verify_ecpoint(
-            r1x, r1y, r2x, r2y, y1x, y1y, y2x, y2y, gx, gy, hx, hy, c, s,
-        )),
-        _ => Err(Error::InvalidArguments),
-    }
+pub fn solve_zk_challenge_s<G: CyclicGroup>(
+    group: &G,
+    x_secret: &G::Scalar,
+    k: &G::Scalar,
+    c: &G::Scalar,
+) -> G::Scalar {
+    group.scalar_sub(k, &group.scalar_mul(c, x_secret))
 }
 
 /// This function verifies that the challenge `s` was properly solved by the
-/// prover for integer cyclic groups.
+/// prover.
 /// r1 = g^s * y1^c && r2 = h^s * y2^c
 ///
+/// * `group` - the cyclic group where `g` and `h` are defined.
 /// * `r1` - g^k generated by the prover.
 /// * `r2` - h^k generated by the prover.
 /// * `y1` - g^x generated by the prover.
 /// * `y2` - h^x generated by the prover.
-/// * `g` - predefined element of the cyclic group.
-/// * `h` - predefined element of the cyclic group (g^n mod q ?).
 /// * `c` - random number generated by the verifier.
 /// * `s` - solution to the challenge computed by the prover.
-/// * `p` - the prime number used to defined the cyclic group.
-pub fn verify_scalar(
-    r1: &BigUint,
-    r2: &BigUint,
-    y1: &BigUint,
-    y2: &BigUint,
-    g: &BigUint,
-    h: &BigUint,
-    c: &BigUint,
-    s: &BigUint,
-    p: &BigUint,
+pub fn verify<G: CyclicGroup>(
+    group: &G,
+    r1: &G::Element,
+    r2: &G::Element,
+    y1: &G::Element,
+    y2: &G::Element,
+    c: &G::Scalar,
+    s: &G::Scalar,
 ) -> bool {
-    let condition_1 = *r1 == (g.modpow(s, p) * y1.modpow(c, p)).modpow(&BigUint::one(), p);
-    let condition_2 = *r2 == (h.modpow(s, p) * y2.modpow(c, p)).modpow(&BigUint::one(), p);
-    condition_1 && condition_2
-}
+    let g = group.generator();
+    let h = group.second_generator();
 
-/// This function verifies that the challenge `s` was properly solved by the
-/// prover for elliptic curves cyclic group (secp256k1).
-/// r1 = s * g + c * y1 && r2 = s * h + c * y2
-///
-/// * `r1x` - x coordinate of k * g generated by the prover.
-/// * `r1y` - y coordinate of k * g generated by the prover.
-/// * `r2x` - x coordinate of k * h generated by the prover.
-/// * `r2y` - y coordinate of k * h generated by the prover.
-/// * `y1x` - x coordinate of x * g generated by the prover.
-/// * `y1y` - y coordinate of x * g generated by the prover.
-/// * `y2x` - x coordinate of x * h generated by the prover.
-/// * `y2y` - x coordinate of x * h generated by the prover.
-/// * `gx` - x coordinate of predefined point of the cyclic group.
-/// * `gy` - y coordinate of predefined point of the cyclic group.
-/// * `hx` - x coordinate of predefined point of the cyclic group.
-/// * `hy` - y coordinate of predefined point of the cyclic group.
-/// * `c` - random number generated by the verifier.
-/// * `s` - solution to the challenge computed by the prover.
-/// * `p` - the prime number used to defined the cyclic group.
-pub fn verify_ecpoint(
-    r1x: &BigUint,
-    r1y: &BigUint,
-    r2x: &BigUint,
-    r2y: &BigUint,
-    y1x: &BigUint,
-    y1y: &BigUint,
-    y2x: &BigUint,
-    y2y: &BigUint,
-    gx: &BigUint,
-    gy: &BigUint,
-    hx: &BigUint,
-    hy: &BigUint,
-    c: &BigUint,
-    s: &BigUint,
-) -> bool {
-    let g = Secp256k1Point::from_bigint(&gx, &gy);
-    let h = Secp256k1Point::from_bigint(&hx, &hy);
-    let y1 = Secp256k1Point::from_bigint(&y1x, &y1y);
-    let y2 = Secp256k1Point::from_bigint(&y2x, &y2y);
-    let r1 = Secp256k1Point::from_bigint(&r1x, &r1y);
-    let r2 = Secp256k1Point::from_bigint(&r2x, &r2y);
-
-    let sg = g.scale(s.clone());
-    let sh = h.scale(s.clone());
-    let cy1 = y1.scale(c.clone());
-    let cy2 = y2.scale(c.clone());
-
-    (r1 == sg + cy1) && (r2 == sh + cy2)
+    let condition_1 = *r1 == group.multi_exponentiate(&[(g, s), (y1, c)]);
+    let condition_2 = *r2 == group.multi_exponentiate(&[(h, s), (y2, c)]);
+    condition_1 && condition_2
 }
 
 /// Generates a random array of bytes which can be use as a secret.
 ///
-/// Warning: Don't use it for production purposes. Better pseudo random
-/// generators should be used.
-pub fn get_random_array<const BYTES: usize>() -> [u8; BYTES] {
+/// * `rng` - the random generator, use `OsRng` for production purposes and a
+///   seeded generator only to reproduce a run.
+pub fn get_random_array<const BYTES: usize, R: RngCore + CryptoRng + ?Sized>(
+    rng: &mut R,
+) -> [u8; BYTES] {
     let mut arr = [0u8; BYTES];
-    thread_rng()
-        .try_fill(&mut arr[..])
+    rng.try_fill(&mut arr[..])
         .expect("Fail to generate array of random number.");
-    return arr;
+    arr
 }
 
 /// Generates a 32-bytes random number
-///
-/// Warning: Don't use it for production purposes.
-pub fn get_random_number() -> BigUint {
-    BigUint::from_bytes_be(&get_random_array::<32>())
+pub fn get_random_number<R: RngCore + CryptoRng + ?Sized>(rng: &mut R) -> BigUint {
+    BigUint::from_bytes_be(&get_random_array::<32, R>(rng))
+}
+
+/// Generates a random number uniformly distributed in [0, bound).
+pub fn get_random_number_below<R: RngCore + CryptoRng + ?Sized>(
+    bound: &BigUint,
+    rng: &mut R,
+) -> BigUint {
+    rng.gen_biguint_below(bound)
 }
 
 /// Generates a random string of any length. It is useful to generates user or
 /// session IDs.
-pub fn get_random_string(n: usize) -> String {
-    rand::thread_rng()
-        .sample_iter(&Alphanumeric)
+pub fn get_random_string<R: RngCore + CryptoRng + ?Sized>(n: usize, rng: &mut R) -> String {
+    rng.sample_iter(&Alphanumeric)
         .take(n)
         .map(char::from)
         .collect()
@@ -351,13 +322,17 @@ pub fn get_random_string(n: usize) -> String {
 mod tests {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
+    use num::traits::One;
+    use rand::rngs::StdRng;
+    use rand::{thread_rng, SeedableRng};
 
     #[test]
     fn test_get_random_array() {
-        let a = get_random_array::<32>();
-        let b = get_random_array::<32>();
-        let c = get_random_array::<32>();
-        let d = get_random_array::<32>();
+        let rng = &mut thread_rng();
+        let a = get_random_array::<32, _>(rng);
+        let b = get_random_array::<32, _>(rng);
+        let c = get_random_array::<32, _>(rng);
+        let d = get_random_array::<32, _>(rng);
         assert_ne!(a, b);
         assert_ne!(b, c);
         assert_ne!(c, d);
@@ -365,240 +340,286 @@ mod tests {
 
     #[test]
     fn test_get_random_number() {
-        let a = get_random_number();
-        let b = get_random_number();
-        let c = get_random_number();
-        let d = get_random_number();
+        let rng = &mut thread_rng();
+        let a = get_random_number(rng);
+        let b = get_random_number(rng);
+        let c = get_random_number(rng);
+        let d = get_random_number(rng);
         assert_ne!(a, b);
         assert_ne!(b, c);
         assert_ne!(c, d);
     }
 
+    #[test]
+    fn test_get_random_number_below() {
+        let bound = BigUint::from(5004u32);
+        for _ in 0..100 {
+            assert!(get_random_number_below(&bound, &mut thread_rng()) < bound);
+        }
+    }
+
+    #[test]
+    fn test_seeded_rng_is_reproducible() {
+        let mut a = StdRng::seed_from_u64(42);
+        let mut b = StdRng::seed_from_u64(42);
+
+        assert_eq!(get_random_string(10, &mut a), get_random_string(10, &mut b));
+        assert_eq!(get_random_number(&mut a), get_random_number(&mut b));
+
+        let group = ModPGroup::rfc3526_2048();
+        assert_eq!(group.random_scalar(&mut a), group.random_scalar(&mut b));
+
+        let mut c = StdRng::seed_from_u64(43);
+        assert_ne!(get_random_string(10, &mut a), get_random_string(10, &mut c));
+    }
+
+    #[test]
+    fn test_parse_group() {
+        assert_eq!("scalar".parse(), Ok(Group::Scalar));
+        assert_eq!("elliptic".parse(), Ok(Group::EllipticCurve));
+        assert_eq!("modp3072".parse(), Ok(Group::Modp3072));
+        assert_eq!("FFDHE2048".parse(), Ok(Group::Ffdhe2048));
+        assert_eq!("p256".parse(), Ok(Group::P256));
+        assert_eq!(" ristretto255 ".parse(), Ok(Group::Ristretto255));
+        assert!("p384".parse::<Group>().unwrap_err().contains("p256"));
+
+        for (group, name) in Group::ALL {
+            assert_eq!(group.to_string(), name);
+            assert_eq!(name.parse(), Ok(group));
+        }
+    }
+
+    #[test]
+    fn test_expand_group_flags() {
+        let args =
+            |args: &[&str]| -> Vec<String> { args.iter().map(|arg| arg.to_string()).collect() };
+
+        assert_eq!(
+            expand_group_flags(args(&["server", "--p256", "--port=50052"])),
+            args(&["server", "--group=p256", "--port=50052"])
+        );
+        assert_eq!(
+            expand_group_flags(args(&["server", "--group", "elliptic", "--scalars"])),
+            args(&["server", "--group", "elliptic", "--scalars"])
+        );
+    }
+
+    #[test]
+    fn test_verify_modp_2048_success() {
+        let group = ModPGroup::rfc3526_2048();
+
+        let x = group.random_scalar(&mut thread_rng());
+        let (y1, y2) = exponentiates_points(&group, &x);
+
+        let k = group.random_scalar(&mut thread_rng());
+        let (r1, r2) = exponentiates_points(&group, &k);
+
+        let c = group.random_scalar(&mut thread_rng());
+
+        let s = solve_zk_challenge_s(&group, &x, &k, &c);
+
+        assert!(verify(&group, &r1, &r2, &y1, &y2, &c, &s));
+
+        let s = group.scalar_add(&s, &BigUint::one());
+        assert!(!verify(&group, &r1, &r2, &y1, &y2, &c, &s));
+    }
+
     #[test]
     fn test_exponentiates_points_scalar() {
-        let p = BigUint::from(10009u32);
-        let g = BigUint::from(3u32);
-        let h = BigUint::from(2892u32);
+        let group = ModPGroup::default();
 
         let secret = BigUint::from(300u32);
 
-        let (y1, y2) = exponentiates_points_scalar(&secret, &g, &h, &p);
+        let (y1, y2) = exponentiates_points(&group, &secret);
 
-        assert_eq!(y1, Point::Scalar(BigUint::from(6419u32)));
-        assert_eq!(y2, Point::Scalar(BigUint::from(4984u32)));
+        assert_eq!(y1, BigUint::from(6419u32));
+        assert_eq!(y2, group.h.modpow(&secret, &group.p));
+    }
+
+    fn check_multi_exponentiate<G: CyclicGroup>(group: &G) {
+        let rng = &mut thread_rng();
+        let g = group.generator();
+        let h = group.second_generator();
+        let a = group.random_scalar(rng);
+        let b = group.random_scalar(rng);
+        let zero = G::Scalar::from(0);
+
+        assert_eq!(group.multi_exponentiate(&[]), group.identity());
+        assert_eq!(group.multi_exponentiate(&[(g, &zero)]), group.identity());
+        assert_eq!(
+            group.multi_exponentiate(&[(g, &a)]),
+            group.exponentiate(g, &a)
+        );
+        assert_eq!(
+            group.multi_exponentiate(&[(g, &a), (h, &b)]),
+            group.operate(&group.exponentiate(g, &a), &group.exponentiate(h, &b))
+        );
+    }
+
+    #[test]
+    fn test_multi_exponentiate() {
+        check_multi_exponentiate(&ModPGroup::default());
+        check_multi_exponentiate(&ModPGroup::rfc3526_2048());
+        check_multi_exponentiate(&Secp256k1Group::default());
+        check_multi_exponentiate(&P256Group::default());
+        check_multi_exponentiate(&Ristretto255Group::default());
+    }
+
+    #[test]
+    fn test_check_second_generator() {
+        assert!(check_second_generator(&ModPGroup::default()));
+        assert!(check_second_generator(&ModPGroup::rfc3526_2048()));
+        assert!(check_second_generator(&ModPGroup::ffdhe4096()));
+        assert!(check_second_generator(&Secp256k1Group::default()));
+
+        // the old h = g^13 is rejected
+        let toy = ModPGroup::default();
+        let group = ModPGroup::new(toy.p, toy.q, toy.g, BigUint::from(2892u32));
+        assert!(!check_second_generator(&group));
+
+        let g = secp256k1::Secp256k1Point::generator();
+        let group = Secp256k1Group::new(g, g.scale(&13.into()));
+        assert!(!check_second_generator(&group));
     }
 
     #[test]
     fn test_solve_zk_challenge_s() {
+        // only the order q = 10 is used
+        let group = ModPGroup::new(
+            BigUint::from(11u32),
+            BigUint::from(10u32),
+            BigUint::from(2u32),
+            BigUint::from(2u32),
+        );
+
         // test positive k - cx
         let x = BigUint::from(3u32);
         let c = BigUint::from(3u32);
         let k = BigUint::from(10u32);
-        let q = BigUint::from(10u32);
 
         // s = 10 - 3 * 3 mod 10 = 1
-        assert_eq!(solve_zk_challenge_s(&x, &k, &c, &q), BigUint::one());
+        assert_eq!(solve_zk_challenge_s(&group, &x, &k, &c), BigUint::one());
 
         // test negative k - cx
         let x = BigUint::from(4u32);
         let c = BigUint::from(3u32);
         let k = BigUint::from(10u32);
-        let q = BigUint::from(10u32);
 
         // s = 10 - 3 * 4 mod 10 = 8
-        assert_eq!(solve_zk_challenge_s(&x, &k, &c, &q), BigUint::from(8u32));
+        assert_eq!(
+            solve_zk_challenge_s(&group, &x, &k, &c),
+            BigUint::from(8u32)
+        );
     }
 
     #[test]
     fn test_verify_scalar_success_toy_example_1() {
-        let p = BigUint::from(10009u32);
-        let q = (&p - BigUint::one()) / BigUint::from(2u32);
+        let group = ModPGroup::default();
 
         let x = BigUint::from(300u32);
-        let g = Point::Scalar(BigUint::from(3u32));
-        let h = Point::Scalar(BigUint::from(2892u32));
-
-        let (y1, y2) = exponentiates_points(&x, &g, &h, &p).unwrap();
+        let (y1, y2) = exponentiates_points(&group, &x);
 
         let k = BigUint::from(10u32);
-        let (r1, r2) = exponentiates_points(&k, &g, &h, &p).unwrap();
+        let (r1, r2) = exponentiates_points(&group, &k);
 
         let c = BigUint::from(894u32);
 
-        let s = solve_zk_challenge_s(&x, &k, &c, &q);
+        let s = solve_zk_challenge_s(&group, &x, &k, &c);
 
-        let verification = verify(&r1, &r2, &y1, &y2, &g, &h, &c, &s, &p).unwrap();
-        assert!(verification)
+        assert!(verify(&group, &r1, &r2, &y1, &y2, &c, &s))
     }
 
     #[test]
     fn test_verify_scalar_success_toy_example_2() {
-        let p = BigUint::from(23u32);
-        let q = BigUint::from(11u32);
+        let group = ModPGroup::new(
+            BigUint::from(23u32),
+            BigUint::from(11u32),
+            BigUint::from(4u32),
+            BigUint::from(9u32),
+        );
 
         let x = BigUint::from(6u32);
-        let g = Point::Scalar(BigUint::from(4u32));
-        let h = Point::Scalar(BigUint::from(9u32));
+        let (y1, y2) = exponentiates_points(&group, &x);
 
-        let (y1, y2) = exponentiates_points(&x, &g, &h, &p).unwrap();
-
-        assert_eq!(y1, Point::Scalar(BigUint::from(2u32)));
-        assert_eq!(y2, Point::Scalar(BigUint::from(3u32)));
+        assert_eq!(y1, BigUint::from(2u32));
+        assert_eq!(y2, BigUint::from(3u32));
 
         let k = BigUint::from(7u32);
-        let (r1, r2) = exponentiates_points(&k, &g, &h, &p).unwrap();
+        let (r1, r2) = exponentiates_points(&group, &k);
 
-        assert_eq!(r1, Point::Scalar(BigUint::from(8u32)));
-        assert_eq!(r2, Point::Scalar(BigUint::from(4u32)));
+        assert_eq!(r1, BigUint::from(8u32));
+        assert_eq!(r2, BigUint::from(4u32));
 
         let c = BigUint::from(4u32);
 
-        let s = solve_zk_challenge_s(&x, &k, &c, &q);
+        let s = solve_zk_challenge_s(&group, &x, &k, &c);
         assert_eq!(s, BigUint::from(5u32));
 
-        let verification = verify(&r1, &r2, &y1, &y2, &g, &h, &c, &s, &p).unwrap();
-        assert!(verification)
+        assert!(verify(&group, &r1, &r2, &y1, &y2, &c, &s))
     }
 
     #[test]
     fn test_verify_scalar_failure_toy_example_1() {
-        let p = BigUint::from(23u32);
-        let q = BigUint::from(11u32);
+        let group = ModPGroup::new(
+            BigUint::from(23u32),
+            BigUint::from(11u32),
+            BigUint::from(4u32),
+            BigUint::from(9u32),
+        );
 
         let x = BigUint::from(6u32);
+        let (y1, y2) = exponentiates_points(&group, &x);
 
-        let g = Point::Scalar(BigUint::from(4u32));
-        let h = Point::Scalar(BigUint::from(9u32));
-
-        let (y1, y2) = exponentiates_points(&x, &g, &h, &p).unwrap();
-        assert_eq!(y1, Point::Scalar(BigUint::from(2u32)));
-        assert_eq!(y2, Point::Scalar(BigUint::from(3u32)));
+        assert_eq!(y1, BigUint::from(2u32));
+        assert_eq!(y2, BigUint::from(3u32));
 
         let k = BigUint::from(7u32);
-        let (r1, r2) = exponentiates_points(&k, &g, &h, &p).unwrap();
+        let (r1, r2) = exponentiates_points(&group, &k);
 
-        assert_eq!(r1, Point::Scalar(BigUint::from(8u32)));
-        assert_eq!(r2, Point::Scalar(BigUint::from(4u32)));
+        assert_eq!(r1, BigUint::from(8u32));
+        assert_eq!(r2, BigUint::from(4u32));
 
         let c = BigUint::from(4u32);
 
-        let mut s = solve_zk_challenge_s(&x, &k, &c, &q);
+        let mut s = solve_zk_challenge_s(&group, &x, &k, &c);
 
         // we compute `s` slightly bad
-        s = s - BigUint::one();
+        s -= BigUint::one();
 
-        let verification = verify(&r1, &r2, &y1, &y2, &g, &h, &c, &s, &p).unwrap();
-        assert!(!verification)
+        assert!(!verify(&group, &r1, &r2, &y1, &y2, &c, &s))
     }
 
     #[test]
     fn test_verify_elliptic_curve_success_example_1() {
-        let p = Secp256k1Point::prime();
-        let q = Secp256k1Point::n();
-
-        let x = BigUint::from(300u32);
-        let g = Secp256k1Point::generator();
-        let h = g.clone().scale(BigUint::from(13u32));
+        let group = Secp256k1Group::default();
 
-        let g = Point::from_secp256k1(&g);
-        let h = Point::from_secp256k1(&h);
-        let (y1, y2) = exponentiates_points(&x, &g, &h, &p).unwrap();
+        let x = secp256k1::Scalar::from(300);
+        let (y1, y2) = exponentiates_points(&group, &x);
 
-        let k = BigUint::from(10u32);
-        let (r1, r2) = exponentiates_points(&k, &g, &h, &p).unwrap();
+        let k = secp256k1::Scalar::from(10);
+        let (r1, r2) = exponentiates_points(&group, &k);
 
-        let c = BigUint::from(894u32);
+        let c = secp256k1::Scalar::from(894);
 
-        let s = solve_zk_challenge_s(&x, &k, &c, &q);
+        let s = solve_zk_challenge_s(&group, &x, &k, &c);
 
-        let verification = verify(&r1, &r2, &y1, &y2, &g, &h, &c, &s, &p).unwrap();
-        assert!(verification)
+        assert!(verify(&group, &r1, &r2, &y1, &y2, &c, &s))
     }
 
     #[test]
     fn test_verify_elliptic_curve_failure_example_1() {
-        let p = Secp256k1Point::prime();
-        let q = Secp256k1Point::n();
-
-        let x = BigUint::from(300u32);
-        let g = Secp256k1Point::generator();
-        let h = g.clone().scale(BigUint::from(13u32));
-
-        let g = Point::from_secp256k1(&g);
-        let h = Point::from_secp256k1(&h);
-        let (y1, y2) = exponentiates_points(&x, &g, &h, &p).unwrap();
-
-        let k = BigUint::from(10u32);
-        let (r1, r2) = exponentiates_points(&k, &g, &h, &p).unwrap();
-
-        let c = BigUint::from(894u32);
+        let group = Secp256k1Group::default();
 
-        let s = solve_zk_challenge_s(&x, &k, &c, &q) + BigUint::one();
+        let x = secp256k1::Scalar::from(300);
+        let (y1, y2) = exponentiates_points(&group, &x);
 
-        let verification = verify(&r1, &r2, &y1, &y2, &g, &h, &c, &s, &p).unwrap();
-        assert!(!verification)
-    }
-
-    #[test]
-    fn test_serialize() {
-        let p = Point::Scalar(BigUint::from(65256u32));
-
-        assert_eq!(p.serialize(), vec![0xfe, 0xe8]);
-
-        let p = Point::ECPoint(BigUint::from(65256u32), BigUint::from(8475u32));
+        let k = secp256k1::Scalar::from(10);
+        let (r1, r2) = exponentiates_points(&group, &k);
 
-        assert_eq!(p.serialize(), vec![0xfe, 0xe8, 0x21, 0x1b]);
+        let c = secp256k1::Scalar::from(894);
 
-        // one array is longer than the other
-        let p = Point::ECPoint(BigUint::from(65256u32), BigUint::from(83957234u32));
+        let s = solve_zk_challenge_s(&group, &x, &k, &c) + secp256k1::Scalar::ONE;
 
-        assert_eq!(
-            p.serialize(),
-            vec![0x00, 0x00, 0xfe, 0xe8, 0x05, 0x01, 0x15, 0xf2]
-        );
-
-        // the other way around
-        let p = Point::ECPoint(BigUint::from(83957234u32), BigUint::from(65256u32));
-
-        assert_eq!(
-            p.serialize(),
-            vec![0x05, 0x01, 0x15, 0xf2, 0x00, 0x00, 0xfe, 0xe8]
-        );
-    }
-
-    #[test]
-    fn test_deserialize() {
-        let p = Point::deserialize(vec![0xfe, 0xe8], &Group::Scalar);
-
-        assert_eq!(p, Point::Scalar(BigUint::from(65256u32)));
-
-        let p = Point::deserialize(vec![0xfe, 0xe8, 0x21, 0x1b], &Group::EllipticCurve);
-
-        assert_eq!(
-            p,
-            Point::ECPoint(BigUint::from(65256u32), BigUint::from(8475u32))
-        );
-
-        // one array is longer than the other
-        let p = Point::deserialize(
-            vec![0x00, 0x00, 0xfe, 0xe8, 0x05, 0x01, 0x15, 0xf2],
-            &Group::EllipticCurve,
-        );
-
-        assert_eq!(
-            p,
-            Point::ECPoint(BigUint::from(65256u32), BigUint::from(83957234u32))
-        );
-
-        // the other way around
-        let p = Point::deserialize(
-            vec![0x05, 0x01, 0x15, 0xf2, 0x00, 0x00, 0xfe, 0xe8],
-            &Group::EllipticCurve,
-        );
-
-        assert_eq!(
-            p,
-            Point::ECPoint(BigUint::from(83957234u32), BigUint::from(65256u32))
-        );
+        assert!(!verify(&group, &r1, &r2, &y1, &y2, &c, &s))
     }
 }
@@ -0,0 +1,159 @@
+//! Multiplication modulo an odd number in the Montgomery form.
+//!
+//! A number a mod p is represented by a * R mod p with R = 2^(64 * n), n the
+//! number of 64 bits limbs of p, so reducing a product only needs
+//! multiplications and shifts instead of a division. The product uses the
+//! CIOS method: the reduction is interleaved with the multiplication limb by
+//! limb.
+
+use num::traits::Zero;
+use num_bigint::BigUint;
+
+/// The constants of the Montgomery form for a modulus.
+#[derive(Debug, Clone)]
+pub(crate) struct Montgomery {
+    modulus: BigUint,
+    /// Limbs of the modulus, least significant first.
+    limbs: Vec<u64>,
+    /// -p^-1 mod 2^64.
+    inv: u64,
+    /// R^2 mod p, converts a number into the Montgomery form.
+    r2: Vec<u64>,
+}
+
+impl Montgomery {
+    /// `modulus` must be odd.
+    pub(crate) fn new(modulus: &BigUint) -> Self {
+        assert!(modulus.bit(0), "Montgomery modulus must be odd");
+        let limbs = modulus.to_u64_digits();
+        let n = limbs.len();
+
+        // p * inv = 1 mod 2^k implies p * inv' = 1 mod 2^2k for
+        // inv' = inv * (2 - p * inv), 6 steps reach 2^64 from 2^1
+        let mut inv = 1u64;
+        for _ in 0..6 {
+            inv = inv.wrapping_mul(2u64.wrapping_sub(limbs[0].wrapping_mul(inv)));
+        }
+
+        let r2 = (BigUint::from(1u32) << (128 * n)) % modulus;
+        Montgomery {
+            modulus: modulus.clone(),
+            r2: pad(r2.to_u64_digits(), n),
+            limbs,
+            inv: inv.wrapping_neg(),
+        }
+    }
+
+    /// Converts `a` into the Montgomery form a * R mod p.
+    pub(crate) fn to_montgomery(&self, a: &BigUint) -> Vec<u64> {
+        let a = pad((a % &self.modulus).to_u64_digits(), self.limbs.len());
+        self.mul(&a, &self.r2)
+    }
+
+    /// Converts `a` back from the Montgomery form.
+    pub(crate) fn to_biguint(&self, a: &[u64]) -> BigUint {
+        let one = pad(vec![1], self.limbs.len());
+        self.mul(a, &one)
+            .iter()
+            .rev()
+            .fold(BigUint::zero(), |number, limb| (number << 64u32) + *limb)
+    }
+
+    /// a * b * R^-1 mod p, for a and b lower than p.
+    pub(crate) fn mul(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        let p = &self.limbs;
+        let n = p.len();
+        let mut t = vec![0u64; n + 2];
+
+        for &b_i in b {
+            // t += a * b_i
+            let mut carry = 0u64;
+            for j in 0..n {
+                let sum = t[j] as u128 + a[j] as u128 * b_i as u128 + carry as u128;
+                t[j] = sum as u64;
+                carry = (sum >> 64) as u64;
+            }
+            let sum = t[n] as u128 + carry as u128;
+            t[n] = sum as u64;
+            t[n + 1] = (sum >> 64) as u64;
+
+            // t = (t + m * p) / 2^64, m chosen so the lowest limb is zero
+            let m = t[0].wrapping_mul(self.inv);
+            let sum = t[0] as u128 + m as u128 * p[0] as u128;
+            let mut carry = (sum >> 64) as u64;
+            for j in 1..n {
+                let sum = t[j] as u128 + m as u128 * p[j] as u128 + carry as u128;
+                t[j - 1] = sum as u64;
+                carry = (sum >> 64) as u64;
+            }
+            let sum = t[n] as u128 + carry as u128;
+            t[n - 1] = sum as u64;
+            t[n] = t[n + 1] + (sum >> 64) as u64;
+        }
+
+        // t < 2p
+        if t[n] != 0 || !less_than(&t[..n], p) {
+            let mut borrow = 0u64;
+            for j in 0..n {
+                let (diff, borrow_1) = t[j].overflowing_sub(p[j]);
+                let (diff, borrow_2) = diff.overflowing_sub(borrow);
+                t[j] = diff;
+                borrow = u64::from(borrow_1 || borrow_2);
+            }
+        }
+
+        t.truncate(n);
+        t
+    }
+}
+
+/// Left-pads the little endian `limbs` with zeros to `n` limbs.
+fn pad(mut limbs: Vec<u64>, n: usize) -> Vec<u64> {
+    limbs.resize(n, 0);
+    limbs
+}
+
+/// a < b for little endian numbers of the same length.
+fn less_than(a: &[u64], b: &[u64]) -> bool {
+    for (a, b) in a.iter().rev().zip(b.iter().rev()) {
+        if a != b {
+            return a < b;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::RandBigInt;
+    use rand::thread_rng;
+
+    #[test]
+    fn test_mul() {
+        let rng = &mut thread_rng();
+        for modulus in [
+            BigUint::from(10009u32),
+            BigUint::from(u64::MAX),
+            (BigUint::from(1u32) << 255u32) - 19u32,
+            rng.gen_biguint(2048) | BigUint::from(1u32),
+        ] {
+            let montgomery = Montgomery::new(&modulus);
+            for _ in 0..20 {
+                let a = rng.gen_biguint_below(&modulus);
+                let b = rng.gen_biguint_below(&modulus);
+                let product =
+                    montgomery.mul(&montgomery.to_montgomery(&a), &montgomery.to_montgomery(&b));
+                assert_eq!(montgomery.to_biguint(&product), (&a * &b) % &modulus);
+            }
+
+            let max = &modulus - 1u32;
+            let max_m = montgomery.to_montgomery(&max);
+            assert_eq!(montgomery.to_biguint(&max_m), max);
+            assert_eq!(
+                montgomery.to_biguint(&montgomery.mul(&max_m, &max_m)),
+                BigUint::from(1u32)
+            );
+        }
+    }
+}
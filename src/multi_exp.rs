@@ -0,0 +1,115 @@
+//! Multi-exponentiation with Straus' method (also known as Shamir's trick).
+//!
+//! b1^e1 * b2^e2 * ... is computed in a single pass over the bits of the
+//! exponents: the squarings are shared by all the terms and every base is
+//! multiplied by one of its precomputed powers b^1 .. b^15 once per window of
+//! 4 bits of its exponent. For the two terms of a verification it costs about
+//! half of two separate exponentiations.
+
+/// Bits of the windows, the table of every base holds 2^WINDOW - 1 powers.
+const WINDOW: usize = 4;
+
+/// Computes the product of the `terms` (base, exponent).
+///
+/// * `terms` - the bases with their exponents in big endian.
+/// * `one` - the identity of the group, returned for no terms.
+/// * `mul` - the group operation.
+/// * `square` - x * x, usually faster than `mul(x, x)`.
+pub(crate) fn straus<E: Clone>(
+    terms: &[(E, Vec<u8>)],
+    one: E,
+    mul: impl Fn(&E, &E) -> E,
+    square: impl Fn(&E) -> E,
+) -> E {
+    let len = terms.iter().map(|(_, exp)| exp.len()).max().unwrap_or(0);
+
+    let tables: Vec<Vec<E>> = terms
+        .iter()
+        .map(|(base, _)| {
+            let mut table = vec![base.clone()];
+            for i in 1..(1 << WINDOW) - 1 {
+                let power = mul(&table[i - 1], base);
+                table.push(power);
+            }
+            table
+        })
+        .collect();
+
+    // None until the first multiplication, squaring the identity is useless
+    let mut product: Option<E> = None;
+    for window in 0..2 * len {
+        if let Some(value) = product.as_mut() {
+            for _ in 0..WINDOW {
+                *value = square(value);
+            }
+        }
+
+        for ((_, exp), table) in terms.iter().zip(&tables) {
+            let digit = nibble(exp, len, window);
+            if digit != 0 {
+                let power = &table[digit - 1];
+                product = Some(match &product {
+                    Some(value) => mul(value, power),
+                    None => power.clone(),
+                });
+            }
+        }
+    }
+
+    product.unwrap_or(one)
+}
+
+/// The `window`-th group of 4 bits, from the most significant, of `exp`
+/// left-padded with zeros to `len` bytes.
+fn nibble(exp: &[u8], len: usize, window: usize) -> usize {
+    let index = window / 2;
+    let padding = len - exp.len();
+    if index < padding {
+        return 0;
+    }
+
+    let byte = exp[index - padding] as usize;
+    if window.is_multiple_of(2) {
+        byte >> 4
+    } else {
+        byte & 0x0f
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::traits::One;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_straus() {
+        let p = BigUint::from(10009u32);
+        let mul = |a: &BigUint, b: &BigUint| (a * b) % &p;
+        let square = |a: &BigUint| (a * a) % &p;
+
+        let product = |terms: &[(u32, u32)]| {
+            let terms: Vec<(BigUint, Vec<u8>)> = terms
+                .iter()
+                .map(|(base, exp)| (BigUint::from(*base), BigUint::from(*exp).to_bytes_be()))
+                .collect();
+            straus(&terms, BigUint::one(), mul, square)
+        };
+        let expected = |terms: &[(u32, u32)]| {
+            terms.iter().fold(BigUint::one(), |product, (base, exp)| {
+                (product * BigUint::from(*base).modpow(&BigUint::from(*exp), &p)) % &p
+            })
+        };
+
+        for terms in [
+            vec![],
+            vec![(2, 0)],
+            vec![(2, 1)],
+            vec![(3, 300)],
+            vec![(2, 5003), (3, 65535)],
+            vec![(2, 16), (6419, 0x0100), (4, 4_000_000_000)],
+        ] {
+            assert_eq!(product(&terms), expected(&terms), "{:?}", terms);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Derivation of the secret `x` from a password the user can remember.
+//!
+//! The password is stretched with the memory-hard Argon2id function and a
+//! random per-user salt, and the output is reduced mod q:
+//!
+//! x = Argon2id(password, salt) mod q
+//!
+//! The salt is public: it is sent to the server at registration and returned
+//! by the `GetSalt` RPC before every login.
+
+use crate::{get_random_array, CyclicGroup, Error};
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::{CryptoRng, RngCore};
+
+/// Length in bytes of the salts generated by `generate_salt`.
+pub const SALT_LEN: usize = 16;
+
+/// Minimum length in bytes of a salt accepted by `derive_secret`.
+pub const MIN_SALT_LEN: usize = argon2::MIN_SALT_LEN;
+
+/// Cost parameters of Argon2id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    /// Memory size in KiB.
+    pub memory_kib: u32,
+    /// Number of passes over the memory.
+    pub iterations: u32,
+    /// Degree of parallelism.
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    /// The parameters recommended by OWASP: 19 MiB, 2 iterations and 1
+    /// degree of parallelism.
+    fn default() -> Self {
+        KdfParams {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+/// Generates a new random salt for a user.
+pub fn generate_salt<R: RngCore + CryptoRng + ?Sized>(rng: &mut R) -> [u8; SALT_LEN] {
+    get_random_array::<SALT_LEN, R>(rng)
+}
+
+/// Derives the secret `x` of a user from its password.
+///
+/// Argon2id is asked for 16 bytes more than the size of `q` so the reduction
+/// mod q is unbiased for every group.
+///
+/// * `group` - the cyclic group, `q` is its order.
+/// * `password` - the password typed by the user.
+/// * `salt` - the salt of the user, at least `MIN_SALT_LEN` bytes.
+/// * `params` - the cost parameters of Argon2id.
+pub fn derive_secret<G: CyclicGroup>(
+    group: &G,
+    password: &[u8],
+    salt: &[u8],
+    params: &KdfParams,
+) -> Result<G::Scalar, Error> {
+    if salt.len() < MIN_SALT_LEN {
+        return Err(Error::InvalidLength);
+    }
+
+    let params = Params::new(
+        params.memory_kib,
+        params.iterations,
+        params.parallelism,
+        None,
+    )
+    .map_err(|_| Error::InvalidArguments)?;
+
+    let mut output = vec![0u8; group.scalar_len() + 16];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password, salt, &mut output)
+        .map_err(|_| Error::InvalidArguments)?;
+
+    Ok(group.scalar_from_bytes(&output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ModPGroup, P256Group};
+    use rand::thread_rng;
+
+    // cheap parameters so the tests run fast
+    const TEST_PARAMS: KdfParams = KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    #[test]
+    fn test_derive_secret() {
+        let group = P256Group::default();
+        let salt = generate_salt(&mut thread_rng());
+
+        let x = derive_secret(&group, b"correct horse", &salt, &TEST_PARAMS).unwrap();
+
+        // the same password and salt always give the same secret
+        assert_eq!(
+            derive_secret(&group, b"correct horse", &salt, &TEST_PARAMS).unwrap(),
+            x
+        );
+        assert_ne!(
+            derive_secret(&group, b"battery staple", &salt, &TEST_PARAMS).unwrap(),
+            x
+        );
+
+        let other_salt = generate_salt(&mut thread_rng());
+        assert_ne!(other_salt, salt);
+        assert_ne!(
+            derive_secret(&group, b"correct horse", &other_salt, &TEST_PARAMS).unwrap(),
+            x
+        );
+    }
+
+    #[test]
+    fn test_derive_secret_reduced_mod_q() {
+        let group = ModPGroup::default();
+
+        for password in [&b"a"[..], b"b", b"c", b"d"] {
+            let x = derive_secret(&group, password, &[0u8; SALT_LEN], &TEST_PARAMS).unwrap();
+            assert!(x < group.q);
+        }
+    }
+
+    #[test]
+    fn test_derive_secret_invalid() {
+        let group = ModPGroup::default();
+
+        assert_eq!(
+            derive_secret(&group, b"password", &[0u8; 4], &TEST_PARAMS),
+            Err(Error::InvalidLength)
+        );
+
+        let params = KdfParams {
+            parallelism: 0,
+            ..TEST_PARAMS
+        };
+        assert_eq!(
+            derive_secret(&group, b"password", &[0u8; SALT_LEN], &params),
+            Err(Error::InvalidArguments)
+        );
+    }
+}
@@ -0,0 +1,494 @@
+//! Typed values and state machines of the interactive Chaum-Pedersen
+//! protocol, independent of the transport.
+//!
+//! ```text
+//! Prover                                   Verifier
+//! Prover::new(witness x)
+//!   statement (g, h, y1, y2) ------------> Verifier::new(group, statement)
+//! Prover::commit
+//!   commitment (r1, r2) -----------------> Verifier::challenge
+//!                       <----------------- challenge c
+//! CommittedProver::respond
+//!   response s --------------------------> ChallengedVerifier::verify
+//! ```
+//!
+//! Every step consumes the previous state, and the committed prover can't be
+//! cloned, so a nonce `k` can't answer two challenges, which would reveal x.
+//! The states only hold values, the group is passed to every step, so they
+//! can be stored between two requests.
+//!
+//! Every value has a canonical byte encoding: the scalars take `scalar_len`
+//! bytes and the elements are prefixed with their length as 2 bytes big
+//! endian. Decoding validates the values and rejects any other encoding.
+
+use std::fmt;
+
+use rand::{CryptoRng, RngCore};
+
+use crate::batch::Transcript;
+use crate::{solve_zk_challenge_s, CyclicGroup, Error};
+
+/// Appends `element` prefixed with the length of its encoding.
+fn put_element<G: CyclicGroup>(group: &G, element: &G::Element, bytes: &mut Vec<u8>) {
+    let encoded = group.encode(element);
+    let len = u16::try_from(encoded.len()).expect("Element encoding too long");
+    bytes.extend(len.to_be_bytes());
+    bytes.extend(encoded);
+}
+
+/// Reads exactly `N` elements serialized with `put_element`.
+fn read_elements<G: CyclicGroup, const N: usize>(
+    group: &G,
+    mut bytes: &[u8],
+) -> Result<[G::Element; N], Error> {
+    let mut elements = Vec::with_capacity(N);
+    while !bytes.is_empty() {
+        if bytes.len() < 2 {
+            return Err(Error::InvalidLength);
+        }
+        let len = u16::from_be_bytes([bytes[0], bytes[1]]) as usize;
+        let encoded = bytes.get(2..2 + len).ok_or(Error::InvalidLength)?;
+
+        let element = group.decode(encoded)?;
+        if group.encode(&element) != encoded {
+            return Err(Error::NonCanonical);
+        }
+        elements.push(element);
+        bytes = &bytes[2 + len..];
+    }
+    elements.try_into().map_err(|_| Error::InvalidLength)
+}
+
+/// The public claim of the prover: y1 = g^x and y2 = h^x for the same x.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement<G: CyclicGroup> {
+    pub g: G::Element,
+    pub h: G::Element,
+    pub y1: G::Element,
+    pub y2: G::Element,
+}
+
+impl<G: CyclicGroup> Statement<G> {
+    /// The statement about the public keys `y1` and `y2` of a user with the
+    /// generators of `group`.
+    pub fn new(group: &G, y1: G::Element, y2: G::Element) -> Self {
+        Statement {
+            g: group.generator().clone(),
+            h: group.second_generator().clone(),
+            y1,
+            y2,
+        }
+    }
+
+    /// Serializes the statement as `g || h || y1 || y2`.
+    pub fn to_bytes(&self, group: &G) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for element in [&self.g, &self.h, &self.y1, &self.y2] {
+            put_element(group, element, &mut bytes);
+        }
+        bytes
+    }
+
+    /// Deserializes a statement serialized with `to_bytes`, its `g` and `h`
+    /// must be the generators of `group`.
+    pub fn from_bytes(group: &G, bytes: &[u8]) -> Result<Self, Error> {
+        let [g, h, y1, y2] = read_elements(group, bytes)?;
+        let statement = Statement { g, h, y1, y2 };
+        statement.check_generators(group)?;
+        Ok(statement)
+    }
+
+    /// Checks that `g` and `h` are the generators of `group`. A prover free to
+    /// pick them could send g = y1 and h = y2 and answer any challenge
+    /// without knowing x.
+    fn check_generators(&self, group: &G) -> Result<(), Error> {
+        if self.g != *group.generator() || self.h != *group.second_generator() {
+            return Err(Error::WrongGenerators);
+        }
+        Ok(())
+    }
+}
+
+/// The secret of the prover, the x of the statement.
+#[derive(Clone, PartialEq)]
+pub struct Witness<G: CyclicGroup> {
+    x: G::Scalar,
+}
+
+impl<G: CyclicGroup> Witness<G> {
+    pub fn new(x: G::Scalar) -> Self {
+        Witness { x }
+    }
+
+    /// Serializes the secret in `scalar_len` bytes.
+    pub fn to_bytes(&self, group: &G) -> Vec<u8> {
+        group.scalar_to_bytes(&self.x)
+    }
+
+    /// Deserializes a secret serialized with `to_bytes`.
+    pub fn from_bytes(group: &G, bytes: &[u8]) -> Result<Self, Error> {
+        Ok(Witness {
+            x: group.decode_scalar(bytes)?,
+        })
+    }
+}
+
+impl<G: CyclicGroup> fmt::Debug for Witness<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Witness(..)")
+    }
+}
+
+/// The first message of the prover: r1 = g^k and r2 = h^k for a random k.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Commitment<G: CyclicGroup> {
+    pub r1: G::Element,
+    pub r2: G::Element,
+}
+
+impl<G: CyclicGroup> Commitment<G> {
+    /// Serializes the commitment as `r1 || r2`.
+    pub fn to_bytes(&self, group: &G) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        put_element(group, &self.r1, &mut bytes);
+        put_element(group, &self.r2, &mut bytes);
+        bytes
+    }
+
+    /// Deserializes a commitment serialized with `to_bytes`.
+    pub fn from_bytes(group: &G, bytes: &[u8]) -> Result<Self, Error> {
+        let [r1, r2] = read_elements(group, bytes)?;
+        Ok(Commitment { r1, r2 })
+    }
+}
+
+/// The random challenge c chosen by the verifier.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Challenge<G: CyclicGroup> {
+    pub c: G::Scalar,
+}
+
+impl<G: CyclicGroup> Challenge<G> {
+    /// Serializes the challenge in `scalar_len` bytes.
+    pub fn to_bytes(&self, group: &G) -> Vec<u8> {
+        group.scalar_to_bytes(&self.c)
+    }
+
+    /// Deserializes a challenge serialized with `to_bytes`.
+    pub fn from_bytes(group: &G, bytes: &[u8]) -> Result<Self, Error> {
+        Ok(Challenge {
+            c: group.decode_scalar(bytes)?,
+        })
+    }
+}
+
+/// The answer of the prover: s = (k - c * x) mod q.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response<G: CyclicGroup> {
+    pub s: G::Scalar,
+}
+
+impl<G: CyclicGroup> Response<G> {
+    /// Serializes the response in `scalar_len` bytes.
+    pub fn to_bytes(&self, group: &G) -> Vec<u8> {
+        group.scalar_to_bytes(&self.s)
+    }
+
+    /// Deserializes a response serialized with `to_bytes`.
+    pub fn from_bytes(group: &G, bytes: &[u8]) -> Result<Self, Error> {
+        Ok(Response {
+            s: group.decode_scalar(bytes)?,
+        })
+    }
+}
+
+/// The prover before the commitment.
+#[derive(Debug, Clone)]
+pub struct Prover<G: CyclicGroup> {
+    statement: Statement<G>,
+    witness: Witness<G>,
+}
+
+impl<G: CyclicGroup> Prover<G> {
+    /// Computes the statement of `witness` with the generators of `group`.
+    pub fn new(group: &G, witness: Witness<G>) -> Self {
+        let statement = Statement::new(
+            group,
+            group.exponentiate_secret(group.generator(), &witness.x),
+            group.exponentiate_secret(group.second_generator(), &witness.x),
+        );
+        Prover { statement, witness }
+    }
+
+    /// The statement to send to the verifier.
+    pub fn statement(&self) -> &Statement<G> {
+        &self.statement
+    }
+
+    /// Picks the random nonce k and returns the commitment to send to the
+    /// verifier.
+    pub fn commit<R: RngCore + CryptoRng + ?Sized>(
+        self,
+        group: &G,
+        rng: &mut R,
+    ) -> (CommittedProver<G>, Commitment<G>) {
+        let k = group.random_scalar(rng);
+        let commitment = Commitment {
+            r1: group.exponentiate_secret(&self.statement.g, &k),
+            r2: group.exponentiate_secret(&self.statement.h, &k),
+        };
+
+        (
+            CommittedProver {
+                statement: self.statement,
+                witness: self.witness,
+                k,
+            },
+            commitment,
+        )
+    }
+}
+
+/// The prover waiting for the challenge, it keeps the nonce k.
+pub struct CommittedProver<G: CyclicGroup> {
+    statement: Statement<G>,
+    witness: Witness<G>,
+    k: G::Scalar,
+}
+
+impl<G: CyclicGroup> CommittedProver<G> {
+    pub fn statement(&self) -> &Statement<G> {
+        &self.statement
+    }
+
+    /// Answers the challenge of the verifier.
+    pub fn respond(self, group: &G, challenge: &Challenge<G>) -> Response<G> {
+        Response {
+            s: solve_zk_challenge_s(group, &self.witness.x, &self.k, &challenge.c),
+        }
+    }
+}
+
+impl<G: CyclicGroup> fmt::Debug for CommittedProver<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CommittedProver")
+            .field("y1", &self.statement.y1)
+            .field("y2", &self.statement.y2)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The verifier before the commitment.
+#[derive(Debug, Clone)]
+pub struct Verifier<G: CyclicGroup> {
+    statement: Statement<G>,
+}
+
+impl<G: CyclicGroup> Verifier<G> {
+    /// The verifier of `statement`, which must be over the generators of
+    /// `group`.
+    pub fn new(group: &G, statement: Statement<G>) -> Result<Self, Error> {
+        statement.check_generators(group)?;
+        Ok(Verifier { statement })
+    }
+
+    /// Receives the commitment of the prover and picks the random challenge
+    /// to send back.
+    pub fn challenge<R: RngCore + CryptoRng + ?Sized>(
+        self,
+        group: &G,
+        commitment: Commitment<G>,
+        rng: &mut R,
+    ) -> (ChallengedVerifier<G>, Challenge<G>) {
+        let c = group.random_scalar(rng);
+
+        (
+            ChallengedVerifier {
+                statement: self.statement,
+                commitment,
+                challenge: Challenge { c: c.clone() },
+            },
+            Challenge { c },
+        )
+    }
+}
+
+/// The verifier waiting for the response.
+#[derive(Debug, Clone)]
+pub struct ChallengedVerifier<G: CyclicGroup> {
+    statement: Statement<G>,
+    commitment: Commitment<G>,
+    challenge: Challenge<G>,
+}
+
+impl<G: CyclicGroup> ChallengedVerifier<G> {
+    pub fn statement(&self) -> &Statement<G> {
+        &self.statement
+    }
+
+    pub fn challenge(&self) -> &Challenge<G> {
+        &self.challenge
+    }
+
+    /// Checks r1 = g^s * y1^c and r2 = h^s * y2^c.
+    pub fn verify(self, group: &G, response: &Response<G>) -> Result<(), Error> {
+        let Statement { g, h, y1, y2 } = &self.statement;
+        let (s, c) = (&response.s, &self.challenge.c);
+
+        if self.commitment.r1 == group.multi_exponentiate(&[(g, s), (y1, c)])
+            && self.commitment.r2 == group.multi_exponentiate(&[(h, s), (y2, c)])
+        {
+            Ok(())
+        } else {
+            Err(Error::InvalidProof)
+        }
+    }
+
+    /// The transcript of the run to verify it later in a batch. The batch
+    /// checks it against the generators of the group, the same as the ones of
+    /// the statement.
+    pub fn transcript(self, response: Response<G>) -> Transcript<G> {
+        Transcript {
+            y1: self.statement.y1,
+            y2: self.statement.y2,
+            r1: self.commitment.r1,
+            r2: self.commitment.r2,
+            c: self.challenge.c,
+            s: response.s,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ModPGroup, P256Group, Ristretto255Group, Secp256k1Group};
+    use num_bigint::BigUint;
+    use p256::elliptic_curve::sec1::ToEncodedPoint;
+    use rand::thread_rng;
+
+    /// Runs the protocol through the byte encodings, with a wrong response if
+    /// `cheat`.
+    fn run<G: CyclicGroup + Clone>(group: &G, cheat: bool) -> Result<(), Error> {
+        let rng = &mut thread_rng();
+
+        let prover = Prover::new(group, Witness::new(group.random_scalar(rng)));
+        let statement = Statement::from_bytes(group, &prover.statement().to_bytes(group))?;
+        let verifier = Verifier::new(group, statement)?;
+
+        let (prover, commitment) = prover.commit(group, rng);
+        let commitment = Commitment::from_bytes(group, &commitment.to_bytes(group))?;
+        let (verifier, challenge) = verifier.challenge(group, commitment, rng);
+
+        let challenge = Challenge::from_bytes(group, &challenge.to_bytes(group))?;
+        let mut response = prover.respond(group, &challenge);
+        if cheat {
+            response.s = group.scalar_add(&response.s, &G::Scalar::from(1));
+        }
+
+        let response = Response::from_bytes(group, &response.to_bytes(group))?;
+        let transcript = verifier.clone().transcript(response.clone());
+        assert_eq!(transcript.verify(group), !cheat);
+        verifier.verify(group, &response)
+    }
+
+    #[test]
+    fn test_prover_verifier() {
+        fn check<G: CyclicGroup + Clone>(group: &G) {
+            assert_eq!(run(group, false), Ok(()));
+            assert_eq!(run(group, true), Err(Error::InvalidProof));
+        }
+
+        check(&ModPGroup::default());
+        check(&Secp256k1Group::default());
+        check(&P256Group::default());
+        check(&Ristretto255Group::default());
+    }
+
+    #[test]
+    fn test_forged_generators() {
+        let group = Ristretto255Group::default();
+        let rng = &mut thread_rng();
+
+        // y1 and y2 don't share a discrete logarithm, but with g = y1 and
+        // h = y2 the answer s = t - c passes r1 = g^s * y1^c for any c
+        let y1 = group.exponentiate(group.generator(), &group.random_scalar(rng));
+        let y2 = group.exponentiate(group.second_generator(), &group.random_scalar(rng));
+        let forged = Statement {
+            g: y1,
+            h: y2,
+            y1,
+            y2,
+        };
+
+        assert_eq!(
+            Statement::from_bytes(&group, &forged.to_bytes(&group)),
+            Err(Error::WrongGenerators)
+        );
+        assert!(matches!(
+            Verifier::new(&group, forged.clone()),
+            Err(Error::WrongGenerators)
+        ));
+
+        let swapped = Statement {
+            g: *group.second_generator(),
+            h: *group.generator(),
+            ..forged
+        };
+        assert!(matches!(
+            Verifier::new(&group, swapped),
+            Err(Error::WrongGenerators)
+        ));
+    }
+
+    #[test]
+    fn test_encoding() {
+        let group = ModPGroup::default();
+        let statement = Statement::new(&group, BigUint::from(4u32), BigUint::from(6419u32));
+
+        // 2 bytes length prefix before each element
+        let bytes = statement.to_bytes(&group);
+        assert_eq!(&bytes[bytes.len() - 8..], &[0, 2, 0, 4, 0, 2, 0x19, 0x13]);
+        assert_eq!(Statement::from_bytes(&group, &bytes), Ok(statement));
+
+        assert_eq!(
+            Statement::from_bytes(&group, &bytes[..bytes.len() - 1]),
+            Err(Error::InvalidLength)
+        );
+        assert_eq!(
+            Commitment::<ModPGroup>::from_bytes(&group, &bytes),
+            Err(Error::InvalidLength)
+        );
+        // the second 4 encoded without its leading zero
+        assert_eq!(
+            Commitment::<ModPGroup>::from_bytes(&group, &[0, 2, 0, 4, 0, 1, 4]),
+            Err(Error::NonCanonical)
+        );
+        assert_eq!(
+            Challenge::<ModPGroup>::from_bytes(&group, &group.q.to_bytes_be()),
+            Err(Error::ScalarOutOfRange)
+        );
+
+        // the uncompressed encoding of a point is valid but not canonical
+        let group = P256Group::default();
+        let mut bytes = vec![0, 65];
+        bytes.extend(
+            group
+                .generator()
+                .to_affine()
+                .to_encoded_point(false)
+                .as_bytes(),
+        );
+        put_element(&group, group.second_generator(), &mut bytes);
+        assert_eq!(
+            Commitment::<P256Group>::from_bytes(&group, &bytes),
+            Err(Error::NonCanonical)
+        );
+    }
+
+    #[test]
+    fn test_witness_debug() {
+        let witness = Witness::<ModPGroup>::new(BigUint::from(1234u32));
+        assert_eq!(format!("{:?}", witness), "Witness(..)");
+    }
+}
@@ -1,330 +1,410 @@
 /// This code is a copy of one library that I was developing for didactic purposes based on the book Programming Bitcoin.
 /// The code is not very well documented and the library is still on development.
 /// This is the original source code: https://github.com/gagiuntoli/bitcoin_rust
+use crate::constant_time::select;
+use crate::fixed_base::Comb;
+use crate::multi_exp::straus;
+use crate::Error;
 use hex;
-use num::{Integer, One, Zero};
-use num_bigint::{BigInt, BigUint, ToBigInt};
+use num_bigint::BigUint;
 use std::fmt::{self, Debug};
-use std::ops::{Add, Div, Mul, Sub};
+use std::ops::{Add, Neg};
+use subtle::{Choice, ConditionallySelectable};
 
-#[derive(PartialEq, Debug, Clone)]
-pub struct FiniteField {
-    pub number: BigUint,
-    pub prime: BigUint,
-}
+mod field;
+mod limbs;
+mod scalar;
 
-impl FiniteField {
-    pub fn from_bytes_be(number: &[u8], prime: &[u8]) -> Self {
-        let number = BigUint::from_bytes_be(number);
-        let prime = BigUint::from_bytes_be(prime);
+pub use field::FieldElement;
+pub use scalar::Scalar;
 
-        FiniteField { number, prime }
-    }
+/// A point of the secp256k1 curve y^2 = x^3 + 7, with coordinates in the
+/// fixed-limb field [`FieldElement`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Secp256k1Point {
+    Coor { x: FieldElement, y: FieldElement },
+    Zero,
+}
 
-    fn check_equal_order_and_panic(self: &Self, rhs: &FiniteField) {
-        if self.prime != rhs.prime {
-            panic!(
-                "Finite fields elements have different order lhs: {}, rhs: {}",
-                self.prime, rhs.prime
+impl Debug for Secp256k1Point {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Secp256k1Point::Coor { x, y } = self {
+            write!(
+                f,
+                "Point [x = {} y = {}]",
+                hex::encode(x.to_bytes()),
+                hex::encode(y.to_bytes())
             )
+        } else {
+            write!(f, "Point = Zero")
         }
     }
+}
 
-    pub fn pow(self, exp: &BigInt) -> FiniteField {
-        let exp = exp.mod_floor(&(self.prime.clone() - BigUint::one()).to_bigint().unwrap());
-        let exp = exp.to_biguint().unwrap();
-
-        let exp = exp.modpow(&BigUint::one(), &(self.prime.clone() - BigUint::one()));
+/// b of the curve y^2 = x^3 + b, a is zero.
+const B: u64 = 7;
 
-        FiniteField {
-            number: self.number.modpow(&exp, &self.prime),
-            prime: self.prime,
-        }
+impl Secp256k1Point {
+    pub fn prime() -> BigUint {
+        let prime = hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F")
+            .unwrap();
+        BigUint::from_bytes_be(&prime)
     }
 
-    pub fn scale(self, scalar: BigUint) -> FiniteField {
-        FiniteField {
-            number: (self.number * scalar) % self.prime.clone(),
-            prime: self.prime,
-        }
+    pub fn n() -> BigUint {
+        let n = hex::decode("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141")
+            .unwrap();
+        BigUint::from_bytes_be(&n)
     }
-}
 
-impl From<(u32, u32)> for FiniteField {
-    fn from(tuple: (u32, u32)) -> Self {
-        FiniteField {
-            number: BigUint::from(tuple.0),
-            prime: BigUint::from(tuple.1),
-        }
-    }
-}
+    pub fn generator() -> Secp256k1Point {
+        let gx = hex::decode("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+            .unwrap();
+        let gy = hex::decode("483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8")
+            .unwrap();
 
-impl From<(BigUint, BigUint)> for FiniteField {
-    fn from(tuple: (BigUint, BigUint)) -> Self {
-        FiniteField {
-            number: tuple.0,
-            prime: tuple.1,
-        }
+        Secp256k1Point::from_bytes_be(&gx, &gy).unwrap()
     }
-}
-
-impl Add for FiniteField {
-    type Output = FiniteField;
-
-    fn add(self, _rhs: FiniteField) -> FiniteField {
-        self.check_equal_order_and_panic(&_rhs);
 
-        FiniteField {
-            number: (self.number + _rhs.number) % self.prime.clone(),
-            prime: self.prime,
+    pub fn is_on_curve(&self) -> bool {
+        match self {
+            Secp256k1Point::Coor { x, y } => y.square() == x.square() * *x + FieldElement::from(B),
+            Secp256k1Point::Zero => true,
         }
     }
-}
-
-impl Sub for FiniteField {
-    type Output = FiniteField;
 
-    fn sub(self, rhs: FiniteField) -> FiniteField {
-        self.check_equal_order_and_panic(&rhs);
+    /// Multiplies the point by a public scalar with Straus' method, its
+    /// running time depends on the scalar.
+    pub fn scale(&self, scalar: &Scalar) -> Self {
+        Self::multi_scale(&[(self, scalar)])
+    }
 
-        if self.number >= rhs.number {
-            FiniteField {
-                number: (self.number - rhs.number) % self.prime.clone(),
-                prime: self.prime,
-            }
-        } else {
-            FiniteField {
-                number: (self.number + self.prime.clone() - rhs.number) % self.prime.clone(),
-                prime: self.prime,
-            }
+    /// Multiplies the point by a secret scalar with a Montgomery ladder in
+    /// Jacobian coordinates: every step does one addition and one doubling
+    /// regardless of the bit of the scalar and the points are swapped with
+    /// masks instead of branches. The scalar is blinded with the order `n` so
+    /// the ladder always runs 256 steps. Use `scale` for public scalars, it is
+    /// faster.
+    pub fn scale_secret(&self, scalar: &Scalar) -> Self {
+        if *self == Secp256k1Point::Zero {
+            return Secp256k1Point::Zero;
         }
-    }
-}
 
-impl Mul for FiniteField {
-    type Output = FiniteField;
+        let scalar = scalar.blinded();
 
-    fn mul(self, rhs: FiniteField) -> FiniteField {
-        self.check_equal_order_and_panic(&rhs);
+        let mut r0 = self.to_jacobian();
+        let mut r1 = r0.double();
 
-        FiniteField {
-            number: (self.number * rhs.number) % self.prime.clone(),
-            prime: self.prime,
+        for i in (0..256).rev() {
+            let bit = Choice::from(((scalar[i / 64] >> (i % 64)) & 1) as u8);
+            Jacobian::conditional_swap(&mut r0, &mut r1, bit);
+            r1 = r0.add(&r1);
+            r0 = r0.double();
+            Jacobian::conditional_swap(&mut r0, &mut r1, bit);
         }
+
+        Self::from_jacobian(&r0)
     }
-}
 
-impl Div for FiniteField {
-    type Output = FiniteField;
+    /// Computes e1 * P1 + e2 * P2 + ... for public scalars with Straus'
+    /// method in Jacobian coordinates, with a single inversion at the end.
+    pub fn multi_scale(terms: &[(&Secp256k1Point, &Scalar)]) -> Secp256k1Point {
+        let terms: Vec<(Jacobian, Vec<u8>)> = terms
+            .iter()
+            .map(|(point, scalar)| (point.to_jacobian(), scalar.to_bytes().to_vec()))
+            .collect();
 
-    fn div(self, rhs: FiniteField) -> FiniteField {
-        self.check_equal_order_and_panic(&rhs);
+        let sum = straus(&terms, Jacobian::INFINITY, Jacobian::add, Jacobian::double);
+        Self::from_jacobian(&sum)
+    }
 
-        self.clone() * rhs.pow(&(self.prime - BigUint::from(2u32)).to_bigint().unwrap())
+    pub fn compute_public_key(e: &Scalar) -> Secp256k1Point {
+        Secp256k1Point::generator().scale_secret(e)
     }
-}
 
-#[derive(PartialEq, Clone)]
-pub enum Point {
-    Coor {
-        a: FiniteField,
-        b: FiniteField,
-        x: FiniteField,
-        y: FiniteField,
-    },
-    Zero,
-}
+    /// The point of the big endian coordinates `x` and `y`. It fails if they
+    /// are longer than 32 bytes, not lower than p or not a point of the curve.
+    pub fn from_bytes_be(x: &[u8], y: &[u8]) -> Result<Secp256k1Point, Error> {
+        let coordinate = |bytes: &[u8]| {
+            if bytes.len() > 32 {
+                return Err(Error::InvalidLength);
+            }
+            let mut fixed = [0u8; 32];
+            fixed[32 - bytes.len()..].copy_from_slice(bytes);
+            FieldElement::from_bytes(&fixed).ok_or(Error::OutOfRange)
+        };
 
-impl Debug for Point {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if let Point::Coor { x, y, .. } = self {
-            write!(
-                f,
-                "Point [x = {} y = {}]",
-                hex::encode(&x.number.to_bytes_be()),
-                hex::encode(&y.number.to_bytes_be())
-            )
-        } else {
-            write!(f, "Point = Zero")
+        let point = Secp256k1Point::Coor {
+            x: coordinate(x)?,
+            y: coordinate(y)?,
+        };
+        if !point.is_on_curve() {
+            return Err(Error::NotOnCurve);
         }
+
+        Ok(point)
     }
-}
 
-impl Point {
-    pub fn new(a: &FiniteField, b: &FiniteField, x: &FiniteField, y: &FiniteField) -> Point {
-        let point = Point::Coor {
-            a: a.clone(),
-            b: b.clone(),
-            x: x.clone(),
-            y: y.clone(),
-        };
-        if !Self::is_on_curve(&point) {
-            panic!("({:?},{:?}) point is not in the curve", x, y);
+    /// Converts the point to Jacobian coordinates (x, y, 1).
+    fn to_jacobian(self) -> Jacobian {
+        match self {
+            Secp256k1Point::Coor { x, y } => Jacobian {
+                x,
+                y,
+                z: FieldElement::ONE,
+            },
+            Secp256k1Point::Zero => Jacobian::INFINITY,
         }
-        point
     }
 
-    #[allow(dead_code)]
-    fn zero() -> Self {
-        Point::Zero
-    }
+    /// Converts a point in Jacobian coordinates back to the affine point
+    /// (x / z^2, y / z^3). It costs one field inversion.
+    fn from_jacobian(point: &Jacobian) -> Secp256k1Point {
+        if point.is_infinity() {
+            return Secp256k1Point::Zero;
+        }
 
-    #[allow(dead_code)]
-    fn is_zero(self) -> bool {
-        self == Point::Zero
+        let z_inv = point.z.invert();
+        let z_inv_2 = z_inv.square();
+        Secp256k1Point::Coor {
+            x: point.x * z_inv_2,
+            y: point.y * z_inv_2 * z_inv,
+        }
     }
 
-    pub fn is_on_curve(p: &Point) -> bool {
-        match p {
-            Point::Coor { a, b, x, y } => {
-                return y.clone().pow(&BigInt::from(2u32))
-                    == x.clone().pow(&BigInt::from(3u32)) + a.clone() * x.clone() + b.clone()
+    /// Serializes the point with the SEC1 encoding:
+    ///  - compressed (33 bytes): `0x02 || x` if y is even, `0x03 || x` if odd.
+    ///  - uncompressed (65 bytes): `0x04 || x || y`.
+    ///
+    /// The point at infinity is encoded as the single byte `0x00`.
+    pub fn to_sec1(&self, compressed: bool) -> Vec<u8> {
+        match self {
+            Secp256k1Point::Coor { x, y } => {
+                let mut bytes = Vec::with_capacity(65);
+                if compressed {
+                    bytes.push(if y.is_odd() { 0x03 } else { 0x02 });
+                    bytes.extend(x.to_bytes());
+                } else {
+                    bytes.push(0x04);
+                    bytes.extend(x.to_bytes());
+                    bytes.extend(y.to_bytes());
+                }
+                bytes
             }
-            Point::Zero => true,
+            Secp256k1Point::Zero => vec![0x00],
         }
     }
 
-    // TODO: take a reference for the scalar
-    #[allow(dead_code)]
-    pub fn scale(self, _scalar: BigUint) -> Self {
-        let mut current = self.clone();
-        let mut scalar = _scalar;
-        let mut result = Point::Zero;
+    /// Deserializes a point encoded with SEC1, compressed or uncompressed, and
+    /// checks that it is a point of the curve. The point at infinity (`0x00`)
+    /// is accepted, callers that need a non-zero point should check it.
+    ///
+    /// As the cofactor of secp256k1 is 1 every point of the curve belongs to
+    /// the subgroup of order `n`.
+    pub fn from_sec1(bytes: &[u8]) -> Result<Secp256k1Point, Error> {
+        let coordinate = |bytes: &[u8]| {
+            FieldElement::from_bytes(bytes.try_into().unwrap()).ok_or(Error::OutOfRange)
+        };
 
-        while scalar != BigUint::zero() {
-            if &scalar & BigUint::one() != BigUint::zero() {
-                result = current.clone() + result;
+        let (x, y) = match (bytes.first(), bytes.len()) {
+            (Some(0x00), 1) => return Ok(Secp256k1Point::Zero),
+            (Some(0x02 | 0x03), 33) | (Some(0x04), 65) => {
+                (coordinate(&bytes[1..33])?, &bytes[33..])
             }
-            current = current.clone() + current;
-            scalar = scalar >> 1;
-        }
-        return result;
-    }
-}
-
-impl Add for Point {
-    type Output = Point;
+            (Some(0x00 | 0x02 | 0x03 | 0x04), _) | (None, _) => return Err(Error::InvalidLength),
+            _ => return Err(Error::InvalidPrefix),
+        };
 
-    fn add(self, rhs: Point) -> Point {
-        match (self.clone(), rhs.clone()) {
-            (Point::Zero, _) => return rhs,
-            (_, Point::Zero) => return self,
-            (
-                Point::Coor { a, b, x, y },
-                Point::Coor {
-                    a: a_rhs,
-                    b: b_rhs,
-                    x: x_rhs,
-                    y: y_rhs,
-                    ..
-                },
-            ) => {
-                if a != a_rhs || b != b_rhs {
-                    panic!(
-                        "The points (x:{:?},y:{:?},a:{:?},b:{:?}) and (x:{:?},y:{:?},a:{:?},b:{:?}) belong to different curves",
-                        x, y, a, b, x_rhs, y_rhs, a_rhs, b_rhs
-                    );
-                }
-                if x == x_rhs && y != y_rhs {
-                    Point::Zero
-                } else if self == rhs && y == x_rhs.clone().scale(BigUint::zero()) {
-                    Point::Zero
-                } else if x != x_rhs {
-                    let s = (y_rhs.clone() - y.clone()) / (x_rhs.clone() - x.clone());
-                    let x_res = s.clone().pow(&BigInt::from(2u32)) - x.clone() - x_rhs.clone();
-                    let y_res = s.clone() * (x.clone() - x_res.clone()) - y;
-
-                    Point::Coor {
-                        a,
-                        b,
-                        x: x_res,
-                        y: y_res,
-                    }
-                } else {
-                    let s = (x
-                        .clone()
-                        .pow(&BigInt::from(2u32))
-                        .scale(BigUint::from(3u32))
-                        + a.clone())
-                        / (y.clone().scale(BigUint::from(2u32)));
-                    let x_res =
-                        s.clone().pow(&BigInt::from(2u32)) - x.clone().scale(BigUint::from(2u32));
-                    let y_res = s * (x - x_res.clone()) - y;
-                    return Point::Coor {
-                        a,
-                        b,
-                        x: x_res,
-                        y: y_res,
-                    };
-                }
+        let y = if y.is_empty() {
+            // y^2 = x^3 + 7
+            let y_2 = x.square() * x + FieldElement::from(B);
+            let y = y_2.sqrt();
+            if y.square() != y_2 {
+                return Err(Error::NotOnCurve);
             }
+            if y.is_odd() != (bytes[0] == 0x03) {
+                -y
+            } else {
+                y
+            }
+        } else {
+            coordinate(y)?
+        };
+
+        let point = Secp256k1Point::Coor { x, y };
+        if !point.is_on_curve() {
+            return Err(Error::NotOnCurve);
         }
+
+        Ok(point)
     }
 }
 
-pub type Secp256k1Point = Point;
+impl Add for Secp256k1Point {
+    type Output = Secp256k1Point;
 
-impl Secp256k1Point {
-    pub fn prime() -> BigUint {
-        let prime = hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F")
-            .unwrap();
-        BigUint::from_bytes_be(&prime)
+    fn add(self, rhs: Secp256k1Point) -> Secp256k1Point {
+        Self::from_jacobian(&self.to_jacobian().add(&rhs.to_jacobian()))
     }
+}
 
-    pub fn n() -> BigUint {
-        let n = hex::decode("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141")
-            .unwrap();
-        BigUint::from_bytes_be(&n)
-    }
+impl Neg for Secp256k1Point {
+    type Output = Secp256k1Point;
 
-    pub fn a() -> FiniteField {
-        FiniteField::from_bytes_be(&[0u8], &Self::prime().to_bytes_be())
+    fn neg(self) -> Secp256k1Point {
+        match self {
+            Secp256k1Point::Coor { x, y } => Secp256k1Point::Coor { x, y: -y },
+            Secp256k1Point::Zero => Secp256k1Point::Zero,
+        }
     }
+}
 
-    pub fn b() -> FiniteField {
-        FiniteField::from_bytes_be(&[7u8], &Self::prime().to_bytes_be())
-    }
+/// A point of secp256k1 in Jacobian coordinates (X, Y, Z), which represents
+/// the affine point (X / Z^2, Y / Z^3). The point at infinity has Z = 0.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Jacobian {
+    x: FieldElement,
+    y: FieldElement,
+    z: FieldElement,
+}
 
-    pub fn generator() -> Point {
-        let gx = hex::decode("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
-            .unwrap();
-        let gy = hex::decode("483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8")
-            .unwrap();
+impl Jacobian {
+    const INFINITY: Jacobian = Jacobian {
+        x: FieldElement::ONE,
+        y: FieldElement::ONE,
+        z: FieldElement::ZERO,
+    };
 
-        Secp256k1Point::from_bytes_be(&gx, &gy)
+    fn is_infinity(&self) -> bool {
+        self.z.is_zero()
     }
 
-    pub fn compute_public_key(e: &BigUint) -> Point {
-        Secp256k1Point::generator().scale(e.clone())
+    /// Doubles the point, for a = 0. It has no branches: the doubling of the
+    /// point at infinity has Z = 0, and no point of the curve has y = 0 as
+    /// its order is odd.
+    fn double(&self) -> Jacobian {
+        let xx = self.x.square();
+        let yy = self.y.square();
+        let yyyy = yy.square();
+        // S = 4 X Y^2 = 2 ((X + Y^2)^2 - X^2 - Y^4)
+        let s = ((self.x + yy).square() - xx - yyyy).double();
+        // M = 3 X^2
+        let m = xx.double() + xx;
+        // X' = M^2 - 2 S
+        let x = m.square() - s.double();
+        // Y' = M (S - X') - 8 Y^4
+        let y = m * (s - x) - yyyy.double().double().double();
+        // Z' = 2 Y Z
+        let z = (self.y * self.z).double();
+
+        Jacobian { x, y, z }
     }
 
-    pub fn n_minus_2() -> BigUint {
-        Self::n() - BigUint::from(2u32)
+    /// Adds two points.
+    fn add(&self, rhs: &Jacobian) -> Jacobian {
+        if self.is_infinity() {
+            return *rhs;
+        }
+        if rhs.is_infinity() {
+            return *self;
+        }
+
+        let z1z1 = self.z.square();
+        let z2z2 = rhs.z.square();
+        // U1 = X1 Z2^2, U2 = X2 Z1^2
+        let u1 = self.x * z2z2;
+        let u2 = rhs.x * z1z1;
+        // S1 = Y1 Z2^3, S2 = Y2 Z1^3
+        let s1 = self.y * rhs.z * z2z2;
+        let s2 = rhs.y * self.z * z1z1;
+
+        if u1 == u2 {
+            if s1 == s2 {
+                return self.double();
+            }
+            return Jacobian::INFINITY;
+        }
+
+        // H = U2 - U1, R = S2 - S1
+        let h = u2 - u1;
+        let r = s2 - s1;
+        let hh = h.square();
+        let hhh = hh * h;
+        let u1hh = u1 * hh;
+
+        // X3 = R^2 - H^3 - 2 U1 H^2
+        let x = r.square() - hhh - u1hh.double();
+        // Y3 = R (U1 H^2 - X3) - S1 H^3
+        let y = r * (u1hh - x) - s1 * hhh;
+        // Z3 = H Z1 Z2
+        let z = h * self.z * rhs.z;
+
+        Jacobian { x, y, z }
     }
+}
 
-    pub fn from_bigint(x: &BigUint, y: &BigUint) -> Point {
-        Self::from_bytes_be(&x.to_bytes_be(), &y.to_bytes_be())
+impl ConditionallySelectable for Jacobian {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Jacobian {
+            x: FieldElement::conditional_select(&a.x, &b.x, choice),
+            y: FieldElement::conditional_select(&a.y, &b.y, choice),
+            z: FieldElement::conditional_select(&a.z, &b.z, choice),
+        }
     }
+}
 
-    pub fn from_bytes_be(x: &[u8], y: &[u8]) -> Point {
-        let prime = hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F")
-            .unwrap();
+/// The comb table of a fixed point to multiply it by scalars lower than 2^256.
+///
+/// The additions in Jacobian coordinates take a shortcut for the point at
+/// infinity, so a point `offset` whose discrete logarithm is unknown is added
+/// to every entry, the one of the columns of zeros included. The sums never
+/// go through the point at infinity and the secret scalars take the same path
+/// whatever their bits. The offsets are subtracted at the end.
+#[derive(Debug, Clone)]
+pub struct CombTable {
+    comb: Comb<Jacobian>,
+    /// -(2^columns - 1) * offset, the opposite of the offsets added with the
+    /// entries.
+    correction: Jacobian,
+}
 
-        let x = FiniteField::from_bytes_be(&x, &prime);
-        let y = FiniteField::from_bytes_be(&y, &prime);
+impl CombTable {
+    pub fn new(point: &Secp256k1Point, offset: &Secp256k1Point) -> Self {
+        let offset_jacobian = offset.to_jacobian();
+        let comb = Comb::new(
+            &point.to_jacobian(),
+            256,
+            Jacobian::INFINITY,
+            Jacobian::add,
+            Jacobian::double,
+        )
+        .map(|entry| entry.add(&offset_jacobian));
+
+        let offsets = Scalar::from((1u64 << comb.columns()) - 1);
+        let correction = (-offset.scale(&offsets)).to_jacobian();
+
+        CombTable { comb, correction }
+    }
 
-        let point = Point::Coor {
-            a: Self::a(),
-            b: Self::b(),
-            x: x.clone(),
-            y: y.clone(),
-        };
+    /// Multiplies the point by a public `scalar`.
+    pub fn scale(&self, scalar: &Scalar) -> Secp256k1Point {
+        self.sum(scalar, |column| self.comb.entries()[column])
+    }
 
-        if !Point::is_on_curve(&point) {
-            panic!("({:?},{:?}) point is not in the curve", x, y);
-        }
+    /// Multiplies the point by a secret `scalar`, reading the whole table for
+    /// every column.
+    pub fn scale_secret(&self, scalar: &Scalar) -> Secp256k1Point {
+        self.sum(scalar, |column| select(self.comb.entries(), column))
+    }
 
-        point
+    fn sum(&self, scalar: &Scalar, select: impl Fn(usize) -> Jacobian) -> Secp256k1Point {
+        let sum = self.comb.exponentiate_secret(
+            &scalar.to_bytes(),
+            select,
+            Jacobian::add,
+            Jacobian::double,
+        );
+        Secp256k1Point::from_jacobian(&sum.add(&self.correction))
     }
 }
 
@@ -332,212 +412,360 @@ impl Secp256k1Point {
 mod tests {
     use super::*;
     use hex;
+    use num::Zero;
 
-    #[test]
-    fn test_on_curve() {
-        let prime = 223;
-        let a = FiniteField::from((0, prime));
-        let b = FiniteField::from((7, prime));
-
-        // on the curve
-        let x = FiniteField::from((192, prime));
-        let y = FiniteField::from((105, prime));
-
-        assert!(Point::is_on_curve(&Point::Coor {
-            a: a.clone(),
-            b: b.clone(),
-            x,
-            y
-        }));
-
-        let x = FiniteField::from((17, prime));
-        let y = FiniteField::from((56, prime));
-
-        assert!(Point::is_on_curve(&Point::Coor {
-            a: a.clone(),
-            b: b.clone(),
-            x,
-            y
-        }));
-
-        let x = FiniteField::from((1, prime));
-        let y = FiniteField::from((193, prime));
-
-        assert!(Point::is_on_curve(&Point::Coor {
-            a: a.clone(),
-            b: b.clone(),
-            x,
-            y
-        }));
-
-        // not on the curve
-        let x = FiniteField::from((200, prime));
-        let y = FiniteField::from((119, prime));
-
-        assert!(!Point::is_on_curve(&Point::Coor {
-            a: a.clone(),
-            b: b.clone(),
-            x,
-            y
-        }));
-
-        let x = FiniteField::from((42, prime));
-        let y = FiniteField::from((99, prime));
-
-        assert!(!Point::is_on_curve(&Point::Coor { a, b, x, y }));
+    /// The point of the hex encoded coordinates.
+    fn point(x: &str, y: &str) -> Secp256k1Point {
+        Secp256k1Point::from_bytes_be(&hex::decode(x).unwrap(), &hex::decode(y).unwrap()).unwrap()
     }
 
-    #[test]
-    fn test_point_addition() {
-        let prime = 223;
-        let a = FiniteField::from((0, prime));
-        let b = FiniteField::from((7, prime));
-
-        let x = FiniteField::from((192, prime));
-        let y = FiniteField::from((105, prime));
-
-        let p1 = Point::new(&a, &b, &x, &y);
-
-        let x = FiniteField::from((17, prime));
-        let y = FiniteField::from((56, prime));
-
-        let p2 = Point::new(&a, &b, &x, &y);
-
-        let x = FiniteField::from((170, prime));
-        let y = FiniteField::from((142, prime));
-
-        let p3 = Point::new(&a, &b, &x, &y);
-
-        assert_eq!(p1 + p2, p3);
-
-        // (170,142) + (60, 139)
-        let x = FiniteField::from((170, prime));
-        let y = FiniteField::from((142, prime));
-
-        let p1 = Point::new(&a, &b, &x, &y);
-
-        let x = FiniteField::from((60, prime));
-        let y = FiniteField::from((139, prime));
-
-        let p2 = Point::new(&a, &b, &x, &y);
-
-        let x = FiniteField::from((220, prime));
-        let y = FiniteField::from((181, prime));
-
-        let p3 = Point::new(&a, &b, &x, &y);
-
-        assert_eq!(p1 + p2, p3);
-
-        // (47,71) + (17,56)
-        let x = FiniteField::from((47, prime));
-        let y = FiniteField::from((71, prime));
-
-        let p1 = Point::new(&a, &b, &x, &y);
-
-        let x = FiniteField::from((17, prime));
-        let y = FiniteField::from((56, prime));
-
-        let p2 = Point::new(&a, &b, &x, &y);
-
-        let x = FiniteField::from((215, prime));
-        let y = FiniteField::from((68, prime));
-
-        let p3 = Point::new(&a, &b, &x, &y);
-
-        assert_eq!(p1 + p2, p3);
+    /// k * G for a few k, the known answers of the scalar multiplications.
+    fn multiples() -> Vec<(Scalar, Secp256k1Point)> {
+        vec![
+            (
+                Scalar::from(2),
+                point(
+                    "c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5",
+                    "1ae168fea63dc339a3c58419466ceaeef7f632653266d0e1236431a950cfe52a",
+                ),
+            ),
+            (
+                Scalar::from(3),
+                point(
+                    "f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9",
+                    "388f7b0f632de8140fe337e62a37f3566500a99934c2231b6cb9fd7584b8e672",
+                ),
+            ),
+            (
+                Scalar::from(0xdeadbeef),
+                point(
+                    "76d2fdf1302d1fa9556f4df94ec84cefba6d482e54f47c6c2a238c1baa560f0e",
+                    "b754ac7e7a3e09c44184cb451a4f5fb557f32053eb015dffebb655b5cfd54d8a",
+                ),
+            ),
+            (
+                Scalar::from_bytes_reduced(&[0xab; 32]),
+                point(
+                    "81aaadc8a5e83f4576df823cf22a5b1969cf704a0d5f6f68bd757410c9917aac",
+                    "a8d3ba51557ae48e250687eefd0a9b51b14c39a78295c2999b600cda67483fad",
+                ),
+            ),
+        ]
+    }
 
-        // (143,98) + (76,66)
-        let x = FiniteField::from((143, prime));
-        let y = FiniteField::from((98, prime));
+    #[test]
+    fn test_on_curve() {
+        let g = Secp256k1Point::generator();
+        assert!(g.is_on_curve());
+        assert!(Secp256k1Point::Zero.is_on_curve());
+        for (_, point) in multiples() {
+            assert!(point.is_on_curve());
+        }
 
-        let p1 = Point::new(&a, &b, &x, &y);
+        let Secp256k1Point::Coor { x, y } = g else {
+            unreachable!()
+        };
+        for (x, y) in [
+            (x, y + FieldElement::ONE),
+            (y, x),
+            (FieldElement::ONE, FieldElement::ONE),
+            (FieldElement::ZERO, FieldElement::ZERO),
+        ] {
+            assert!(!Secp256k1Point::Coor { x, y }.is_on_curve());
+        }
+    }
 
-        let x = FiniteField::from((76, prime));
-        let y = FiniteField::from((66, prime));
+    #[test]
+    fn test_bitcoin_generator_point() {
+        let g = Secp256k1Point::generator();
+        assert_eq!(
+            hex::encode(g.to_sec1(false)),
+            "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798\
+             483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8"
+        );
+
+        // n * G = 0: n is not a scalar, so (n - 1) * G + G
+        let n_minus_1 = -Scalar::ONE;
+        assert_eq!(g.scale(&n_minus_1), -g);
+        assert_eq!(g.scale_secret(&n_minus_1), -g);
+        assert_eq!(g.scale(&n_minus_1) + g, Secp256k1Point::Zero);
+        assert_eq!(g.scale_secret(&n_minus_1) + g, Secp256k1Point::Zero);
+
+        let n = Scalar::from_bytes_reduced(&Secp256k1Point::n().to_bytes_be());
+        assert_eq!(g.scale(&n), Secp256k1Point::Zero);
+        assert_eq!(g.scale_secret(&n), Secp256k1Point::Zero);
+    }
 
-        let p2 = Point::new(&a, &b, &x, &y);
+    #[test]
+    fn test_scale() {
+        let g = Secp256k1Point::generator();
+        for (scalar, expected) in multiples() {
+            assert_eq!(g.scale(&scalar), expected);
+            assert_eq!(g.scale_secret(&scalar), expected);
+            assert_eq!(Secp256k1Point::compute_public_key(&scalar), expected);
+        }
+        assert_eq!(g.scale(&Scalar::ONE), g);
+        assert_eq!(g.scale_secret(&Scalar::ONE), g);
+    }
 
-        let x = FiniteField::from((47, prime));
-        let y = FiniteField::from((71, prime));
+    #[test]
+    fn test_point_addition() {
+        let g = Secp256k1Point::generator();
+        let [(_, g2), (_, g3), ..] = &multiples()[..] else {
+            unreachable!()
+        };
 
-        let p3 = Point::new(&a, &b, &x, &y);
+        assert_eq!(g + g, *g2);
+        assert_eq!(g + *g2, *g3);
+        assert_eq!(*g2 + g, *g3);
+        assert_eq!(*g3 + -g, *g2);
+        assert_eq!(*g2 + -*g2, Secp256k1Point::Zero);
+        assert_eq!(Secp256k1Point::Zero + *g3, *g3);
+    }
 
-        assert_eq!(p1 + p2, p3);
+    /// The point in Jacobian coordinates (x z^2, y z^3, z).
+    fn to_jacobian_with_z(point: &Secp256k1Point, z: u64) -> Jacobian {
+        let Secp256k1Point::Coor { x, y } = *point else {
+            return Jacobian::INFINITY;
+        };
+        let z = FieldElement::from(z);
+        Jacobian {
+            x: x * z.square(),
+            y: y * z.square() * z,
+            z,
+        }
     }
 
     #[test]
-    fn test_scale() {
-        let prime = 223;
-        let a = FiniteField::from((0, prime));
-        let b = FiniteField::from((7, prime));
-
-        let x = FiniteField::from((47, prime));
-        let y = FiniteField::from((71, prime));
+    fn test_jacobian_add_and_double() {
+        let g = Secp256k1Point::generator();
+        let [(_, g2), (_, g3), ..] = &multiples()[..] else {
+            unreachable!()
+        };
+        let affine = Secp256k1Point::from_jacobian;
+
+        // the results don't depend on the representation: z != 1
+        let j1 = to_jacobian_with_z(&g, 5);
+        let j2 = to_jacobian_with_z(g2, 7);
+        assert_eq!(affine(&j1), g);
+        assert_eq!(affine(&j1.add(&j2)), *g3);
+        assert_eq!(affine(&j2.add(&j1)), *g3);
+        assert_eq!(affine(&j1.double()), *g2);
+        assert_eq!(affine(&j1.add(&to_jacobian_with_z(&g, 3))), *g2);
+        assert_eq!(affine(&j1.double().add(&j1)), *g3);
+
+        // P + (-P) = 0, and 0 is neutral
+        let minus = to_jacobian_with_z(&-g, 3);
+        assert!(j1.add(&minus).is_infinity());
+        assert!(Jacobian::INFINITY.double().is_infinity());
+        assert_eq!(affine(&Jacobian::INFINITY.add(&j2)), *g2);
+        assert_eq!(affine(&j2.add(&Jacobian::INFINITY)), *g2);
+    }
 
-        let p = Point::new(&a, &b, &x, &y);
+    #[test]
+    fn test_scale_secret() {
+        let g = Secp256k1Point::generator();
+
+        for scalar in [
+            Scalar::from(1),
+            Scalar::from(2),
+            Scalar::from(13),
+            Scalar::from(0xdeadbeef),
+            -Scalar::ONE,
+            Scalar::from_bytes_reduced(&[0xab; 32]),
+        ] {
+            assert_eq!(g.scale_secret(&scalar), g.scale(&scalar));
+        }
 
-        let x = FiniteField::from((47, prime));
-        let y = FiniteField::from((71, prime));
-        let pr = Point::new(&a, &b, &x, &y);
-        assert_eq!(p.clone().scale(BigUint::from(1u32)), pr);
+        assert_eq!(g.scale_secret(&Scalar::ZERO), Secp256k1Point::Zero);
+        assert_eq!(g.scale(&Scalar::ZERO), Secp256k1Point::Zero);
+        assert_eq!(
+            Secp256k1Point::Zero.scale_secret(&Scalar::from(7)),
+            Secp256k1Point::Zero
+        );
+    }
 
-        let x = FiniteField::from((36, prime));
-        let y = FiniteField::from((111, prime));
-        let pr = Point::new(&a, &b, &x, &y);
-        assert_eq!(p.clone().scale(BigUint::from(2u32)), pr);
+    /// The affine coordinates of a point as `BigUint`s, `None` for the point
+    /// at infinity.
+    type TextbookPoint = Option<(BigUint, BigUint)>;
+
+    fn to_textbook(point: &Secp256k1Point) -> TextbookPoint {
+        match point {
+            Secp256k1Point::Coor { x, y } => Some((
+                BigUint::from_bytes_be(&x.to_bytes()),
+                BigUint::from_bytes_be(&y.to_bytes()),
+            )),
+            Secp256k1Point::Zero => None,
+        }
+    }
 
-        let x = FiniteField::from((15, prime));
-        let y = FiniteField::from((137, prime));
-        let pr = Point::new(&a, &b, &x, &y);
-        assert_eq!(p.clone().scale(BigUint::from(3u32)), pr);
+    /// P + Q with the textbook chord and tangent formulas of y^2 = x^3 + 7.
+    fn textbook_add(p: &TextbookPoint, q: &TextbookPoint) -> TextbookPoint {
+        let prime = Secp256k1Point::prime();
+        let inverse = |a: BigUint| a.modpow(&(&prime - 2u32), &prime);
 
-        let x = FiniteField::from((194, prime));
-        let y = FiniteField::from((51, prime));
-        let pr = Point::new(&a, &b, &x, &y);
-        assert_eq!(p.clone().scale(BigUint::from(4u32)), pr);
+        let (Some((x1, y1)), Some((x2, y2))) = (p, q) else {
+            return p.clone().or(q.clone());
+        };
+        let slope = if x1 != x2 {
+            (y2 + &prime - y1) * inverse(x2 + &prime - x1)
+        } else if y1 == y2 && !y1.is_zero() {
+            3u32 * x1 * x1 * inverse(2u32 * y1)
+        } else {
+            return None;
+        } % &prime;
 
-        let x = FiniteField::from((47, prime));
-        let y = FiniteField::from((152, prime));
-        let pr = Point::new(&a, &b, &x, &y);
-        assert_eq!(p.clone().scale(BigUint::from(20u32)), pr);
+        let x3 = (&slope * &slope + 2u32 * &prime - x1 - x2) % &prime;
+        let y3 = (&slope * (x1 + &prime - &x3) % &prime + &prime - y1) % &prime;
+        Some((x3, y3))
+    }
 
-        assert_eq!(p.scale(BigUint::from(21u32)), Point::Zero);
+    /// k * P with double-and-add.
+    fn textbook_scale(p: &TextbookPoint, k: &BigUint) -> TextbookPoint {
+        (0..k.bits()).rev().fold(None, |sum, i| {
+            let sum = textbook_add(&sum, &sum);
+            if k.bit(i) {
+                textbook_add(&sum, p)
+            } else {
+                sum
+            }
+        })
     }
 
     #[test]
-    fn test_bitcoin_generator_point() {
-        let prime = hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F")
-            .unwrap();
+    fn test_matches_textbook_point() {
+        let g = Secp256k1Point::generator();
+        let p = g.scale(&Scalar::from(0x1234_5678));
+
+        for scalar in [Scalar::from(3), Scalar::from_bytes_reduced(&[0x5c; 32])] {
+            let expected = textbook_scale(
+                &to_textbook(&g),
+                &BigUint::from_bytes_be(&scalar.to_bytes()),
+            );
+            assert_eq!(to_textbook(&g.scale(&scalar)), expected);
+        }
 
-        let a = hex::decode("00").unwrap();
-        let b = hex::decode("07").unwrap();
+        assert_eq!(
+            to_textbook(&(g + p)),
+            textbook_add(&to_textbook(&g), &to_textbook(&p))
+        );
+        assert_eq!(
+            to_textbook(&(g + g)),
+            textbook_add(&to_textbook(&g), &to_textbook(&g))
+        );
+        assert_eq!(g + -g, Secp256k1Point::Zero);
+        assert_eq!(g + Secp256k1Point::Zero, g);
+    }
 
-        let gx = hex::decode("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
-            .unwrap();
-        let gy = hex::decode("483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8")
-            .unwrap();
+    #[test]
+    fn test_comb_table() {
+        let g = Secp256k1Point::generator();
+        let offset = g.scale(&Scalar::from(99));
+        let table = CombTable::new(&g, &offset);
+
+        for scalar in [
+            Scalar::ZERO,
+            Scalar::ONE,
+            -Scalar::ONE,
+            Scalar::from_bytes_reduced(&[0x3e; 32]),
+        ] {
+            assert_eq!(table.scale(&scalar), g.scale(&scalar));
+            assert_eq!(table.scale_secret(&scalar), g.scale(&scalar));
+        }
+    }
 
-        let a = FiniteField::from_bytes_be(&a, &prime);
-        let b = FiniteField::from_bytes_be(&b, &prime);
-        let gx = FiniteField::from_bytes_be(&gx, &prime);
-        let gy = FiniteField::from_bytes_be(&gy, &prime);
+    #[test]
+    fn test_sec1_encoding() {
+        let g = Secp256k1Point::generator();
+
+        let compressed = g.to_sec1(true);
+        assert_eq!(
+            hex::encode(&compressed),
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+        );
+
+        let uncompressed = g.to_sec1(false);
+        assert_eq!(
+            hex::encode(&uncompressed),
+            "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798\
+             483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8"
+        );
+
+        assert_eq!(Secp256k1Point::from_sec1(&compressed), Ok(g));
+        assert_eq!(Secp256k1Point::from_sec1(&uncompressed), Ok(g));
+
+        // -g has the same x and an odd y
+        let minus_g = g.scale(&-Scalar::ONE);
+        let compressed = minus_g.to_sec1(true);
+        assert_eq!(compressed[0], 0x03);
+        assert_eq!(compressed[1..], g.to_sec1(true)[1..]);
+        assert_eq!(Secp256k1Point::from_sec1(&compressed), Ok(minus_g));
+
+        assert_eq!(Secp256k1Point::Zero.to_sec1(true), vec![0x00]);
+        assert_eq!(Secp256k1Point::from_sec1(&[0x00]), Ok(Secp256k1Point::Zero));
+    }
 
-        assert!(Point::is_on_curve(&Point::Coor {
-            a: a.clone(),
-            b: b.clone(),
-            x: gx.clone(),
-            y: gy.clone()
-        }));
+    #[test]
+    fn test_sec1_invalid_encoding() {
+        let g = Secp256k1Point::generator();
+
+        // wrong prefix
+        let mut bytes = g.to_sec1(true);
+        bytes[0] = 0x05;
+        assert_eq!(Secp256k1Point::from_sec1(&bytes), Err(Error::InvalidPrefix));
+
+        // wrong length
+        assert_eq!(
+            Secp256k1Point::from_sec1(&g.to_sec1(true)[..32]),
+            Err(Error::InvalidLength)
+        );
+        assert_eq!(
+            Secp256k1Point::from_sec1(&g.to_sec1(false)[..33]),
+            Err(Error::InvalidLength)
+        );
+        assert_eq!(Secp256k1Point::from_sec1(&[]), Err(Error::InvalidLength));
+
+        // not in the curve
+        let mut bytes = g.to_sec1(false);
+        bytes[64] ^= 1;
+        assert_eq!(Secp256k1Point::from_sec1(&bytes), Err(Error::NotOnCurve));
+
+        // x = 5 has no y in the curve: 5^3 + 7 = 132 is not a square mod p
+        let mut bytes = vec![0u8; 33];
+        bytes[0] = 0x02;
+        bytes[32] = 5;
+        assert_eq!(Secp256k1Point::from_sec1(&bytes), Err(Error::NotOnCurve));
+
+        // x = p
+        let mut bytes = vec![0x02];
+        bytes.extend(Secp256k1Point::prime().to_bytes_be());
+        assert_eq!(Secp256k1Point::from_sec1(&bytes), Err(Error::OutOfRange));
+    }
 
-        let n = hex::decode("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141")
-            .unwrap();
-        let p = Point::Coor {
-            a,
-            b,
-            x: gx.clone(),
-            y: gy.clone(),
+    #[test]
+    fn test_from_bytes_be() {
+        let g = Secp256k1Point::generator();
+        let Secp256k1Point::Coor { x, y } = g else {
+            unreachable!()
         };
-
-        assert_eq!(p.scale(BigUint::from_bytes_be(&n)), Point::Zero);
+        assert_eq!(
+            Secp256k1Point::from_bytes_be(&x.to_bytes(), &y.to_bytes()),
+            Ok(g)
+        );
+
+        let mut wrong_y = y.to_bytes();
+        wrong_y[31] ^= 1;
+        assert_eq!(
+            Secp256k1Point::from_bytes_be(&x.to_bytes(), &wrong_y),
+            Err(Error::NotOnCurve)
+        );
+        assert_eq!(
+            Secp256k1Point::from_bytes_be(&Secp256k1Point::prime().to_bytes_be(), &y.to_bytes()),
+            Err(Error::OutOfRange)
+        );
+        assert_eq!(
+            Secp256k1Point::from_bytes_be(&[0; 33], &y.to_bytes()),
+            Err(Error::InvalidLength)
+        );
     }
 }
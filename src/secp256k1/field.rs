@@ -0,0 +1,268 @@
+//! The field of the coordinates of secp256k1, the integers modulo the prime
+//! p = 2^256 - 2^32 - 977.
+//!
+//! An element is stored on four 64 bits limbs, always fully reduced, so it is
+//! `Copy`, needs no allocation and has a single representation. As 2^256 is
+//! 2^32 + 977 mod p, the high half of a product is folded into the low one
+//! with a multiplication by this small constant instead of a division.
+
+use super::limbs::{self, adc, mac, Limbs};
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+/// The prime p.
+const P: Limbs = [
+    0xFFFF_FFFE_FFFF_FC2F,
+    0xFFFF_FFFF_FFFF_FFFF,
+    0xFFFF_FFFF_FFFF_FFFF,
+    0xFFFF_FFFF_FFFF_FFFF,
+];
+
+/// 2^256 mod p = 2^32 + 977.
+const C: u64 = 0x1_0000_03D1;
+
+/// p - 2, the exponent of the inversion.
+const P_MINUS_2: Limbs = [
+    0xFFFF_FFFE_FFFF_FC2D,
+    0xFFFF_FFFF_FFFF_FFFF,
+    0xFFFF_FFFF_FFFF_FFFF,
+    0xFFFF_FFFF_FFFF_FFFF,
+];
+
+/// (p + 1) / 4, the exponent of the square root.
+const P_PLUS_1_DIV_4: Limbs = [
+    0xFFFF_FFFF_BFFF_FF0C,
+    0xFFFF_FFFF_FFFF_FFFF,
+    0xFFFF_FFFF_FFFF_FFFF,
+    0x3FFF_FFFF_FFFF_FFFF,
+];
+
+/// An integer mod p.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct FieldElement(Limbs);
+
+impl FieldElement {
+    pub const ZERO: FieldElement = FieldElement([0; 4]);
+    pub const ONE: FieldElement = FieldElement([1, 0, 0, 0]);
+
+    /// The element of the big endian `bytes`, `None` if they are not lower
+    /// than p.
+    pub fn from_bytes(bytes: &[u8; 32]) -> Option<FieldElement> {
+        let limbs = limbs::from_be_bytes(bytes);
+        if bool::from(limbs::less_than(&limbs, &P)) {
+            Some(FieldElement(limbs))
+        } else {
+            None
+        }
+    }
+
+    /// The big endian encoding of the element.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        limbs::to_be_bytes(&self.0)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        *self == FieldElement::ZERO
+    }
+
+    pub fn is_odd(&self) -> bool {
+        self.0[0] & 1 == 1
+    }
+
+    pub fn square(&self) -> FieldElement {
+        *self * *self
+    }
+
+    /// 2 * self.
+    pub fn double(&self) -> FieldElement {
+        *self + *self
+    }
+
+    /// self^exp for a public exponent.
+    fn pow(&self, exp: &Limbs) -> FieldElement {
+        let mut result = FieldElement::ONE;
+        for i in (0..256).rev() {
+            result = result.square();
+            if (exp[i / 64] >> (i % 64)) & 1 == 1 {
+                result = result * *self;
+            }
+        }
+        result
+    }
+
+    /// 1 / self with the Fermat's little theorem: self^(p - 2). The inverse
+    /// of zero is zero. The exponent is fixed, so it takes the same time for
+    /// every element.
+    pub fn invert(&self) -> FieldElement {
+        self.pow(&P_MINUS_2)
+    }
+
+    /// Square root for p = 3 mod 4: self^((p + 1) / 4). The result is a root
+    /// only if `self` is a quadratic residue, so the caller should check it.
+    pub fn sqrt(&self) -> FieldElement {
+        self.pow(&P_PLUS_1_DIV_4)
+    }
+
+    /// Reduces a 512 bits product t = hi * 2^256 + lo to lo + hi * C mod p.
+    fn reduce_wide(t: &[u64; 8]) -> FieldElement {
+        let mut r = [0u64; 4];
+        let mut carry = 0;
+        for i in 0..4 {
+            (r[i], carry) = mac(t[i], t[i + 4], C, carry);
+        }
+
+        // the carry is lower than 2^34, fold it again
+        let top = carry as u128 * C as u128;
+        let mut carry;
+        (r[0], carry) = adc(r[0], top as u64, 0);
+        (r[1], carry) = adc(r[1], (top >> 64) as u64, carry);
+        (r[2], carry) = adc(r[2], 0, carry);
+        (r[3], carry) = adc(r[3], 0, carry);
+
+        // a last carry leaves a small number, adding C to it can't overflow
+        (r[0], carry) = adc(r[0], carry * C, 0);
+        (r[1], carry) = adc(r[1], 0, carry);
+        (r[2], carry) = adc(r[2], 0, carry);
+        r[3] += carry;
+
+        FieldElement(limbs::reduce_once(&r, &P))
+    }
+}
+
+impl From<u64> for FieldElement {
+    fn from(value: u64) -> Self {
+        FieldElement([value, 0, 0, 0])
+    }
+}
+
+impl Add for FieldElement {
+    type Output = FieldElement;
+
+    fn add(self, rhs: FieldElement) -> FieldElement {
+        FieldElement(limbs::add_mod(&self.0, &rhs.0, &P))
+    }
+}
+
+impl Sub for FieldElement {
+    type Output = FieldElement;
+
+    fn sub(self, rhs: FieldElement) -> FieldElement {
+        FieldElement(limbs::sub_mod(&self.0, &rhs.0, &P))
+    }
+}
+
+impl Mul for FieldElement {
+    type Output = FieldElement;
+
+    fn mul(self, rhs: FieldElement) -> FieldElement {
+        FieldElement::reduce_wide(&limbs::mul_wide(&self.0, &rhs.0))
+    }
+}
+
+impl Neg for FieldElement {
+    type Output = FieldElement;
+
+    fn neg(self) -> FieldElement {
+        FieldElement::ZERO - self
+    }
+}
+
+impl ConditionallySelectable for FieldElement {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        FieldElement(limbs::select(&a.0, &b.0, choice))
+    }
+}
+
+impl ConstantTimeEq for FieldElement {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
+impl fmt::Debug for FieldElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FieldElement({})", hex::encode(self.to_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secp256k1::Secp256k1Point;
+    use num_bigint::{BigUint, RandBigInt};
+    use rand::thread_rng;
+
+    fn to_biguint(a: &FieldElement) -> BigUint {
+        BigUint::from_bytes_be(&a.to_bytes())
+    }
+
+    fn from_biguint(a: &BigUint) -> FieldElement {
+        let mut bytes = [0u8; 32];
+        let a = a.to_bytes_be();
+        bytes[32 - a.len()..].copy_from_slice(&a);
+        FieldElement::from_bytes(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_constants() {
+        let p = Secp256k1Point::prime();
+        assert_eq!(BigUint::from_bytes_be(&limbs::to_be_bytes(&P)), p);
+        assert_eq!(
+            BigUint::from_bytes_be(&limbs::to_be_bytes(&P_MINUS_2)),
+            &p - 2u32
+        );
+        assert_eq!(
+            BigUint::from_bytes_be(&limbs::to_be_bytes(&P_PLUS_1_DIV_4)),
+            (&p + 1u32) >> 2
+        );
+        assert_eq!(BigUint::from(C), (BigUint::from(1u32) << 256u32) % &p);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let p = Secp256k1Point::prime();
+        let rng = &mut thread_rng();
+
+        let edges = [
+            BigUint::from(0u32),
+            BigUint::from(1u32),
+            BigUint::from(C),
+            &p - 1u32,
+            &p - 2u32,
+            (BigUint::from(1u32) << 255u32) + 3u32,
+        ];
+        let randoms: Vec<BigUint> = (0..20).map(|_| rng.gen_biguint_below(&p)).collect();
+
+        for a in edges.iter().chain(&randoms) {
+            for b in edges.iter().chain(&randoms) {
+                let (fa, fb) = (from_biguint(a), from_biguint(b));
+                assert_eq!(to_biguint(&(fa + fb)), (a + b) % &p);
+                assert_eq!(to_biguint(&(fa - fb)), (a + &p - b) % &p);
+                assert_eq!(to_biguint(&(fa * fb)), (a * b) % &p);
+            }
+            let fa = from_biguint(a);
+            assert_eq!(to_biguint(&-fa), (&p - a) % &p);
+            assert_eq!(to_biguint(&fa.square()), (a * a) % &p);
+            if !fa.is_zero() {
+                assert_eq!(fa * fa.invert(), FieldElement::ONE);
+            }
+            let root = fa.square().sqrt();
+            assert!(root == fa || root == -fa);
+        }
+        assert_eq!(FieldElement::ZERO.invert(), FieldElement::ZERO);
+    }
+
+    #[test]
+    fn test_bytes() {
+        let mut bytes = Secp256k1Point::prime().to_bytes_be();
+        assert!(FieldElement::from_bytes(&bytes.clone().try_into().unwrap()).is_none());
+
+        bytes[31] -= 1;
+        let minus_one = FieldElement::from_bytes(&bytes.clone().try_into().unwrap()).unwrap();
+        assert_eq!(minus_one, -FieldElement::ONE);
+        assert_eq!(minus_one.to_bytes().to_vec(), bytes);
+        assert!(!minus_one.is_odd());
+        assert!(FieldElement::ONE.is_odd());
+    }
+}
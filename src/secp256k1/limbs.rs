@@ -0,0 +1,151 @@
+//! Arithmetic of 256 bits numbers on four 64 bits limbs, least significant
+//! first, shared by the field elements and the scalars of secp256k1.
+//!
+//! None of the helpers branch on the values of the limbs.
+
+use subtle::{Choice, ConditionallySelectable};
+
+pub(super) type Limbs = [u64; 4];
+
+/// a + b + carry, returns the sum and the new carry.
+#[inline(always)]
+pub(super) fn adc(a: u64, b: u64, carry: u64) -> (u64, u64) {
+    let sum = a as u128 + b as u128 + carry as u128;
+    (sum as u64, (sum >> 64) as u64)
+}
+
+/// a - b - borrow, returns the difference and the new borrow (0 or 1).
+#[inline(always)]
+pub(super) fn sbb(a: u64, b: u64, borrow: u64) -> (u64, u64) {
+    let diff = (a as u128).wrapping_sub(b as u128 + borrow as u128);
+    (diff as u64, (diff >> 127) as u64)
+}
+
+/// acc + a * b + carry, returns the low limb and the carry. It can't
+/// overflow: (2^64 - 1) + (2^64 - 1)^2 + (2^64 - 1) = 2^128 - 1.
+#[inline(always)]
+pub(super) fn mac(acc: u64, a: u64, b: u64, carry: u64) -> (u64, u64) {
+    let sum = acc as u128 + a as u128 * b as u128 + carry as u128;
+    (sum as u64, (sum >> 64) as u64)
+}
+
+/// a + b, returns the sum and the carry out of the top limb.
+pub(super) fn add(a: &Limbs, b: &Limbs) -> (Limbs, u64) {
+    let mut sum = [0u64; 4];
+    let mut carry = 0;
+    for i in 0..4 {
+        (sum[i], carry) = adc(a[i], b[i], carry);
+    }
+    (sum, carry)
+}
+
+/// a - b, returns the difference mod 2^256 and the borrow out of the top limb.
+pub(super) fn sub(a: &Limbs, b: &Limbs) -> (Limbs, u64) {
+    let mut diff = [0u64; 4];
+    let mut borrow = 0;
+    for i in 0..4 {
+        (diff[i], borrow) = sbb(a[i], b[i], borrow);
+    }
+    (diff, borrow)
+}
+
+/// The 512 bits product a * b.
+pub(super) fn mul_wide(a: &Limbs, b: &Limbs) -> [u64; 8] {
+    let mut product = [0u64; 8];
+    for i in 0..4 {
+        let mut carry = 0;
+        for j in 0..4 {
+            (product[i + j], carry) = mac(product[i + j], a[i], b[j], carry);
+        }
+        product[i + 4] = carry;
+    }
+    product
+}
+
+/// (a + b) mod m for a and b lower than m.
+pub(super) fn add_mod(a: &Limbs, b: &Limbs, m: &Limbs) -> Limbs {
+    let (sum, carry) = add(a, b);
+    let (reduced, borrow) = sub(&sum, m);
+    // the sum is at least m if it overflowed or if subtracting m didn't
+    let keep_sum = Choice::from((borrow & !carry & 1) as u8);
+    select(&reduced, &sum, keep_sum)
+}
+
+/// (a - b) mod m for a and b lower than m.
+pub(super) fn sub_mod(a: &Limbs, b: &Limbs, m: &Limbs) -> Limbs {
+    let (diff, borrow) = sub(a, b);
+    let mask = borrow.wrapping_neg();
+    let (diff, _) = add(&diff, &m.map(|limb| limb & mask));
+    diff
+}
+
+/// a mod m for a lower than 2m.
+pub(super) fn reduce_once(a: &Limbs, m: &Limbs) -> Limbs {
+    let (reduced, borrow) = sub(a, m);
+    select(&reduced, a, Choice::from(borrow as u8))
+}
+
+/// `b` if `choice` is set, `a` otherwise.
+pub(super) fn select(a: &Limbs, b: &Limbs, choice: Choice) -> Limbs {
+    [0, 1, 2, 3].map(|i| u64::conditional_select(&a[i], &b[i], choice))
+}
+
+/// Whether a < b, in constant time.
+pub(super) fn less_than(a: &Limbs, b: &Limbs) -> Choice {
+    Choice::from(sub(a, b).1 as u8)
+}
+
+pub(super) fn from_be_bytes(bytes: &[u8; 32]) -> Limbs {
+    [0, 1, 2, 3].map(|i| {
+        let start = 32 - 8 * (i + 1);
+        u64::from_be_bytes(bytes[start..start + 8].try_into().unwrap())
+    })
+}
+
+pub(super) fn to_be_bytes(limbs: &Limbs) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    for (i, limb) in limbs.iter().enumerate() {
+        let start = 32 - 8 * (i + 1);
+        bytes[start..start + 8].copy_from_slice(&limb.to_be_bytes());
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+
+    fn to_biguint(limbs: &[u64]) -> BigUint {
+        limbs
+            .iter()
+            .rev()
+            .fold(BigUint::default(), |number, limb| (number << 64u32) + *limb)
+    }
+
+    #[test]
+    fn test_limbs() {
+        let a = [u64::MAX, 3, u64::MAX, 1 << 63];
+        let b = [1, u64::MAX, 7, u64::MAX];
+        let (a_big, b_big) = (to_biguint(&a), to_biguint(&b));
+        let two_256 = BigUint::from(1u32) << 256u32;
+
+        let (sum, carry) = add(&a, &b);
+        assert_eq!(
+            to_biguint(&sum) + (BigUint::from(carry) << 256u32),
+            &a_big + &b_big
+        );
+
+        let (diff, borrow) = sub(&a, &b);
+        assert_eq!(borrow, 1);
+        assert_eq!(to_biguint(&diff), &a_big + &two_256 - &b_big);
+        assert_eq!(sub(&b, &a).1, 0);
+        assert!(bool::from(less_than(&a, &b)));
+        assert!(!bool::from(less_than(&b, &a)));
+
+        assert_eq!(to_biguint(&mul_wide(&a, &b)), &a_big * &b_big);
+
+        assert_eq!(from_be_bytes(&to_be_bytes(&a)), a);
+        assert_eq!(BigUint::from_bytes_be(&to_be_bytes(&a)), a_big);
+    }
+}
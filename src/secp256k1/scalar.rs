@@ -0,0 +1,246 @@
+//! The scalars of secp256k1, the integers modulo the order of the curve
+//! n = 2^256 - 0x14551231950b75fc4402da1732fc9bebf.
+//!
+//! As for the field elements, a scalar is stored fully reduced on four 64 bits
+//! limbs. 2^256 - n has 129 bits, so a 512 bits product is reduced by folding
+//! its high half, multiplied by 2^256 - n, into the low one four times.
+
+use super::limbs::{self, adc, mac, Limbs};
+use rand::{CryptoRng, RngCore};
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+/// The order n.
+const N: Limbs = [
+    0xBFD2_5E8C_D036_4141,
+    0xBAAE_DCE6_AF48_A03B,
+    0xFFFF_FFFF_FFFF_FFFE,
+    0xFFFF_FFFF_FFFF_FFFF,
+];
+
+/// 2^256 - n = 2^256 mod n.
+const NEG_N: [u64; 3] = [0x402D_A173_2FC9_BEBF, 0x4551_2319_50B7_5FC4, 0x1];
+
+/// An integer mod n.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct Scalar(Limbs);
+
+impl Scalar {
+    pub const ZERO: Scalar = Scalar([0; 4]);
+    pub const ONE: Scalar = Scalar([1, 0, 0, 0]);
+
+    /// The big endian `bytes`, of any length, reduced mod n.
+    pub fn from_bytes_reduced(bytes: &[u8]) -> Scalar {
+        let mut padded = vec![0u8; (32 - bytes.len() % 32) % 32];
+        padded.extend_from_slice(bytes);
+
+        // Horner's rule on the chunks of 256 bits: s = s * 2^256 + chunk
+        padded.chunks(32).fold(Scalar::ZERO, |scalar, chunk| {
+            let chunk = limbs::from_be_bytes(chunk.try_into().unwrap());
+            let mut wide = [0u64; 8];
+            wide[..4].copy_from_slice(&chunk);
+            wide[4..].copy_from_slice(&scalar.0);
+            Scalar::reduce_wide(&wide)
+        })
+    }
+
+    /// The big endian encoding of the scalar.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        limbs::to_be_bytes(&self.0)
+    }
+
+    /// A uniformly random scalar: 512 random bits reduced mod n, the bias is
+    /// lower than 2^-256.
+    pub fn random<R: RngCore + CryptoRng + ?Sized>(rng: &mut R) -> Scalar {
+        let mut bytes = [0u8; 64];
+        rng.fill_bytes(&mut bytes);
+        Scalar::from_bytes_reduced(&bytes)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        *self == Scalar::ZERO
+    }
+
+    /// The scalar plus n or 2n, whichever has exactly 257 bits, so a ladder
+    /// over its bits runs the same number of steps for every secret and
+    /// starts from a known top bit. Limbs least significant first.
+    pub(crate) fn blinded(&self) -> [u64; 5] {
+        let (plus_n, carry) = limbs::add(&self.0, &N);
+        let (plus_2n, carry_2n) = limbs::add(&plus_n, &N);
+
+        // keep k + n if it already reaches 2^256, k + 2n otherwise
+        let top = Choice::from(carry as u8);
+        let blinded = limbs::select(&plus_2n, &plus_n, top);
+        let carry = u64::conditional_select(&carry_2n, &carry, top);
+        [blinded[0], blinded[1], blinded[2], blinded[3], carry]
+    }
+
+    /// Reduces a 512 bits number mod n.
+    fn reduce_wide(t: &[u64; 8]) -> Scalar {
+        // t < 2^512, then 2^386, 2^260, 2^256 + 2^133 and 2^256 after every
+        // fold
+        let mut t = *t;
+        for _ in 0..4 {
+            t = Scalar::fold(&t);
+        }
+        let low = [t[0], t[1], t[2], t[3]];
+        Scalar(limbs::reduce_once(&low, &N))
+    }
+
+    /// lo + hi * (2^256 - n) for t = hi * 2^256 + lo.
+    fn fold(t: &[u64; 8]) -> [u64; 8] {
+        let mut r = [t[0], t[1], t[2], t[3], 0, 0, 0, 0];
+        for i in 0..4 {
+            let mut carry = 0;
+            for (j, neg_n) in NEG_N.iter().enumerate() {
+                (r[i + j], carry) = mac(r[i + j], t[i + 4], *neg_n, carry);
+            }
+            for limb in &mut r[i + 3..] {
+                (*limb, carry) = adc(*limb, 0, carry);
+            }
+        }
+        r
+    }
+}
+
+impl From<u64> for Scalar {
+    fn from(value: u64) -> Self {
+        Scalar([value, 0, 0, 0])
+    }
+}
+
+impl Add for Scalar {
+    type Output = Scalar;
+
+    fn add(self, rhs: Scalar) -> Scalar {
+        Scalar(limbs::add_mod(&self.0, &rhs.0, &N))
+    }
+}
+
+impl Sub for Scalar {
+    type Output = Scalar;
+
+    fn sub(self, rhs: Scalar) -> Scalar {
+        Scalar(limbs::sub_mod(&self.0, &rhs.0, &N))
+    }
+}
+
+impl Mul for Scalar {
+    type Output = Scalar;
+
+    fn mul(self, rhs: Scalar) -> Scalar {
+        Scalar::reduce_wide(&limbs::mul_wide(&self.0, &rhs.0))
+    }
+}
+
+impl Neg for Scalar {
+    type Output = Scalar;
+
+    fn neg(self) -> Scalar {
+        Scalar::ZERO - self
+    }
+}
+
+impl ConditionallySelectable for Scalar {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Scalar(limbs::select(&a.0, &b.0, choice))
+    }
+}
+
+impl ConstantTimeEq for Scalar {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
+impl fmt::Debug for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Scalar({})", hex::encode(self.to_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secp256k1::Secp256k1Point;
+    use num_bigint::{BigUint, RandBigInt};
+    use rand::thread_rng;
+
+    fn to_biguint(a: &Scalar) -> BigUint {
+        BigUint::from_bytes_be(&a.to_bytes())
+    }
+
+    #[test]
+    fn test_constants() {
+        let n = Secp256k1Point::n();
+        let two_256 = BigUint::from(1u32) << 256u32;
+        assert_eq!(BigUint::from_bytes_be(&limbs::to_be_bytes(&N)), n);
+        let neg_n = [NEG_N[0], NEG_N[1], NEG_N[2], 0];
+        assert_eq!(
+            BigUint::from_bytes_be(&limbs::to_be_bytes(&neg_n)),
+            two_256 - n
+        );
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let n = Secp256k1Point::n();
+        let rng = &mut thread_rng();
+
+        let edges = [
+            BigUint::from(0u32),
+            BigUint::from(1u32),
+            &n - 1u32,
+            &n - 2u32,
+            (BigUint::from(1u32) << 255u32) + 3u32,
+        ];
+        let randoms: Vec<BigUint> = (0..20).map(|_| rng.gen_biguint_below(&n)).collect();
+
+        for a in edges.iter().chain(&randoms) {
+            for b in edges.iter().chain(&randoms) {
+                let sa = Scalar::from_bytes_reduced(&a.to_bytes_be());
+                let sb = Scalar::from_bytes_reduced(&b.to_bytes_be());
+                assert_eq!(to_biguint(&(sa + sb)), (a + b) % &n);
+                assert_eq!(to_biguint(&(sa - sb)), (a + &n - b) % &n);
+                assert_eq!(to_biguint(&(sa * sb)), (a * b) % &n);
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_bytes_reduced() {
+        let n = Secp256k1Point::n();
+        let rng = &mut thread_rng();
+
+        for bits in [0, 8, 255, 256, 257, 512, 700] {
+            let number = rng.gen_biguint(bits);
+            assert_eq!(
+                to_biguint(&Scalar::from_bytes_reduced(&number.to_bytes_be())),
+                &number % &n
+            );
+        }
+        assert_eq!(Scalar::from_bytes_reduced(&n.to_bytes_be()), Scalar::ZERO);
+        assert_eq!(Scalar::from_bytes_reduced(&[]), Scalar::ZERO);
+        assert_eq!(-Scalar::ONE + Scalar::ONE, Scalar::ZERO);
+    }
+
+    #[test]
+    fn test_blinded() {
+        let n = Secp256k1Point::n();
+        for k in [
+            Scalar::ZERO,
+            Scalar::ONE,
+            -Scalar::ONE,
+            Scalar::from(1u64 << 63),
+        ] {
+            let blinded = k.blinded();
+            let number = blinded
+                .iter()
+                .rev()
+                .fold(BigUint::default(), |number, limb| (number << 64u32) + *limb);
+            assert_eq!(number.bits(), 257);
+            assert_eq!(&number % &n, to_biguint(&k));
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Pending authentication challenges of the interactive protocol.
+//!
+//! Every challenge expires after a configurable TTL, is removed on the first
+//! verification attempt whether it passes or fails, and every user can only
+//! have a limited number of pending challenges. The IDs of the removed
+//! challenges are remembered for another TTL so a late or replayed answer gets
+//! a precise error.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use chaum_pedersen_zkp::protocol::ChallengedVerifier;
+use chaum_pedersen_zkp::CyclicGroup;
+
+/// Default time a challenge can be answered.
+pub const DEFAULT_CHALLENGE_TTL: Duration = Duration::from_secs(60);
+
+/// Default number of pending challenges per user.
+pub const DEFAULT_MAX_CHALLENGES_PER_USER: usize = 5;
+
+/// A challenge `c` sent to a user, waiting for its answer `s`.
+#[derive(Debug, Clone)]
+pub struct AuthInfo<G: CyclicGroup> {
+    pub auth_id: String,
+    pub user: String,
+    /// The keys of the user, the commitment and the challenge.
+    pub verifier: ChallengedVerifier<G>,
+}
+
+/// Why a challenge can't be answered anymore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChallengeError {
+    /// The `auth_id` was never issued or was forgotten.
+    NotFound,
+    /// The TTL of the challenge passed.
+    Expired,
+    /// The challenge was already answered.
+    AlreadyUsed,
+    /// The challenge was dropped because the user requested too many.
+    Evicted,
+}
+
+/// Pending challenges indexed by `auth_id`.
+#[derive(Debug)]
+pub struct ChallengeRegistry<G: CyclicGroup> {
+    ttl: Duration,
+    max_per_user: usize,
+    /// Pending challenges with the time they expire.
+    pending: HashMap<String, (AuthInfo<G>, Instant)>,
+    /// Removed challenges with the reason and the time they can be forgotten.
+    closed: HashMap<String, (ChallengeError, Instant)>,
+}
+
+impl<G: CyclicGroup> ChallengeRegistry<G> {
+    /// * `ttl` - time a challenge can be answered.
+    /// * `max_per_user` - number of pending challenges per user, at least 1.
+    pub fn new(ttl: Duration, max_per_user: usize) -> Self {
+        ChallengeRegistry {
+            ttl,
+            max_per_user: max_per_user.max(1),
+            pending: HashMap::new(),
+            closed: HashMap::new(),
+        }
+    }
+
+    /// Adds a challenge which expires at now + TTL. If the user already has the
+    /// maximum number of pending challenges the oldest one is evicted.
+    pub fn insert(&mut self, info: AuthInfo<G>, now: Instant) {
+        let mut of_user: Vec<(Instant, String)> = self
+            .pending
+            .values()
+            .filter(|(pending, _)| pending.user == info.user)
+            .map(|(pending, expires_at)| (*expires_at, pending.auth_id.clone()))
+            .collect();
+
+        if of_user.len() >= self.max_per_user {
+            of_user.sort();
+            for (_, auth_id) in &of_user[..=of_user.len() - self.max_per_user] {
+                self.close(auth_id, ChallengeError::Evicted, now);
+            }
+        }
+
+        self.pending
+            .insert(info.auth_id.clone(), (info, now + self.ttl));
+    }
+
+    /// Removes the challenge `auth_id` to verify its answer, it can't be used
+    /// again.
+    pub fn take(&mut self, auth_id: &str, now: Instant) -> Result<AuthInfo<G>, ChallengeError> {
+        if let Some((_, expires_at)) = self.pending.get(auth_id) {
+            if *expires_at <= now {
+                self.close(auth_id, ChallengeError::Expired, now);
+                return Err(ChallengeError::Expired);
+            }
+            let (info, _) = self.pending.remove(auth_id).unwrap();
+            self.closed.insert(
+                auth_id.to_string(),
+                (ChallengeError::AlreadyUsed, now + self.ttl),
+            );
+            return Ok(info);
+        }
+
+        match self.closed.get(auth_id) {
+            Some((reason, _)) => Err(*reason),
+            None => Err(ChallengeError::NotFound),
+        }
+    }
+
+    /// Evicts the expired challenges and forgets the old closed ones.
+    pub fn sweep(&mut self, now: Instant) {
+        let expired: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, (_, expires_at))| *expires_at <= now)
+            .map(|(auth_id, _)| auth_id.clone())
+            .collect();
+        for auth_id in expired {
+            self.close(&auth_id, ChallengeError::Expired, now);
+        }
+
+        self.closed.retain(|_, (_, forget_at)| *forget_at > now);
+    }
+
+    fn close(&mut self, auth_id: &str, reason: ChallengeError, now: Instant) {
+        if self.pending.remove(auth_id).is_some() {
+            self.closed
+                .insert(auth_id.to_string(), (reason, now + self.ttl));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chaum_pedersen_zkp::protocol::{Commitment, Statement, Verifier};
+    use chaum_pedersen_zkp::ModPGroup;
+    use num_bigint::BigUint;
+    use rand::thread_rng;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    fn info(auth_id: &str, user: &str) -> AuthInfo<ModPGroup> {
+        let group = ModPGroup::default();
+        let one = BigUint::from(1u32);
+        let (verifier, _) = Verifier::new(&group, Statement::new(&group, one.clone(), one.clone()))
+            .unwrap()
+            .challenge(
+                &group,
+                Commitment {
+                    r1: one.clone(),
+                    r2: one,
+                },
+                &mut thread_rng(),
+            );
+
+        AuthInfo {
+            auth_id: auth_id.to_string(),
+            user: user.to_string(),
+            verifier,
+        }
+    }
+
+    #[test]
+    fn test_single_use() {
+        let now = Instant::now();
+        let mut registry = ChallengeRegistry::new(TTL, 5);
+
+        registry.insert(info("a", "alice"), now);
+        assert_eq!(registry.take("a", now).unwrap().user, "alice");
+        assert_eq!(
+            registry.take("a", now).unwrap_err(),
+            ChallengeError::AlreadyUsed
+        );
+        assert_eq!(
+            registry.take("b", now).unwrap_err(),
+            ChallengeError::NotFound
+        );
+        assert_eq!(registry.pending.len(), 0);
+    }
+
+    #[test]
+    fn test_expiry() {
+        let now = Instant::now();
+        let mut registry = ChallengeRegistry::new(TTL, 5);
+
+        registry.insert(info("a", "alice"), now);
+        registry.insert(info("b", "alice"), now);
+        assert!(registry
+            .take("a", now + TTL - Duration::from_secs(1))
+            .is_ok());
+
+        let later = now + TTL;
+        assert_eq!(
+            registry.take("b", later).unwrap_err(),
+            ChallengeError::Expired
+        );
+        assert_eq!(
+            registry.take("b", later).unwrap_err(),
+            ChallengeError::Expired
+        );
+    }
+
+    #[test]
+    fn test_sweep() {
+        let now = Instant::now();
+        let mut registry = ChallengeRegistry::new(TTL, 5);
+
+        registry.insert(info("a", "alice"), now);
+        registry.insert(info("b", "bob"), now + Duration::from_secs(30));
+
+        registry.sweep(now + TTL);
+        assert_eq!(registry.pending.len(), 1);
+        assert_eq!(
+            registry.take("a", now + TTL).unwrap_err(),
+            ChallengeError::Expired
+        );
+
+        // the closed challenges are forgotten after another TTL, `b` was
+        // closed by this sweep
+        registry.sweep(now + 3 * TTL);
+        assert_eq!(registry.pending.len(), 0);
+        assert_eq!(
+            registry.take("a", now + 3 * TTL).unwrap_err(),
+            ChallengeError::NotFound
+        );
+        assert_eq!(
+            registry.take("b", now + 3 * TTL).unwrap_err(),
+            ChallengeError::Expired
+        );
+
+        registry.sweep(now + 5 * TTL);
+        assert_eq!(
+            registry.take("b", now + 5 * TTL).unwrap_err(),
+            ChallengeError::NotFound
+        );
+    }
+
+    #[test]
+    fn test_max_per_user() {
+        let now = Instant::now();
+        let mut registry = ChallengeRegistry::new(TTL, 2);
+
+        registry.insert(info("a", "alice"), now);
+        registry.insert(info("b", "alice"), now + Duration::from_secs(1));
+        registry.insert(info("c", "bob"), now + Duration::from_secs(2));
+        registry.insert(info("d", "alice"), now + Duration::from_secs(3));
+
+        assert_eq!(registry.pending.len(), 3);
+        assert_eq!(
+            registry.take("a", now).unwrap_err(),
+            ChallengeError::Evicted
+        );
+        assert!(registry.take("b", now).is_ok());
+        assert!(registry.take("c", now).is_ok());
+        assert!(registry.take("d", now).is_ok());
+    }
+}
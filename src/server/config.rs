@@ -0,0 +1,498 @@
+//! Configuration of the server.
+//!
+//! Every option can be given, from the highest to the lowest priority, as a
+//! command line flag, as an environment variable `ZKP_SERVER_<OPTION>` or in
+//! a TOML file selected with `--config`. The options given nowhere take their
+//! default value. Invalid values are reported as a [`ConfigError`].
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use clap::Parser;
+use log::LevelFilter;
+use serde::{Deserialize, Deserializer};
+
+use chaum_pedersen_zkp::{expand_group_flags, Group};
+
+use crate::challenges::{DEFAULT_CHALLENGE_TTL, DEFAULT_MAX_CHALLENGES_PER_USER};
+use crate::key_tables::DEFAULT_MAX_KEY_TABLES;
+use crate::sessions::DEFAULT_SESSION_TTL_SECS;
+use crate::store::StoreKind;
+use crate::throttle::ThrottleConfig;
+use crate::tls::TlsOptions;
+
+/// Default address the server listens on.
+pub const DEFAULT_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+/// Default port the server listens on.
+pub const DEFAULT_PORT: u16 = 50051;
+
+/// The options as they are read from the command line, the environment and
+/// the config file. They are all optional so the sources can be merged.
+#[derive(Debug, Default, Parser, Deserialize)]
+#[command(
+    name = "server",
+    version,
+    about = "Chaum-Pedersen zero knowledge proof authentication server",
+    after_help = "The groups can also be selected with a flag, e.g. --elliptic or --p256."
+)]
+#[serde(default, deny_unknown_fields)]
+struct Options {
+    /// TOML file with the options not given as flags or environment variables
+    #[arg(long, env = "ZKP_SERVER_CONFIG", value_name = "PATH")]
+    #[serde(skip)]
+    config: Option<PathBuf>,
+
+    /// Address to listen on [default: 127.0.0.1]
+    #[arg(long, env = "ZKP_SERVER_ADDRESS")]
+    #[serde(deserialize_with = "parse_option")]
+    address: Option<IpAddr>,
+
+    /// Port to listen on [default: 50051]
+    #[arg(long, env = "ZKP_SERVER_PORT")]
+    port: Option<u16>,
+
+    /// Cyclic group of the protocol: scalar, elliptic, modp2048, modp3072,
+    /// modp4096, ffdhe2048, ffdhe3072, ffdhe4096, p256 or ristretto255
+    /// [default: scalar]
+    #[arg(long, env = "ZKP_SERVER_GROUP")]
+    #[serde(deserialize_with = "parse_option")]
+    group: Option<Group>,
+
+    /// Where the users are kept: memory, file:<path> or sqlite:<path>
+    /// [default: memory]
+    #[arg(long, env = "ZKP_SERVER_STORE")]
+    #[serde(deserialize_with = "parse_option")]
+    store: Option<StoreKind>,
+
+    /// PEM certificate chain of the server, enables TLS
+    #[arg(long, env = "ZKP_SERVER_TLS_CERT", value_name = "PATH")]
+    tls_cert: Option<PathBuf>,
+
+    /// PEM private key of the server certificate
+    #[arg(long, env = "ZKP_SERVER_TLS_KEY", value_name = "PATH")]
+    tls_key: Option<PathBuf>,
+
+    /// PEM CA the client certificates must be signed by, enables mutual TLS
+    #[arg(long, env = "ZKP_SERVER_TLS_CLIENT_CA", value_name = "PATH")]
+    tls_client_ca: Option<PathBuf>,
+
+    /// Seconds a challenge can be answered [default: 60]
+    #[arg(long, env = "ZKP_SERVER_CHALLENGE_TTL", value_name = "SECS")]
+    challenge_ttl: Option<u64>,
+
+    /// Pending challenges per user [default: 5]
+    #[arg(long, env = "ZKP_SERVER_MAX_CHALLENGES")]
+    max_challenges: Option<usize>,
+
+    /// Lifetime of the sessions in seconds [default: 3600]
+    #[arg(long, env = "ZKP_SERVER_SESSION_TTL", value_name = "SECS")]
+    session_ttl: Option<u64>,
+
+    /// Users whose keys get precomputed tables to verify their logins faster,
+    /// 0 disables them [default: 256]
+    #[arg(long, env = "ZKP_SERVER_KEY_TABLES")]
+    key_tables: Option<usize>,
+
+    /// Failed attempts before the first lockout [default: 5]
+    #[arg(long, env = "ZKP_SERVER_MAX_FAILURES")]
+    max_failures: Option<u32>,
+
+    /// Seconds of the first lockout [default: 1]
+    #[arg(long, env = "ZKP_SERVER_LOCKOUT_BASE", value_name = "SECS")]
+    lockout_base: Option<u64>,
+
+    /// Maximum seconds of a lockout [default: 900]
+    #[arg(long, env = "ZKP_SERVER_LOCKOUT_MAX", value_name = "SECS")]
+    lockout_max: Option<u64>,
+
+    /// Seed of a deterministic random generator, for tests and demos only
+    #[arg(long, env = "ZKP_SERVER_SEED")]
+    seed: Option<u64>,
+
+    /// Maximum level of the logs: off, error, warn, info, debug or trace
+    /// [default: info]
+    #[arg(long, env = "ZKP_SERVER_LOG_LEVEL")]
+    #[serde(deserialize_with = "parse_option")]
+    log_level: Option<LevelFilter>,
+}
+
+impl Options {
+    /// Takes the options missing in `self` from `other`.
+    fn or(self, other: Options) -> Options {
+        Options {
+            config: self.config.or(other.config),
+            address: self.address.or(other.address),
+            port: self.port.or(other.port),
+            group: self.group.or(other.group),
+            store: self.store.or(other.store),
+            tls_cert: self.tls_cert.or(other.tls_cert),
+            tls_key: self.tls_key.or(other.tls_key),
+            tls_client_ca: self.tls_client_ca.or(other.tls_client_ca),
+            challenge_ttl: self.challenge_ttl.or(other.challenge_ttl),
+            max_challenges: self.max_challenges.or(other.max_challenges),
+            session_ttl: self.session_ttl.or(other.session_ttl),
+            key_tables: self.key_tables.or(other.key_tables),
+            max_failures: self.max_failures.or(other.max_failures),
+            lockout_base: self.lockout_base.or(other.lockout_base),
+            lockout_max: self.lockout_max.or(other.lockout_max),
+            seed: self.seed.or(other.seed),
+            log_level: self.log_level.or(other.log_level),
+        }
+    }
+}
+
+/// Deserializes an option from its string representation, like it is given in
+/// the command line.
+fn parse_option<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| value.parse().map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// Fails if a number of the configuration is zero.
+fn positive<T: Default + PartialEq>(value: T, name: &str) -> Result<T, ConfigError> {
+    if value == T::default() {
+        Err(ConfigError::Invalid(format!("{} must be positive", name)))
+    } else {
+        Ok(value)
+    }
+}
+
+/// The errors found reading the configuration.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// Invalid flag or environment variable, or the help or the version was
+    /// asked.
+    Cli(clap::Error),
+    /// The config file can't be read.
+    Io(PathBuf, io::Error),
+    /// The config file is not valid TOML or has unknown options.
+    File(PathBuf, toml::de::Error),
+    /// An option has an invalid value.
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Cli(error) => write!(f, "{}", error),
+            ConfigError::Io(path, error) => {
+                write!(
+                    f,
+                    "can't read the config file {}: {}",
+                    path.display(),
+                    error
+                )
+            }
+            ConfigError::File(path, error) => {
+                write!(f, "invalid config file {}: {}", path.display(), error)
+            }
+            ConfigError::Invalid(message) => write!(f, "invalid configuration: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// The configuration of the server with every default applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerConfig {
+    pub addr: SocketAddr,
+    pub group: Group,
+    pub store: StoreKind,
+    /// Certificate of the server and optional client CA, plaintext if `None`.
+    pub tls: Option<TlsOptions>,
+    pub challenge_ttl: Duration,
+    pub max_challenges_per_user: usize,
+    pub session_ttl_secs: u64,
+    /// Number of users whose keys have precomputed tables.
+    pub key_tables: usize,
+    pub throttle: ThrottleConfig,
+    pub seed: Option<u64>,
+    pub log_level: LevelFilter,
+}
+
+impl ServerConfig {
+    /// Reads the configuration from the command line `args`, the environment
+    /// and the config file.
+    pub fn load<I: IntoIterator<Item = String>>(args: I) -> Result<Self, ConfigError> {
+        let options =
+            Options::try_parse_from(expand_group_flags(args)).map_err(ConfigError::Cli)?;
+
+        let options = match &options.config {
+            Some(path) => {
+                let file = fs::read_to_string(path)
+                    .map_err(|error| ConfigError::Io(path.clone(), error))?;
+                let file = toml::from_str(&file)
+                    .map_err(|error| ConfigError::File(path.clone(), error))?;
+                options.or(file)
+            }
+            None => options,
+        };
+
+        ServerConfig::resolve(options)
+    }
+
+    /// Applies the defaults and checks the values.
+    fn resolve(options: Options) -> Result<Self, ConfigError> {
+        let tls = match (options.tls_cert, options.tls_key) {
+            (Some(cert), Some(key)) => Some(TlsOptions {
+                cert,
+                key,
+                client_ca: options.tls_client_ca,
+            }),
+            (None, None) if options.tls_client_ca.is_none() => None,
+            (None, None) => {
+                return Err(ConfigError::Invalid(
+                    "tls-client-ca needs tls-cert and tls-key".to_string(),
+                ))
+            }
+            _ => {
+                return Err(ConfigError::Invalid(
+                    "TLS needs both tls-cert and tls-key".to_string(),
+                ))
+            }
+        };
+
+        let default = ThrottleConfig::default();
+        let throttle = ThrottleConfig {
+            max_failures: positive(
+                options.max_failures.unwrap_or(default.max_failures),
+                "max-failures",
+            )?,
+            base_delay: options
+                .lockout_base
+                .map(|secs| positive(secs, "lockout-base").map(Duration::from_secs))
+                .transpose()?
+                .unwrap_or(default.base_delay),
+            max_delay: options
+                .lockout_max
+                .map(|secs| positive(secs, "lockout-max").map(Duration::from_secs))
+                .transpose()?
+                .unwrap_or(default.max_delay),
+        };
+        if throttle.base_delay > throttle.max_delay {
+            return Err(ConfigError::Invalid(
+                "lockout-base must not be greater than lockout-max".to_string(),
+            ));
+        }
+
+        Ok(ServerConfig {
+            addr: SocketAddr::new(
+                options.address.unwrap_or(DEFAULT_ADDRESS),
+                options.port.unwrap_or(DEFAULT_PORT),
+            ),
+            group: options.group.unwrap_or_default(),
+            store: options.store.unwrap_or_default(),
+            tls,
+            challenge_ttl: options
+                .challenge_ttl
+                .map(|secs| positive(secs, "challenge-ttl").map(Duration::from_secs))
+                .transpose()?
+                .unwrap_or(DEFAULT_CHALLENGE_TTL),
+            max_challenges_per_user: positive(
+                options
+                    .max_challenges
+                    .unwrap_or(DEFAULT_MAX_CHALLENGES_PER_USER),
+                "max-challenges",
+            )?,
+            session_ttl_secs: positive(
+                options.session_ttl.unwrap_or(DEFAULT_SESSION_TTL_SECS),
+                "session-ttl",
+            )?,
+            key_tables: options.key_tables.unwrap_or(DEFAULT_MAX_KEY_TABLES),
+            throttle,
+            seed: options.seed,
+            log_level: options.log_level.unwrap_or(LevelFilter::Info),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(args: &[&str]) -> Result<ServerConfig, ConfigError> {
+        ServerConfig::load(
+            std::iter::once("server")
+                .chain(args.iter().copied())
+                .map(String::from),
+        )
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = load(&[]).unwrap();
+
+        assert_eq!(config.addr, "127.0.0.1:50051".parse().unwrap());
+        assert_eq!(config.group, Group::Scalar);
+        assert_eq!(config.store, StoreKind::Memory);
+        assert_eq!(config.tls, None);
+        assert_eq!(config.challenge_ttl, DEFAULT_CHALLENGE_TTL);
+        assert_eq!(
+            config.max_challenges_per_user,
+            DEFAULT_MAX_CHALLENGES_PER_USER
+        );
+        assert_eq!(config.session_ttl_secs, DEFAULT_SESSION_TTL_SECS);
+        assert_eq!(config.key_tables, DEFAULT_MAX_KEY_TABLES);
+        // max_failures is set in the environment by test_environment
+        assert_eq!(
+            config.throttle.base_delay,
+            ThrottleConfig::default().base_delay
+        );
+        assert_eq!(
+            config.throttle.max_delay,
+            ThrottleConfig::default().max_delay
+        );
+        assert_eq!(config.seed, None);
+        assert_eq!(config.log_level, LevelFilter::Info);
+    }
+
+    #[test]
+    fn test_command_line() {
+        let config = load(&[
+            "--address=0.0.0.0",
+            "--port",
+            "50052",
+            "--p256",
+            "--store=sqlite:/var/lib/zkp/users.db",
+            "--tls-cert=server.pem",
+            "--tls-key=server.key",
+            "--tls-client-ca=ca.pem",
+            "--challenge-ttl=30",
+            "--max-challenges=2",
+            "--session-ttl=600",
+            "--key-tables=0",
+            "--max-failures=3",
+            "--lockout-base=2",
+            "--lockout-max=60",
+            "--seed=42",
+            "--log-level=debug",
+        ])
+        .unwrap();
+
+        assert_eq!(config.addr, "0.0.0.0:50052".parse().unwrap());
+        assert_eq!(config.group, Group::P256);
+        assert_eq!(
+            config.store,
+            StoreKind::Sqlite("/var/lib/zkp/users.db".into())
+        );
+        assert_eq!(
+            config.tls,
+            Some(TlsOptions {
+                cert: "server.pem".into(),
+                key: "server.key".into(),
+                client_ca: Some("ca.pem".into()),
+            })
+        );
+        assert_eq!(config.challenge_ttl, Duration::from_secs(30));
+        assert_eq!(config.max_challenges_per_user, 2);
+        assert_eq!(config.session_ttl_secs, 600);
+        assert_eq!(config.key_tables, 0);
+        assert_eq!(
+            config.throttle,
+            ThrottleConfig {
+                max_failures: 3,
+                base_delay: Duration::from_secs(2),
+                max_delay: Duration::from_secs(60),
+            }
+        );
+        assert_eq!(config.seed, Some(42));
+        assert_eq!(config.log_level, LevelFilter::Debug);
+    }
+
+    #[test]
+    fn test_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("server.toml");
+        fs::write(
+            &path,
+            r#"
+                port = 50053
+                group = "ristretto255"
+                store = "file:users.log"
+                session_ttl = 60
+            "#,
+        )
+        .unwrap();
+        let config_arg = format!("--config={}", path.display());
+
+        let config = load(&[&config_arg]).unwrap();
+        assert_eq!(config.addr, "127.0.0.1:50053".parse().unwrap());
+        assert_eq!(config.group, Group::Ristretto255);
+        assert_eq!(config.store, StoreKind::File("users.log".into()));
+        assert_eq!(config.session_ttl_secs, 60);
+
+        // the flags win over the file
+        let config = load(&[&config_arg, "--elliptic", "--port=50054"]).unwrap();
+        assert_eq!(config.addr.port(), 50054);
+        assert_eq!(config.group, Group::EllipticCurve);
+        assert_eq!(config.session_ttl_secs, 60);
+    }
+
+    #[test]
+    fn test_environment() {
+        // the only test reading this variable
+        std::env::set_var("ZKP_SERVER_MAX_FAILURES", "7");
+        let config = load(&[]);
+        let overridden = load(&["--max-failures=8"]);
+        std::env::remove_var("ZKP_SERVER_MAX_FAILURES");
+
+        assert_eq!(config.unwrap().throttle.max_failures, 7);
+        assert_eq!(overridden.unwrap().throttle.max_failures, 8);
+    }
+
+    #[test]
+    fn test_errors() {
+        let invalid = |args: &[&str]| match load(args) {
+            Err(ConfigError::Invalid(message)) => message,
+            other => panic!("unexpected {:?}", other),
+        };
+
+        assert_eq!(
+            invalid(&["--session-ttl=0"]),
+            "session-ttl must be positive"
+        );
+        assert_eq!(
+            invalid(&["--tls-cert=server.pem"]),
+            "TLS needs both tls-cert and tls-key"
+        );
+        assert_eq!(
+            invalid(&["--lockout-base=60", "--lockout-max=10"]),
+            "lockout-base must not be greater than lockout-max"
+        );
+
+        assert!(matches!(load(&["--group=p384"]), Err(ConfigError::Cli(_))));
+        assert!(matches!(load(&["--port=70000"]), Err(ConfigError::Cli(_))));
+        assert!(matches!(load(&["--store=redis"]), Err(ConfigError::Cli(_))));
+        assert!(matches!(load(&["--unknown"]), Err(ConfigError::Cli(_))));
+        assert!(matches!(
+            load(&["--config=/nonexistent/server.toml"]),
+            Err(ConfigError::Io(_, _))
+        ));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("server.toml");
+        fs::write(&path, "prot = 50051\n").unwrap();
+        assert!(matches!(
+            load(&[&format!("--config={}", path.display())]),
+            Err(ConfigError::File(_, _))
+        ));
+        fs::write(&path, "group = \"p384\"\n").unwrap();
+        assert!(matches!(
+            load(&[&format!("--config={}", path.display())]),
+            Err(ConfigError::File(_, _))
+        ));
+    }
+}
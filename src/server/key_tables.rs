@@ -0,0 +1,184 @@
+//! Precomputed tables of the public keys of the users logging in often.
+//!
+//! Verifying a login proof exponentiates the keys y1 and y2 of the user. Once
+//! a user has logged in successfully `threshold` times, the tables of their
+//! keys are built (see [`FixedBase`]) and their next proofs are verified
+//! several times faster. The tables take memory, a few kilobytes per key on
+//! the elliptic curves and up to a hundred on the largest MODP groups, so only
+//! a limited number of users keep them: the least recently used ones lose
+//! them first.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use chaum_pedersen_zkp::fixed_base::FixedBase;
+use chaum_pedersen_zkp::CyclicGroup;
+
+/// Default number of users whose keys have tables.
+pub const DEFAULT_MAX_KEY_TABLES: usize = 256;
+
+/// Successful logins of a user before the tables of their keys are built.
+pub const KEY_TABLE_THRESHOLD: u32 = 3;
+
+/// The tables of the keys y1 and y2 of a user.
+pub type KeyTablePair<G> = Arc<[FixedBase<G>; 2]>;
+
+/// What is known about the logins of a user.
+struct Entry<G: CyclicGroup> {
+    /// Successful logins counted until the tables are built.
+    logins: u32,
+    tables: Option<KeyTablePair<G>>,
+    last_used: Instant,
+}
+
+/// The tables of the keys of the users, indexed by user name.
+pub struct KeyTables<G: CyclicGroup> {
+    capacity: usize,
+    entries: HashMap<String, Entry<G>>,
+}
+
+impl<G: CyclicGroup> KeyTables<G> {
+    /// * `capacity` - number of users whose keys have tables, 0 disables them.
+    pub fn new(capacity: usize) -> Self {
+        KeyTables {
+            capacity,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// The tables of the keys of `user`, if they were built for the keys `y1`
+    /// and `y2`. The tables of keys that were rotated are dropped.
+    pub fn get(
+        &mut self,
+        user: &str,
+        y1: &G::Element,
+        y2: &G::Element,
+        now: Instant,
+    ) -> Option<KeyTablePair<G>> {
+        let entry = self.entries.get_mut(user)?;
+        let tables = entry.tables.clone()?;
+        if tables[0].base() != y1 || tables[1].base() != y2 {
+            entry.tables = None;
+            entry.logins = 0;
+            return None;
+        }
+
+        entry.last_used = now;
+        Some(tables)
+    }
+
+    /// Counts a successful login of `user` verified without tables. Returns
+    /// whether the tables of their keys should be built now.
+    pub fn record_login(&mut self, user: &str, now: Instant) -> bool {
+        if self.capacity == 0 {
+            return false;
+        }
+
+        let entry = self.entries.entry(user.to_string()).or_insert(Entry {
+            logins: 0,
+            tables: None,
+            last_used: now,
+        });
+        entry.logins += 1;
+        entry.last_used = now;
+        entry.tables.is_none() && entry.logins >= KEY_TABLE_THRESHOLD
+    }
+
+    /// Keeps the tables of the keys of `user`, dropping the ones of the least
+    /// recently used user if there are already `capacity` of them.
+    pub fn insert(&mut self, user: &str, tables: KeyTablePair<G>, now: Instant) {
+        let with_tables: Vec<(Instant, &String)> = self
+            .entries
+            .iter()
+            .filter(|(other, entry)| entry.tables.is_some() && *other != user)
+            .map(|(other, entry)| (entry.last_used, other))
+            .collect();
+
+        if with_tables.len() >= self.capacity {
+            if let Some((_, oldest)) = with_tables.into_iter().min() {
+                let oldest = oldest.clone();
+                let entry = self.entries.get_mut(&oldest).unwrap();
+                entry.tables = None;
+                entry.logins = 0;
+            }
+        }
+
+        let entry = self.entries.entry(user.to_string()).or_insert(Entry {
+            logins: 0,
+            tables: None,
+            last_used: now,
+        });
+        entry.tables = Some(tables);
+        entry.last_used = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chaum_pedersen_zkp::{exponentiates_points, Ristretto255Group};
+    use std::time::Duration;
+
+    fn tables(group: &Ristretto255Group, x: u64) -> KeyTablePair<Ristretto255Group> {
+        let (y1, y2) = exponentiates_points(group, &x.into());
+        Arc::new([FixedBase::new(group, &y1), FixedBase::new(group, &y2)])
+    }
+
+    #[test]
+    fn test_built_after_threshold() {
+        let group = Ristretto255Group::default();
+        let mut key_tables = KeyTables::new(2);
+        let now = Instant::now();
+
+        for _ in 1..KEY_TABLE_THRESHOLD {
+            assert!(!key_tables.record_login("alice", now));
+        }
+        assert!(key_tables.record_login("alice", now));
+
+        let alice = tables(&group, 300);
+        let (y1, y2) = (*alice[0].base(), *alice[1].base());
+        assert!(key_tables.get("alice", &y1, &y2, now).is_none());
+        key_tables.insert("alice", alice, now);
+        assert!(key_tables.get("alice", &y1, &y2, now).is_some());
+
+        // rotated keys drop the tables
+        let (y3, y4) = exponentiates_points(&group, &301u64.into());
+        assert!(key_tables.get("alice", &y3, &y4, now).is_none());
+        assert!(key_tables.get("alice", &y1, &y2, now).is_none());
+        assert!(!key_tables.record_login("alice", now));
+    }
+
+    #[test]
+    fn test_least_recently_used_evicted() {
+        let group = Ristretto255Group::default();
+        let mut key_tables = KeyTables::new(2);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        let users = [("alice", tables(&group, 1)), ("bob", tables(&group, 2))];
+        for (i, (user, pair)) in users.iter().enumerate() {
+            key_tables.insert(user, Arc::clone(pair), at(i as u64));
+        }
+
+        // alice is used again, bob loses his tables
+        let get = |key_tables: &mut KeyTables<_>, user, pair: &KeyTablePair<_>, now| {
+            key_tables
+                .get(user, pair[0].base(), pair[1].base(), now)
+                .is_some()
+        };
+        assert!(get(&mut key_tables, "alice", &users[0].1, at(2)));
+        key_tables.insert("carol", tables(&group, 3), at(3));
+
+        assert!(get(&mut key_tables, "alice", &users[0].1, at(4)));
+        assert!(!get(&mut key_tables, "bob", &users[1].1, at(4)));
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut key_tables = KeyTables::<Ristretto255Group>::new(0);
+        for _ in 0..2 * KEY_TABLE_THRESHOLD {
+            assert!(!key_tables.record_login("alice", Instant::now()));
+        }
+    }
+}
@@ -1,51 +1,330 @@
-use num_bigint::BigUint;
+use log::{error, info, warn, LevelFilter};
+use rand::rngs::{OsRng, StdRng};
+use rand::{CryptoRng, RngCore, SeedableRng};
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::env;
-use std::sync::Mutex;
-use tonic::{transport::Server, Code, Request, Response, Status};
+use std::net::SocketAddr;
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tonic::transport::{Server, ServerTlsConfig};
+use tonic::{Code, Request, Response, Status};
 
+use chaum_pedersen_zkp::fiat_shamir::{
+    login_context, rotate_key_context, verify_non_interactive, verify_non_interactive_precomputed,
+    NonInteractiveProof,
+};
+use chaum_pedersen_zkp::fixed_base::FixedBase;
+use chaum_pedersen_zkp::password::MIN_SALT_LEN;
+use chaum_pedersen_zkp::protocol::{Commitment, Response as ProofResponse, Statement, Verifier};
 use chaum_pedersen_zkp::{
-    get_constants, get_random_number, get_random_string, parse_group_from_command_line, verify,
-    Group, Point,
+    get_random_string, CyclicGroup, Error, Group, ModPGroup, P256Group, Ristretto255Group,
+    Secp256k1Group,
 };
 
 pub mod zkp_auth {
     include!("../zkp_auth.rs");
 }
 
+mod challenges;
+mod config;
+mod key_tables;
+mod sessions;
+mod store;
+mod throttle;
+mod tls;
+mod verifier;
+
+use challenges::{AuthInfo, ChallengeError, ChallengeRegistry};
+use config::{ConfigError, ServerConfig};
+use key_tables::KeyTables;
+use sessions::{SessionError, SessionStore, SESSION_ID_LEN};
+use store::{StoreError, UserRecord, UserStore};
+use throttle::{Throttle, ThrottleConfig, ThrottleKey};
+use verifier::{QueueFull, VerifyQueue, DEFAULT_MAX_BATCH, DEFAULT_QUEUE_CAPACITY};
+
 use zkp_auth::auth_server::{Auth, AuthServer};
 use zkp_auth::{
     AuthenticationAnswerRequest, AuthenticationAnswerResponse, AuthenticationChallengeRequest,
-    AuthenticationChallengeResponse, RegisterRequest, RegisterResponse,
+    AuthenticationChallengeResponse, GetParametersRequest, GetParametersResponse, GetSaltRequest,
+    GetSaltResponse, LoginRequest, LoginResponse, LogoutRequest, LogoutResponse,
+    RefreshSessionRequest, RefreshSessionResponse, RegisterRequest, RegisterResponse,
+    RotateKeyRequest, RotateKeyResponse, ValidateSessionRequest, ValidateSessionResponse,
 };
 
-#[derive(Default)]
-pub struct AuthImpl {
-    user_registry: Mutex<HashMap<String, UserInfo>>,
-    auth_registry: Mutex<HashMap<String, AuthInfo>>,
-    group: Group,
+/// Maximum difference in seconds between the timestamp of a non-interactive
+/// proof (login or key rotation) and the server clock.
+const LOGIN_WINDOW_SECS: u64 = 60;
+
+/// Period of the task evicting the expired sessions.
+const SESSION_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Period of the task forgetting the old failed attempts.
+const THROTTLE_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A cryptographically secure random generator that can be shared between
+/// the requests.
+pub trait ServerRng: RngCore + CryptoRng + Send {}
+
+impl<R: RngCore + CryptoRng + Send> ServerRng for R {}
+
+pub struct AuthImpl<G: CyclicGroup> {
+    /// Registered users, with their public keys encoded.
+    user_registry: Box<dyn UserStore>,
+    /// Pending challenges, shared with the task evicting the expired ones.
+    auth_registry: Arc<Mutex<ChallengeRegistry<G>>>,
+    /// Challenges `c` of the non-interactive proofs already used to login or
+    /// to rotate a key with the time they were received, to reject replayed
+    /// proofs.
+    login_registry: Mutex<HashMap<Vec<u8>, u64>>,
+    /// Precomputed tables of the keys of the users logging in often.
+    key_tables: Mutex<KeyTables<G>>,
+    /// Sessions opened by a successful login, shared with the task evicting
+    /// the expired ones.
+    session_registry: Arc<Mutex<SessionStore>>,
+    /// Failed attempts per user and per peer address, shared with the task
+    /// forgetting the old ones.
+    throttle: Arc<Mutex<Throttle>>,
+    /// Source of every challenge `c` and every auth and session ID.
+    rng: Mutex<Box<dyn ServerRng>>,
+    /// Queue verifying the answers to the challenges in batches.
+    verify_queue: VerifyQueue<G>,
+    /// Identifier of `group` announced to the clients.
+    group_id: Group,
+    group: Arc<G>,
 }
 
 #[derive(Debug, Clone)]
-pub struct UserInfo {
+pub struct UserInfo<G: CyclicGroup> {
     pub user: String,
-    pub y1: Point,
-    pub y2: Point,
+    pub y1: G::Element,
+    pub y2: G::Element,
+    /// Salt of the password of the user, x = Argon2id(password, salt) mod q.
+    pub salt: Vec<u8>,
 }
 
-#[derive(Debug, Clone)]
-pub struct AuthInfo {
-    pub auth_id: String,
-    pub y1: Point,
-    pub y2: Point,
-    pub r1: Point,
-    pub r2: Point,
-    pub c: BigUint,
-    pub session_id: String,
+/// Returns the current unix time in seconds.
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System clock before unix epoch")
+        .as_secs()
+}
+
+/// Maps an error of the user store into a gRPC status.
+fn store_error(error: StoreError) -> Status {
+    error!("{}", error);
+    Status::new(Code::Internal, "(Server): user store unavailable")
+}
+
+/// Maps the reason a challenge can't be answered into a gRPC status.
+fn challenge_error(error: ChallengeError, auth_id: &str) -> Status {
+    warn!("auth_id {}: {:?}", auth_id, error);
+    match error {
+        ChallengeError::NotFound => Status::new(Code::NotFound, "auth_id doesn't exist"),
+        ChallengeError::Expired => protocol_error(Error::ChallengeExpired),
+        ChallengeError::AlreadyUsed => {
+            Status::new(Code::FailedPrecondition, "(Server): challenge already used")
+        }
+        ChallengeError::Evicted => Status::new(
+            Code::Aborted,
+            "(Server): challenge discarded, too many pending challenges for the user",
+        ),
+    }
+}
+
+/// The brute force counters of a request: the user, if known, and the address
+/// of the peer, if the transport gives it.
+fn throttle_keys(user: Option<&str>, peer: Option<SocketAddr>) -> Vec<ThrottleKey> {
+    user.map(|user| ThrottleKey::User(user.to_string()))
+        .into_iter()
+        .chain(peer.map(|peer| ThrottleKey::Peer(peer.ip())))
+        .collect()
+}
+
+/// Status returned while a user or a peer is locked out, with the seconds to
+/// wait in the `retry-after` metadata.
+fn throttled(retry_after: Duration) -> Status {
+    let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    warn!("locked out for {} seconds", secs);
+
+    let mut status = Status::new(
+        Code::ResourceExhausted,
+        format!(
+            "(Server): too many failed attempts, retry after {} seconds",
+            secs
+        ),
+    );
+    status.metadata_mut().insert("retry-after", secs.into());
+    status
+}
+
+/// Maps the reason a session can't be used into a gRPC status.
+fn session_error(error: SessionError) -> Status {
+    match error {
+        SessionError::NotFound => Status::new(Code::NotFound, "(Server): session not found"),
+        SessionError::Expired => Status::new(Code::Unauthenticated, "(Server): session expired"),
+    }
+}
+
+/// Maps an error of the protocol into a gRPC status, so the clients can tell
+/// a malformed request from an unknown user or a wrong proof.
+fn protocol_error(error: Error) -> Status {
+    // the other errors are logged with their context where they are found
+    if error.is_encoding() {
+        warn!("invalid request: {}", error);
+    }
+    let code = match error {
+        Error::UnknownUser => Code::NotFound,
+        Error::InvalidProof => Code::Unauthenticated,
+        Error::ChallengeExpired | Error::GroupMismatch { .. } => Code::FailedPrecondition,
+        Error::InvalidArguments
+        | Error::InvalidLength
+        | Error::InvalidPrefix
+        | Error::NotOnCurve
+        | Error::OutOfRange
+        | Error::IdentityElement
+        | Error::NotInSubgroup
+        | Error::NonCanonical
+        | Error::ScalarOutOfRange
+        | Error::WrongGenerators => Code::InvalidArgument,
+    };
+    Status::new(code, format!("(Server): {}", error))
+}
+
+impl<G: CyclicGroup> AuthImpl<G> {
+    /// Reads a user from the store and decodes its public keys.
+    fn get_user(&self, user: &str) -> Result<Option<UserInfo<G>>, StoreError> {
+        match self.user_registry.get(user)? {
+            Some(record) => self.decode_user(&record).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Decodes the public keys of a stored user.
+    fn decode_user(&self, record: &UserRecord) -> Result<UserInfo<G>, StoreError> {
+        let decode = |bytes: &[u8]| {
+            self.group.decode(bytes).map_err(|error| {
+                StoreError::Corrupted(format!("invalid key of user {}: {:?}", record.user, error))
+            })
+        };
+
+        Ok(UserInfo {
+            user: record.user.clone(),
+            y1: decode(&record.y1)?,
+            y2: decode(&record.y2)?,
+            salt: record.salt.clone(),
+        })
+    }
+
+    /// Fails with the time to wait if any of the keys is locked out.
+    fn check_throttle(&self, keys: &[ThrottleKey]) -> Result<(), Duration> {
+        self.throttle.lock().unwrap().check(keys, Instant::now())
+    }
+
+    fn record_failure(&self, keys: &[ThrottleKey]) {
+        self.throttle
+            .lock()
+            .unwrap()
+            .record_failure(keys, Instant::now());
+    }
+
+    /// Verifies a login proof of `user`, with the tables of their keys if they
+    /// log in often.
+    fn verify_login(
+        &self,
+        user_info: &UserInfo<G>,
+        proof: &NonInteractiveProof<G>,
+        context: &[u8],
+    ) -> bool {
+        let group = &*self.group;
+        let (user, y1, y2) = (&user_info.user, &user_info.y1, &user_info.y2);
+
+        let tables = self
+            .key_tables
+            .lock()
+            .unwrap()
+            .get(user, y1, y2, Instant::now());
+        if let Some(tables) = tables {
+            let [t1, t2] = &*tables;
+            return verify_non_interactive_precomputed(group, t1, t2, proof, context);
+        }
+
+        if !verify_non_interactive(group, y1, y2, proof, context) {
+            return false;
+        }
+        if self
+            .key_tables
+            .lock()
+            .unwrap()
+            .record_login(user, Instant::now())
+        {
+            // built outside of the lock, it takes a few exponentiations
+            let tables = Arc::new([FixedBase::new(group, y1), FixedBase::new(group, y2)]);
+            info!("Precomputed the key tables of user {}", user);
+            self.key_tables
+                .lock()
+                .unwrap()
+                .insert(user, tables, Instant::now());
+        }
+        true
+    }
+
+    /// Reserves the challenge `c` of a non-interactive proof received at
+    /// `now` so it can't be replayed, returns false if it was already used.
+    /// The proof is verified after the lock is released, see `release_proof`.
+    fn reserve_proof(&self, c: Vec<u8>, now: u64) -> bool {
+        let mut login_registry = self.login_registry.lock().unwrap();
+
+        // forget the proofs that are already out of the window
+        login_registry.retain(|_, received| now.abs_diff(*received) <= 2 * LOGIN_WINDOW_SECS);
+
+        match login_registry.entry(c) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(now);
+                true
+            }
+        }
+    }
+
+    /// Releases a proof reserved with `reserve_proof` that was rejected.
+    fn release_proof(&self, c: &[u8]) {
+        self.login_registry.lock().unwrap().remove(c);
+    }
+
+    /// Opens a new session for `user` and returns its ID.
+    fn open_session(&self, user: &str) -> String {
+        let session_id = get_random_string(SESSION_ID_LEN, &mut *self.rng.lock().unwrap());
+        self.throttle.lock().unwrap().record_success(user);
+        self.session_registry.lock().unwrap().create(
+            session_id.clone(),
+            user.to_string(),
+            now_secs(),
+        );
+        session_id
+    }
 }
 
 #[tonic::async_trait]
-impl Auth for AuthImpl {
+impl<G> Auth for AuthImpl<G>
+where
+    G: CyclicGroup + Send + Sync + 'static,
+{
+    async fn get_parameters(
+        &self,
+        _request: Request<GetParametersRequest>,
+    ) -> Result<Response<GetParametersResponse>, Status> {
+        Ok(Response::new(GetParametersResponse {
+            group: self.group_id.to_string(),
+            p: self.group.modulus().to_bytes_be(),
+            q: self.group.order().to_bytes_be(),
+            g: self.group.encode(self.group.generator()),
+            h: self.group.encode(self.group.second_generator()),
+        }))
+    }
+
     async fn register(
         &self,
         request: Request<RegisterRequest>,
@@ -54,62 +333,227 @@ impl Auth for AuthImpl {
         let response = RegisterResponse {};
 
         let user_name = register_request.user.clone();
-        println!("[SERVER] Registering user: {}", user_name);
+        info!("Registering user: {}", user_name);
 
-        // we add a new UserInfo, replace old y1 & y2 if the user was already register.
-        let user_info = UserInfo {
-            user: user_name,
-            y1: Point::deserialize(register_request.y1, &self.group),
-            y2: Point::deserialize(register_request.y2, &self.group),
-        };
+        if register_request.salt.len() < MIN_SALT_LEN {
+            warn!("salt too short user: {}", user_name);
+            return Err(Status::new(
+                Code::InvalidArgument,
+                "(Server): salt too short",
+            ));
+        }
 
-        let user_registry = &mut *self.user_registry.lock().unwrap();
-        user_registry.insert(register_request.user, user_info);
+        // only valid elements are stored
+        self.group
+            .decode(&register_request.y1)
+            .map_err(protocol_error)?;
+        self.group
+            .decode(&register_request.y2)
+            .map_err(protocol_error)?;
+
+        // an existing user can only change its keys with RotateKey
+        let inserted = self
+            .user_registry
+            .insert(UserRecord {
+                user: user_name.clone(),
+                y1: register_request.y1,
+                y2: register_request.y2,
+                salt: register_request.salt,
+            })
+            .map_err(store_error)?;
+
+        if !inserted {
+            warn!("user {} already registered", user_name);
+            return Err(Status::new(
+                Code::AlreadyExists,
+                "(Server): user already registered, use RotateKey to change its keys",
+            ));
+        }
 
         Ok(Response::new(response))
     }
 
+    async fn rotate_key(
+        &self,
+        request: Request<RotateKeyRequest>,
+    ) -> Result<Response<RotateKeyResponse>, Status> {
+        let peer = request.remote_addr();
+        let rotate_request = request.into_inner();
+
+        let user = rotate_request.user;
+        let timestamp = rotate_request.timestamp;
+        info!("Rotating the key of user: {}", user);
+
+        let keys = throttle_keys(Some(&user), peer);
+        self.check_throttle(&keys).map_err(throttled)?;
+
+        let now = now_secs();
+        if now.abs_diff(timestamp) > LOGIN_WINDOW_SECS {
+            warn!("Key rotation proof of user {} expired", user);
+            return Err(protocol_error(Error::ChallengeExpired));
+        }
+
+        if rotate_request.salt.len() < MIN_SALT_LEN {
+            warn!("salt too short user: {}", user);
+            return Err(Status::new(
+                Code::InvalidArgument,
+                "(Server): salt too short",
+            ));
+        }
+
+        // only valid elements are stored
+        self.group
+            .decode(&rotate_request.y1)
+            .map_err(protocol_error)?;
+        self.group
+            .decode(&rotate_request.y2)
+            .map_err(protocol_error)?;
+
+        let proof = NonInteractiveProof {
+            c: self
+                .group
+                .decode_scalar(&rotate_request.c)
+                .map_err(protocol_error)?,
+            s: self
+                .group
+                .decode_scalar(&rotate_request.s)
+                .map_err(protocol_error)?,
+        };
+
+        // the raw record is kept to replace it only if nobody changed it
+        let current = match self.user_registry.get(&user).map_err(store_error)? {
+            Some(current) => current,
+            None => {
+                warn!("User {} not found", user);
+                self.record_failure(&keys);
+                return Err(protocol_error(Error::UnknownUser));
+            }
+        };
+        let user_info = self.decode_user(&current).map_err(store_error)?;
+
+        let c = self.group.scalar_to_bytes(&proof.c);
+        if !self.reserve_proof(c.clone(), now) {
+            warn!("Replayed key rotation proof for user {}", user);
+            self.record_failure(&throttle_keys(None, peer));
+            return Err(Status::new(
+                Code::InvalidArgument,
+                "(Server): key rotation proof already used",
+            ));
+        }
+
+        // the proof is made with the current secret and commits to the new keys
+        let context = rotate_key_context(
+            &user,
+            timestamp,
+            &rotate_request.y1,
+            &rotate_request.y2,
+            &rotate_request.salt,
+        );
+        if !verify_non_interactive(&*self.group, &user_info.y1, &user_info.y2, &proof, &context) {
+            warn!("invalid key rotation proof user: {}", user);
+            self.release_proof(&c);
+            self.record_failure(&keys);
+            return Err(protocol_error(Error::InvalidProof));
+        }
+
+        let replaced = self
+            .user_registry
+            .replace(
+                &current,
+                UserRecord {
+                    user: user.clone(),
+                    y1: rotate_request.y1,
+                    y2: rotate_request.y2,
+                    salt: rotate_request.salt,
+                },
+            )
+            .map_err(store_error)?;
+        if !replaced {
+            warn!("Keys of user {} changed during the rotation", user);
+            return Err(Status::new(
+                Code::Aborted,
+                "(Server): the keys changed during the rotation, retry with the new ones",
+            ));
+        }
+
+        // the sessions opened with the old key are not trusted anymore
+        let revoked = self.session_registry.lock().unwrap().revoke_user(&user);
+        info!("Key rotated user: {}, {} sessions revoked", user, revoked);
+        Ok(Response::new(RotateKeyResponse {}))
+    }
+
+    async fn get_salt(
+        &self,
+        request: Request<GetSaltRequest>,
+    ) -> Result<Response<GetSaltResponse>, Status> {
+        let user = request.into_inner().user;
+
+        match self.get_user(&user).map_err(store_error)? {
+            Some(user_info) => Ok(Response::new(GetSaltResponse {
+                salt: user_info.salt,
+            })),
+            None => {
+                warn!("User {} not found", user);
+                Err(protocol_error(Error::UnknownUser))
+            }
+        }
+    }
+
     async fn create_authentication_challenge(
         &self,
         request: Request<AuthenticationChallengeRequest>,
     ) -> Result<Response<AuthenticationChallengeResponse>, Status> {
+        let peer = request.remote_addr();
         let register_request = request.into_inner();
 
         let user = register_request.user;
 
-        let r1 = Point::deserialize(register_request.r1, &self.group);
-        let r2 = Point::deserialize(register_request.r2, &self.group);
+        let keys = throttle_keys(Some(&user), peer);
+        self.check_throttle(&keys).map_err(throttled)?;
+
+        let commitment = Commitment {
+            r1: self
+                .group
+                .decode(&register_request.r1)
+                .map_err(protocol_error)?,
+            r2: self
+                .group
+                .decode(&register_request.r2)
+                .map_err(protocol_error)?,
+        };
+
+        let user_info = self.get_user(&user).map_err(store_error)?;
 
-        let user_registry = &mut *self.user_registry.lock().unwrap();
         let auth_registry = &mut *self.auth_registry.lock().unwrap();
+        let rng = &mut *self.rng.lock().unwrap();
 
-        let auth_id = get_random_string(10);
+        let auth_id = get_random_string(10, rng);
 
-        if let Some(user_info) = user_registry.get(&user) {
-            let c = get_random_number();
+        if let Some(user_info) = user_info {
+            let statement = Statement::new(&*self.group, user_info.y1, user_info.y2);
+            let (verifier, challenge) = Verifier::new(&*self.group, statement)
+                .map_err(protocol_error)?
+                .challenge(&*self.group, commitment, rng);
 
             auth_registry.insert(
-                auth_id.clone(),
                 AuthInfo {
                     auth_id: auth_id.clone(),
-                    y1: user_info.y1.clone(),
-                    y2: user_info.y2.clone(),
-                    r1,
-                    r2,
-                    c: c.clone(),
-                    session_id: String::new(),
+                    user,
+                    verifier,
                 },
+                Instant::now(),
             );
 
             let response = AuthenticationChallengeResponse {
                 auth_id,
-                c: c.to_bytes_be(),
+                c: challenge.to_bytes(&*self.group),
             };
 
             Ok(Response::new(response))
         } else {
-            println!("[SERVER] User {} not found\n", user);
-            return Err(Status::new(Code::NotFound, "(Server) User not found"));
+            warn!("User {} not found", user);
+            self.record_failure(&keys);
+            Err(protocol_error(Error::UnknownUser))
         }
     }
 
@@ -117,79 +561,910 @@ impl Auth for AuthImpl {
         &self,
         request: Request<AuthenticationAnswerRequest>,
     ) -> Result<Response<AuthenticationAnswerResponse>, Status> {
+        let peer = request.remote_addr();
         let register_request = request.into_inner();
 
         let auth_id = register_request.auth_id;
-        let s = register_request.s;
-        let s = BigUint::from_bytes_be(&s);
 
-        let auth_registry = &mut *self.auth_registry.lock().unwrap();
+        let peer_keys = throttle_keys(None, peer);
+        self.check_throttle(&peer_keys).map_err(throttled)?;
+
+        // the challenge is removed whether the answer is right, wrong or
+        // malformed
+        let info = self
+            .auth_registry
+            .lock()
+            .unwrap()
+            .take(&auth_id, Instant::now())
+            .map_err(|error| {
+                self.record_failure(&peer_keys);
+                challenge_error(error, &auth_id)
+            })?;
 
-        let (p, _, g, h) = get_constants(&self.group);
-
-        if let Some(info) = auth_registry.get_mut(&auth_id) {
-            match verify(
-                &info.r1, &info.r2, &info.y1, &info.y2, &g, &h, &info.c, &s, &p,
-            ) {
-                Ok(verification) => {
-                    if verification {
-                        let session_id = get_random_string(10);
-                        info.session_id = session_id.clone();
-
-                        let response = AuthenticationAnswerResponse {
-                            session_id: session_id.clone(),
-                        };
-
-                        println!("[SERVER] Successful login auth_id: {}\n", auth_id);
-                        Ok(Response::new(response))
-                    } else {
-                        println!(
-                            "[SERVER] Error: challenge not solved properly auth_id: {}\n",
-                            auth_id
-                        );
-
-                        return Err(Status::new(
-                            Code::NotFound,
-                            "(Server): challenge not solved properly",
-                        ));
-                    }
-                }
-                Err(error) => {
-                    println!(
-                        "[SERVER] algorithm error during verification: {:?}\n",
-                        error
-                    );
-
-                    return Err(Status::new(
-                        Code::NotFound,
-                        "(Server): algorithm error during verification",
-                    ));
-                }
+        let keys = throttle_keys(Some(&info.user), peer);
+        self.check_throttle(&keys).map_err(throttled)?;
+
+        // a malformed answer burns the challenge like a wrong one, so it
+        // counts as a failure too
+        let response =
+            ProofResponse::from_bytes(&*self.group, &register_request.s).map_err(|error| {
+                self.record_failure(&keys);
+                protocol_error(error)
+            })?;
+        let transcript = info.verifier.transcript(response);
+        let valid = self
+            .verify_queue
+            .verify(transcript)
+            .await
+            .map_err(|QueueFull| {
+                warn!(
+                    "Verification queue full, answer of auth_id {} refused",
+                    auth_id
+                );
+                Status::new(
+                    Code::ResourceExhausted,
+                    "(Server): too many answers waiting to be verified, retry later",
+                )
+            })?;
+        if valid {
+            let session_id = self.open_session(&info.user);
+
+            let response = AuthenticationAnswerResponse { session_id };
+
+            info!("Successful login auth_id: {}", auth_id);
+            Ok(Response::new(response))
+        } else {
+            warn!("challenge not solved properly auth_id: {}", auth_id);
+            self.record_failure(&keys);
+
+            Err(protocol_error(Error::InvalidProof))
+        }
+    }
+
+    async fn login(
+        &self,
+        request: Request<LoginRequest>,
+    ) -> Result<Response<LoginResponse>, Status> {
+        let peer = request.remote_addr();
+        let login_request = request.into_inner();
+
+        let user = login_request.user;
+        let timestamp = login_request.timestamp;
+
+        let keys = throttle_keys(Some(&user), peer);
+        self.check_throttle(&keys).map_err(throttled)?;
+
+        let now = now_secs();
+        if now.abs_diff(timestamp) > LOGIN_WINDOW_SECS {
+            warn!("Login proof of user {} expired", user);
+            return Err(protocol_error(Error::ChallengeExpired));
+        }
+
+        let proof = NonInteractiveProof {
+            c: self
+                .group
+                .decode_scalar(&login_request.c)
+                .map_err(protocol_error)?,
+            s: self
+                .group
+                .decode_scalar(&login_request.s)
+                .map_err(protocol_error)?,
+        };
+
+        let user_info = self.get_user(&user).map_err(store_error)?;
+
+        let c = self.group.scalar_to_bytes(&proof.c);
+        if !self.reserve_proof(c.clone(), now) {
+            warn!("Replayed login proof for user {}", user);
+            self.record_failure(&throttle_keys(None, peer));
+            return Err(Status::new(
+                Code::InvalidArgument,
+                "(Server): login proof already used",
+            ));
+        }
+
+        let user_info = match user_info {
+            Some(user_info) => user_info,
+            None => {
+                warn!("User {} not found", user);
+                self.release_proof(&c);
+                self.record_failure(&keys);
+                return Err(protocol_error(Error::UnknownUser));
             }
+        };
+
+        let context = login_context(&user, timestamp);
+        if self.verify_login(&user_info, &proof, &context) {
+            let session_id = self.open_session(&user);
+            info!("Successful login user: {}", user);
+            Ok(Response::new(LoginResponse { session_id }))
         } else {
-            println!("[SERVER] auth_id {} not found", auth_id);
-            return Err(Status::new(Code::NotFound, "auth_id doesn't exist"));
+            warn!("invalid login proof user: {}", user);
+            self.release_proof(&c);
+            self.record_failure(&keys);
+            Err(protocol_error(Error::InvalidProof))
+        }
+    }
+
+    async fn validate_session(
+        &self,
+        request: Request<ValidateSessionRequest>,
+    ) -> Result<Response<ValidateSessionResponse>, Status> {
+        let session_id = request.into_inner().session_id;
+
+        let session = self
+            .session_registry
+            .lock()
+            .unwrap()
+            .validate(&session_id, now_secs())
+            .map_err(session_error)?;
+
+        Ok(Response::new(ValidateSessionResponse {
+            user: session.user,
+            expires_at: session.expires_at,
+        }))
+    }
+
+    async fn refresh_session(
+        &self,
+        request: Request<RefreshSessionRequest>,
+    ) -> Result<Response<RefreshSessionResponse>, Status> {
+        let session_id = request.into_inner().session_id;
+
+        let session = self
+            .session_registry
+            .lock()
+            .unwrap()
+            .refresh(&session_id, now_secs())
+            .map_err(session_error)?;
+
+        info!("Session refreshed user: {}", session.user);
+        Ok(Response::new(RefreshSessionResponse {
+            expires_at: session.expires_at,
+        }))
+    }
+
+    async fn logout(
+        &self,
+        request: Request<LogoutRequest>,
+    ) -> Result<Response<LogoutResponse>, Status> {
+        let session_id = request.into_inner().session_id;
+
+        match self.session_registry.lock().unwrap().revoke(&session_id) {
+            Some(session) => {
+                info!("Logout user: {}", session.user);
+                Ok(Response::new(LogoutResponse {}))
+            }
+            None => Err(session_error(SessionError::NotFound)),
         }
     }
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let addr = "127.0.0.1:50051".parse().unwrap();
-    let mut auth = AuthImpl::default();
+/// Everything the server needs to run besides the cyclic group.
+struct ServerOptions {
+    /// Identifier of the cyclic group, returned by `GetParameters`.
+    group_id: Group,
+    /// Source of all the randomness of the server.
+    rng: Box<dyn ServerRng>,
+    /// Where the registered users are kept.
+    users: Box<dyn UserStore>,
+    /// Time a challenge can be answered.
+    challenge_ttl: Duration,
+    /// Number of pending challenges per user.
+    max_challenges_per_user: usize,
+    /// Lifetime of the sessions in seconds.
+    session_ttl_secs: u64,
+    /// Number of users whose keys have precomputed tables.
+    key_tables: usize,
+    /// Limits of the lockout after failed attempts.
+    throttle: ThrottleConfig,
+    /// Certificate of the server and optional client CA, plaintext if `None`.
+    tls: Option<ServerTlsConfig>,
+}
 
-    let args: Vec<String> = env::args().collect();
-    auth.group = parse_group_from_command_line(args);
+/// Runs the gRPC server using the cyclic group `group` for the ZK protocol.
+async fn serve<G>(
+    addr: SocketAddr,
+    group: G,
+    options: ServerOptions,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    G: CyclicGroup + Send + Sync + 'static,
+{
+    let challenge_ttl = options.challenge_ttl;
+    let auth_registry = Arc::new(Mutex::new(ChallengeRegistry::new(
+        challenge_ttl,
+        options.max_challenges_per_user,
+    )));
 
-    println!(
-        "Bookstore server listening on {} ZKP: {:?}",
-        addr, auth.group
-    );
+    // evicts the expired challenges in the background
+    let sweeper_registry = Arc::clone(&auth_registry);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(challenge_ttl);
+        loop {
+            interval.tick().await;
+            sweeper_registry.lock().unwrap().sweep(Instant::now());
+        }
+    });
+
+    let session_registry = Arc::new(Mutex::new(SessionStore::new(options.session_ttl_secs)));
+
+    // evicts the expired sessions in the background
+    let sweeper_registry = Arc::clone(&session_registry);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SESSION_SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            sweeper_registry.lock().unwrap().sweep(now_secs());
+        }
+    });
+
+    let throttle = Arc::new(Mutex::new(Throttle::new(options.throttle)));
+
+    // forgets the old failed attempts in the background
+    let sweeper_throttle = Arc::clone(&throttle);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(THROTTLE_SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            sweeper_throttle.lock().unwrap().sweep(Instant::now());
+        }
+    });
+
+    let group = Arc::new(group);
+    let auth = AuthImpl {
+        user_registry: options.users,
+        auth_registry,
+        login_registry: Mutex::default(),
+        key_tables: Mutex::new(KeyTables::new(options.key_tables)),
+        session_registry,
+        throttle,
+        rng: Mutex::new(options.rng),
+        verify_queue: VerifyQueue::start(
+            Arc::clone(&group),
+            DEFAULT_MAX_BATCH,
+            DEFAULT_QUEUE_CAPACITY,
+        ),
+        group_id: options.group_id,
+        group,
+    };
 
-    Server::builder()
+    let mut server = Server::builder();
+    if let Some(tls) = options.tls {
+        server = server.tls_config(tls)?;
+    }
+
+    server
         .add_service(AuthServer::new(auth))
         .serve(addr)
         .await?;
 
     Ok(())
 }
+
+#[tokio::main]
+async fn main() {
+    let config = match ServerConfig::load(env::args()) {
+        Ok(config) => config,
+        Err(ConfigError::Cli(error)) => error.exit(),
+        Err(error) => {
+            eprintln!("error: {}", error);
+            process::exit(2);
+        }
+    };
+
+    // the logs of the dependencies are only shown from the warnings
+    env_logger::Builder::new()
+        .filter_level(config.log_level.min(LevelFilter::Warn))
+        .filter_module(module_path!(), config.log_level)
+        .init();
+
+    if let Err(error) = run(config).await {
+        error!("{}", error);
+        process::exit(1);
+    }
+}
+
+/// Opens everything the configuration refers to and serves until an error.
+async fn run(config: ServerConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let addr = config.addr;
+    let group = config.group;
+
+    info!("Bookstore server listening on {} ZKP: {:?}", addr, group);
+
+    // the OS random generator unless a seed is given to reproduce a run
+    let rng: Box<dyn ServerRng> = match config.seed {
+        Some(seed) => {
+            warn!(
+                "deterministic random generator with seed {}, don't use it in production",
+                seed
+            );
+            Box::new(StdRng::seed_from_u64(seed))
+        }
+        None => Box::new(OsRng),
+    };
+
+    let tls = match config.tls {
+        Some(tls) => {
+            info!("TLS: {:?}", tls);
+            let tls = tls
+                .load()
+                .map_err(|error| format!("can't read the TLS files: {}", error))?;
+            Some(tls)
+        }
+        None => {
+            warn!("no TLS certificate, the traffic is not encrypted");
+            None
+        }
+    };
+
+    info!("User store: {:?}", config.store);
+    let options = ServerOptions {
+        group_id: group,
+        rng,
+        users: config.store.open()?,
+        challenge_ttl: config.challenge_ttl,
+        max_challenges_per_user: config.max_challenges_per_user,
+        session_ttl_secs: config.session_ttl_secs,
+        key_tables: config.key_tables,
+        throttle: config.throttle,
+        tls,
+    };
+
+    match group {
+        Group::Scalar => serve(addr, ModPGroup::default(), options).await,
+        Group::EllipticCurve => serve(addr, Secp256k1Group::default(), options).await,
+        Group::Modp2048 => serve(addr, ModPGroup::rfc3526_2048(), options).await,
+        Group::Modp3072 => serve(addr, ModPGroup::rfc3526_3072(), options).await,
+        Group::Modp4096 => serve(addr, ModPGroup::rfc3526_4096(), options).await,
+        Group::Ffdhe2048 => serve(addr, ModPGroup::ffdhe2048(), options).await,
+        Group::Ffdhe3072 => serve(addr, ModPGroup::ffdhe3072(), options).await,
+        Group::Ffdhe4096 => serve(addr, ModPGroup::ffdhe4096(), options).await,
+        Group::P256 => serve(addr, P256Group::default(), options).await,
+        Group::Ristretto255 => serve(addr, Ristretto255Group::default(), options).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chaum_pedersen_zkp::fiat_shamir::prove_non_interactive;
+    use chaum_pedersen_zkp::protocol::{Challenge, Prover, Witness};
+    use chaum_pedersen_zkp::{exponentiates_points, solve_zk_challenge_s};
+    use num_bigint::BigUint;
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+    use std::net::TcpListener;
+    use store::MemoryStore;
+    use tonic::transport::{Certificate, Channel, ClientTlsConfig, Identity};
+    use zkp_auth::auth_client::AuthClient;
+
+    /// A CA and a server and a client certificate signed by it, in PEM.
+    struct TestPki {
+        ca: String,
+        server: (String, String),
+        client: (String, String),
+    }
+
+    fn test_pki() -> TestPki {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+
+        let issue = |name: &str| {
+            let key = KeyPair::generate().unwrap();
+            let cert = CertificateParams::new(vec![name.to_string()])
+                .unwrap()
+                .signed_by(&key, &ca, &ca_key)
+                .unwrap();
+            (cert.pem(), key.serialize_pem())
+        };
+
+        TestPki {
+            ca: ca.pem(),
+            server: issue("localhost"),
+            client: issue("client"),
+        }
+    }
+
+    /// Starts a server of the default MODP group on a free port and returns
+    /// the port.
+    async fn start_server(tls: Option<ServerTlsConfig>) -> u16 {
+        start_group_server(ModPGroup::default(), Group::Scalar, tls).await
+    }
+
+    /// Starts a server of `group` on a free port and returns the port.
+    async fn start_group_server<G>(group: G, group_id: Group, tls: Option<ServerTlsConfig>) -> u16
+    where
+        G: CyclicGroup + Send + Sync + 'static,
+    {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let options = ServerOptions {
+            group_id,
+            rng: Box::new(StdRng::seed_from_u64(0)),
+            users: Box::new(MemoryStore::default()),
+            challenge_ttl: Duration::from_secs(60),
+            max_challenges_per_user: 5,
+            session_ttl_secs: 3600,
+            key_tables: key_tables::DEFAULT_MAX_KEY_TABLES,
+            throttle: ThrottleConfig::default(),
+            tls,
+        };
+        let addr = SocketAddr::from(([127, 0, 0, 1], port));
+        tokio::spawn(async move {
+            serve(addr, group, options).await.unwrap();
+        });
+
+        port
+    }
+
+    /// Connects to the server, retrying while it starts.
+    async fn connect(port: u16, tls: Option<ClientTlsConfig>) -> Option<Channel> {
+        let scheme = if tls.is_some() { "https" } else { "http" };
+        for _ in 0..50 {
+            let mut endpoint =
+                Channel::from_shared(format!("{}://127.0.0.1:{}", scheme, port)).unwrap();
+            if let Some(tls) = tls.clone() {
+                endpoint = endpoint.tls_config(tls).unwrap();
+            }
+
+            match endpoint.connect().await {
+                Ok(channel) => return Some(channel),
+                Err(_) => tokio::time::sleep(Duration::from_millis(50)).await,
+            }
+        }
+        None
+    }
+
+    /// Whether a `GetSalt` request reaches the server.
+    async fn reaches_server(port: u16, tls: Option<ClientTlsConfig>) -> bool {
+        let channel = match connect(port, tls).await {
+            Some(channel) => channel,
+            None => return false,
+        };
+
+        let response = AuthClient::new(channel)
+            .get_salt(GetSaltRequest {
+                user: "alice".to_string(),
+            })
+            .await;
+        matches!(response, Err(status) if status.code() == Code::NotFound)
+    }
+
+    fn client_tls(pki: &TestPki) -> ClientTlsConfig {
+        ClientTlsConfig::new()
+            .ca_certificate(Certificate::from_pem(&pki.ca))
+            .domain_name("localhost")
+    }
+
+    #[tokio::test]
+    async fn test_tls() {
+        let pki = test_pki();
+        let (cert, key) = &pki.server;
+        let port = start_server(Some(
+            ServerTlsConfig::new().identity(Identity::from_pem(cert, key)),
+        ))
+        .await;
+
+        assert!(reaches_server(port, Some(client_tls(&pki))).await);
+
+        // a client that doesn't trust the CA or speaks plaintext is rejected
+        let untrusted = test_pki();
+        assert!(!reaches_server(port, Some(client_tls(&untrusted))).await);
+        assert!(!reaches_server(port, None).await);
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls() {
+        let pki = test_pki();
+        let (cert, key) = &pki.server;
+        let port = start_server(Some(
+            ServerTlsConfig::new()
+                .identity(Identity::from_pem(cert, key))
+                .client_ca_root(Certificate::from_pem(&pki.ca)),
+        ))
+        .await;
+
+        let (cert, key) = &pki.client;
+        let identity = Identity::from_pem(cert, key);
+        assert!(reaches_server(port, Some(client_tls(&pki).identity(identity))).await);
+
+        // without a certificate, or with one from another CA
+        assert!(!reaches_server(port, Some(client_tls(&pki))).await);
+        let (cert, key) = &test_pki().client;
+        let identity = Identity::from_pem(cert, key);
+        assert!(!reaches_server(port, Some(client_tls(&pki).identity(identity))).await);
+    }
+
+    #[tokio::test]
+    async fn test_get_parameters() {
+        let port = start_server(None).await;
+        let channel = connect(port, None).await.unwrap();
+
+        let parameters = AuthClient::new(channel)
+            .get_parameters(GetParametersRequest {})
+            .await
+            .unwrap()
+            .into_inner();
+
+        let group = ModPGroup::default();
+        assert_eq!(parameters.group, "scalar");
+        assert_eq!(parameters.p, group.p.to_bytes_be());
+        assert_eq!(parameters.q, group.q.to_bytes_be());
+        assert_eq!(parameters.g, group.encode(&group.g));
+        assert_eq!(parameters.h, group.encode(&group.h));
+    }
+
+    /// Registers a user, logs in with a challenge and with a non-interactive
+    /// proof, and checks the sessions, on a server of `group`.
+    async fn check_login_flow<G>(group: G, group_id: Group)
+    where
+        G: CyclicGroup + Clone + Send + Sync + 'static,
+    {
+        let port = start_group_server(group.clone(), group_id, None).await;
+        let mut client = AuthClient::new(connect(port, None).await.unwrap());
+        let rng = &mut StdRng::seed_from_u64(6);
+
+        let x = group.random_scalar(rng);
+        let prover = Prover::new(&group, Witness::new(x.clone()));
+        let statement = prover.statement();
+        client
+            .register(RegisterRequest {
+                user: "alice".to_string(),
+                y1: group.encode(&statement.y1),
+                y2: group.encode(&statement.y2),
+                salt: vec![0; MIN_SALT_LEN],
+            })
+            .await
+            .unwrap();
+
+        let (prover, commitment) = prover.commit(&group, rng);
+        let challenge = client
+            .create_authentication_challenge(AuthenticationChallengeRequest {
+                user: "alice".to_string(),
+                r1: group.encode(&commitment.r1),
+                r2: group.encode(&commitment.r2),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        let answer = prover.respond(
+            &group,
+            &Challenge::from_bytes(&group, &challenge.c).unwrap(),
+        );
+        let session_id = client
+            .verify_authentication(AuthenticationAnswerRequest {
+                auth_id: challenge.auth_id,
+                s: answer.to_bytes(&group),
+            })
+            .await
+            .unwrap()
+            .into_inner()
+            .session_id;
+
+        let session = client
+            .validate_session(ValidateSessionRequest { session_id })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(session.user, "alice");
+
+        let timestamp = now_secs();
+        let proof = prove_non_interactive(&group, &x, &login_context("alice", timestamp), rng);
+        let session_id = client
+            .login(LoginRequest {
+                user: "alice".to_string(),
+                timestamp,
+                c: group.scalar_to_bytes(&proof.c),
+                s: group.scalar_to_bytes(&proof.s),
+            })
+            .await
+            .unwrap()
+            .into_inner()
+            .session_id;
+        assert!(client
+            .validate_session(ValidateSessionRequest { session_id })
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_login_flow() {
+        check_login_flow(ModPGroup::default(), Group::Scalar).await;
+        check_login_flow(Secp256k1Group::default(), Group::EllipticCurve).await;
+        check_login_flow(P256Group::default(), Group::P256).await;
+        check_login_flow(Ristretto255Group::default(), Group::Ristretto255).await;
+    }
+
+    #[tokio::test]
+    async fn test_status_codes() {
+        let port = start_server(None).await;
+        let mut client = AuthClient::new(connect(port, None).await.unwrap());
+        let group = ModPGroup::default();
+        let rng = &mut StdRng::seed_from_u64(1);
+
+        let x = group.random_scalar(rng);
+        let (y1, y2) = exponentiates_points(&group, &x);
+        client
+            .register(RegisterRequest {
+                user: "alice".to_string(),
+                y1: group.encode(&y1),
+                y2: group.encode(&y2),
+                salt: vec![0; MIN_SALT_LEN],
+            })
+            .await
+            .unwrap();
+
+        let mut login = |user: &str, timestamp: u64, x: &BigUint| {
+            let proof = prove_non_interactive(&group, x, &login_context(user, timestamp), rng);
+            LoginRequest {
+                user: user.to_string(),
+                timestamp,
+                c: group.scalar_to_bytes(&proof.c),
+                s: group.scalar_to_bytes(&proof.s),
+            }
+        };
+        let mut requests = vec![
+            (
+                login("alice", now_secs(), &(&x + 1u32)),
+                Code::Unauthenticated,
+            ),
+            (login("bob", now_secs(), &x), Code::NotFound),
+            (
+                login("alice", now_secs() - 600, &x),
+                Code::FailedPrecondition,
+            ),
+        ];
+        let mut out_of_range = login("alice", now_secs(), &x);
+        out_of_range.s = group.q.to_bytes_be();
+        requests.push((out_of_range, Code::InvalidArgument));
+
+        for (request, code) in requests {
+            let status = client.login(request).await.unwrap_err();
+            assert_eq!(status.code(), code, "{}", status.message());
+        }
+
+        let status = client
+            .create_authentication_challenge(AuthenticationChallengeRequest {
+                user: "alice".to_string(),
+                r1: group.encode(&BigUint::from(1u32)),
+                r2: group.encode(&y2),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(status.message().contains("identity element"));
+
+        // a rejected proof is not consumed, a valid one is used only once
+        let wrong = login("alice", now_secs(), &(&x + 1u32));
+        for _ in 0..2 {
+            let status = client.login(wrong.clone()).await.unwrap_err();
+            assert_eq!(status.code(), Code::Unauthenticated);
+        }
+        let valid = login("alice", now_secs(), &x);
+        client.login(valid.clone()).await.unwrap();
+        let status = client.login(valid).await.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(status.message().contains("already used"));
+    }
+
+    #[tokio::test]
+    async fn test_malformed_answer_uses_challenge() {
+        let port = start_server(None).await;
+        let mut client = AuthClient::new(connect(port, None).await.unwrap());
+        let group = ModPGroup::default();
+        let rng = &mut StdRng::seed_from_u64(4);
+
+        let x = group.random_scalar(rng);
+        let (y1, y2) = exponentiates_points(&group, &x);
+        client
+            .register(RegisterRequest {
+                user: "alice".to_string(),
+                y1: group.encode(&y1),
+                y2: group.encode(&y2),
+                salt: vec![0; MIN_SALT_LEN],
+            })
+            .await
+            .unwrap();
+
+        let k = group.random_scalar(rng);
+        let (r1, r2) = exponentiates_points(&group, &k);
+        let challenge = client
+            .create_authentication_challenge(AuthenticationChallengeRequest {
+                user: "alice".to_string(),
+                r1: group.encode(&r1),
+                r2: group.encode(&r2),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+
+        let status = client
+            .verify_authentication(AuthenticationAnswerRequest {
+                auth_id: challenge.auth_id.clone(),
+                s: vec![0xff; 100],
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        // the right answer comes too late
+        let c = group.decode_scalar(&challenge.c).unwrap();
+        let s = solve_zk_challenge_s(&group, &x, &k, &c);
+        let status = client
+            .verify_authentication(AuthenticationAnswerRequest {
+                auth_id: challenge.auth_id,
+                s: group.scalar_to_bytes(&s),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_answers_lock_out() {
+        let port = start_server(None).await;
+        let mut client = AuthClient::new(connect(port, None).await.unwrap());
+        let group = ModPGroup::default();
+        let rng = &mut StdRng::seed_from_u64(5);
+
+        let x = group.random_scalar(rng);
+        let (y1, y2) = exponentiates_points(&group, &x);
+        client
+            .register(RegisterRequest {
+                user: "alice".to_string(),
+                y1: group.encode(&y1),
+                y2: group.encode(&y2),
+                salt: vec![0; MIN_SALT_LEN],
+            })
+            .await
+            .unwrap();
+
+        let (r1, r2) = exponentiates_points(&group, &group.random_scalar(rng));
+        let challenge_request = AuthenticationChallengeRequest {
+            user: "alice".to_string(),
+            r1: group.encode(&r1),
+            r2: group.encode(&r2),
+        };
+
+        for _ in 0..throttle::DEFAULT_MAX_FAILURES {
+            let challenge = client
+                .create_authentication_challenge(challenge_request.clone())
+                .await
+                .unwrap()
+                .into_inner();
+            let status = client
+                .verify_authentication(AuthenticationAnswerRequest {
+                    auth_id: challenge.auth_id,
+                    s: vec![0xff; 100],
+                })
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), Code::InvalidArgument);
+        }
+
+        let status = client
+            .create_authentication_challenge(challenge_request)
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+    }
+
+    #[tokio::test]
+    async fn test_rotate_key() {
+        let port = start_server(None).await;
+        let mut client = AuthClient::new(connect(port, None).await.unwrap());
+        let group = ModPGroup::default();
+        let rng = &mut StdRng::seed_from_u64(3);
+
+        let x = group.random_scalar(rng);
+        let (y1, y2) = exponentiates_points(&group, &x);
+        client
+            .register(RegisterRequest {
+                user: "alice".to_string(),
+                y1: group.encode(&y1),
+                y2: group.encode(&y2),
+                salt: vec![0; MIN_SALT_LEN],
+            })
+            .await
+            .unwrap();
+
+        let timestamp = now_secs();
+        let proof = prove_non_interactive(&group, &x, &login_context("alice", timestamp), rng);
+        let session_id = client
+            .login(LoginRequest {
+                user: "alice".to_string(),
+                timestamp,
+                c: group.scalar_to_bytes(&proof.c),
+                s: group.scalar_to_bytes(&proof.s),
+            })
+            .await
+            .unwrap()
+            .into_inner()
+            .session_id;
+
+        let (new_y1, new_y2) = exponentiates_points(&group, &(&x + 7u32));
+        let (new_y1, new_y2) = (group.encode(&new_y1), group.encode(&new_y2));
+        let salt = vec![1; MIN_SALT_LEN];
+        let mut rotate = |x: &BigUint| {
+            let timestamp = now_secs();
+            let context = rotate_key_context("alice", timestamp, &new_y1, &new_y2, &salt);
+            let proof = prove_non_interactive(&group, x, &context, rng);
+            RotateKeyRequest {
+                user: "alice".to_string(),
+                timestamp,
+                c: group.scalar_to_bytes(&proof.c),
+                s: group.scalar_to_bytes(&proof.s),
+                y1: new_y1.clone(),
+                y2: new_y2.clone(),
+                salt: salt.clone(),
+            }
+        };
+
+        // a rejected proof is not consumed, a valid one is used only once
+        let wrong = rotate(&(&x + 1u32));
+        for _ in 0..2 {
+            let status = client.rotate_key(wrong.clone()).await.unwrap_err();
+            assert_eq!(status.code(), Code::Unauthenticated);
+        }
+        let valid = rotate(&x);
+        client.rotate_key(valid.clone()).await.unwrap();
+        let status = client.rotate_key(valid).await.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(status.message().contains("already used"));
+
+        // the old key can't sign another rotation
+        let status = client.rotate_key(rotate(&x)).await.unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+
+        // the session opened with the old key is revoked
+        let status = client
+            .validate_session(ValidateSessionRequest { session_id })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_key_tables() {
+        let port = start_server(None).await;
+        let mut client = AuthClient::new(connect(port, None).await.unwrap());
+        let group = ModPGroup::default();
+        let rng = &mut StdRng::seed_from_u64(2);
+
+        let x = group.random_scalar(rng);
+        let (y1, y2) = exponentiates_points(&group, &x);
+        client
+            .register(RegisterRequest {
+                user: "alice".to_string(),
+                y1: group.encode(&y1),
+                y2: group.encode(&y2),
+                salt: vec![0; MIN_SALT_LEN],
+            })
+            .await
+            .unwrap();
+
+        let mut login = |x: &BigUint| {
+            let timestamp = now_secs();
+            let proof = prove_non_interactive(&group, x, &login_context("alice", timestamp), rng);
+            LoginRequest {
+                user: "alice".to_string(),
+                timestamp,
+                c: group.scalar_to_bytes(&proof.c),
+                s: group.scalar_to_bytes(&proof.s),
+            }
+        };
+
+        // the last logins are verified with the tables of the keys
+        for _ in 0..key_tables::KEY_TABLE_THRESHOLD + 2 {
+            client.login(login(&x)).await.unwrap();
+        }
+        let status = client.login(login(&(&x + 1u32))).await.unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+    }
+}
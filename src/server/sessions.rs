@@ -0,0 +1,168 @@
+//! Sessions issued after a successful login.
+//!
+//! A session is identified by a random ID and is valid until its expiry time,
+//! which can be pushed forward with a refresh. Other services check the
+//! sessions with the `ValidateSession` RPC.
+
+use std::collections::HashMap;
+
+/// Default lifetime of a session in seconds.
+pub const DEFAULT_SESSION_TTL_SECS: u64 = 3600;
+
+/// Length of the session IDs, ~190 bits of entropy.
+pub const SESSION_ID_LEN: usize = 32;
+
+/// A session of a user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    pub user: String,
+    /// Unix time in seconds after which the session is not valid.
+    pub expires_at: u64,
+}
+
+/// Why a session can't be used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionError {
+    /// The session was never issued, was revoked or expired a while ago.
+    NotFound,
+    /// The session expired.
+    Expired,
+}
+
+/// Sessions indexed by their ID.
+#[derive(Debug)]
+pub struct SessionStore {
+    ttl_secs: u64,
+    sessions: HashMap<String, Session>,
+}
+
+impl SessionStore {
+    /// * `ttl_secs` - lifetime of a session, and of a refresh.
+    pub fn new(ttl_secs: u64) -> Self {
+        SessionStore {
+            ttl_secs,
+            sessions: HashMap::new(),
+        }
+    }
+
+    /// Opens a session for `user` valid until now + TTL.
+    pub fn create(&mut self, session_id: String, user: String, now: u64) -> Session {
+        let session = Session {
+            user,
+            expires_at: now + self.ttl_secs,
+        };
+        self.sessions.insert(session_id, session.clone());
+        session
+    }
+
+    /// Returns the session if it is still valid. An expired session is
+    /// removed.
+    pub fn validate(&mut self, session_id: &str, now: u64) -> Result<Session, SessionError> {
+        match self.sessions.get(session_id) {
+            Some(session) if session.expires_at > now => Ok(session.clone()),
+            Some(_) => {
+                self.sessions.remove(session_id);
+                Err(SessionError::Expired)
+            }
+            None => Err(SessionError::NotFound),
+        }
+    }
+
+    /// Extends a valid session until now + TTL.
+    pub fn refresh(&mut self, session_id: &str, now: u64) -> Result<Session, SessionError> {
+        self.validate(session_id, now)?;
+
+        let session = self.sessions.get_mut(session_id).unwrap();
+        session.expires_at = now + self.ttl_secs;
+        Ok(session.clone())
+    }
+
+    /// Ends a session, returns the session if it existed.
+    pub fn revoke(&mut self, session_id: &str) -> Option<Session> {
+        self.sessions.remove(session_id)
+    }
+
+    /// Ends every session of `user`, returns how many there were.
+    pub fn revoke_user(&mut self, user: &str) -> usize {
+        let before = self.sessions.len();
+        self.sessions.retain(|_, session| session.user != user);
+        before - self.sessions.len()
+    }
+
+    /// Removes the expired sessions.
+    pub fn sweep(&mut self, now: u64) {
+        self.sessions.retain(|_, session| session.expires_at > now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: u64 = 3600;
+    const NOW: u64 = 1_700_000_000;
+
+    #[test]
+    fn test_validate() {
+        let mut store = SessionStore::new(TTL);
+
+        let session = store.create("s1".to_string(), "alice".to_string(), NOW);
+        assert_eq!(session.expires_at, NOW + TTL);
+
+        assert_eq!(store.validate("s1", NOW + TTL - 1), Ok(session));
+        assert_eq!(store.validate("s2", NOW), Err(SessionError::NotFound));
+
+        assert_eq!(store.validate("s1", NOW + TTL), Err(SessionError::Expired));
+        assert_eq!(store.validate("s1", NOW + TTL), Err(SessionError::NotFound));
+    }
+
+    #[test]
+    fn test_refresh() {
+        let mut store = SessionStore::new(TTL);
+        store.create("s1".to_string(), "alice".to_string(), NOW);
+
+        let session = store.refresh("s1", NOW + 100).unwrap();
+        assert_eq!(session.expires_at, NOW + 100 + TTL);
+        assert!(store.validate("s1", NOW + TTL).is_ok());
+
+        assert_eq!(
+            store.refresh("s1", NOW + 100 + TTL),
+            Err(SessionError::Expired)
+        );
+        assert_eq!(store.refresh("s2", NOW), Err(SessionError::NotFound));
+    }
+
+    #[test]
+    fn test_revoke() {
+        let mut store = SessionStore::new(TTL);
+        store.create("s1".to_string(), "alice".to_string(), NOW);
+
+        assert_eq!(store.revoke("s1").unwrap().user, "alice");
+        assert_eq!(store.revoke("s1"), None);
+        assert_eq!(store.validate("s1", NOW), Err(SessionError::NotFound));
+    }
+
+    #[test]
+    fn test_revoke_user() {
+        let mut store = SessionStore::new(TTL);
+        store.create("s1".to_string(), "alice".to_string(), NOW);
+        store.create("s2".to_string(), "alice".to_string(), NOW);
+        store.create("s3".to_string(), "bob".to_string(), NOW);
+
+        assert_eq!(store.revoke_user("alice"), 2);
+        assert_eq!(store.revoke_user("alice"), 0);
+        assert_eq!(store.validate("s2", NOW), Err(SessionError::NotFound));
+        assert!(store.validate("s3", NOW).is_ok());
+    }
+
+    #[test]
+    fn test_sweep() {
+        let mut store = SessionStore::new(TTL);
+        store.create("s1".to_string(), "alice".to_string(), NOW);
+        store.create("s2".to_string(), "bob".to_string(), NOW + 10);
+
+        store.sweep(NOW + TTL);
+        assert_eq!(store.validate("s1", NOW), Err(SessionError::NotFound));
+        assert!(store.validate("s2", NOW + TTL).is_ok());
+    }
+}
@@ -0,0 +1,481 @@
+//! Storage of the registered users.
+//!
+//! The server keeps the public keys `y1`, `y2` and the salt of every user
+//! behind the [`UserStore`] trait so they can survive a restart. The records
+//! are stored with the group elements already encoded, the server decodes and
+//! validates them again every time it reads them.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use log::warn;
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// A registered user as it is stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserRecord {
+    pub user: String,
+    /// Encoded g^x.
+    pub y1: Vec<u8>,
+    /// Encoded h^x.
+    pub y2: Vec<u8>,
+    /// Salt of the password of the user.
+    pub salt: Vec<u8>,
+}
+
+/// The errors returned by the user stores.
+#[derive(Debug)]
+pub enum StoreError {
+    Io(io::Error),
+    Sqlite(rusqlite::Error),
+    /// A stored record can't be parsed.
+    Corrupted(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Io(error) => write!(f, "user store I/O error: {}", error),
+            StoreError::Sqlite(error) => write!(f, "user store SQLite error: {}", error),
+            StoreError::Corrupted(message) => write!(f, "user store corrupted: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<io::Error> for StoreError {
+    fn from(error: io::Error) -> Self {
+        StoreError::Io(error)
+    }
+}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(error: rusqlite::Error) -> Self {
+        StoreError::Sqlite(error)
+    }
+}
+
+/// Storage of the registered users.
+pub trait UserStore: Send + Sync {
+    /// Returns the record of `user` if it is registered.
+    fn get(&self, user: &str) -> Result<Option<UserRecord>, StoreError>;
+
+    /// Stores `record` only if the user is not registered yet, returns
+    /// whether it was stored.
+    fn insert(&self, record: UserRecord) -> Result<bool, StoreError>;
+
+    /// Stores `record` only if the stored record of the user is still
+    /// `current`, returns whether it was stored. Two updates made from the
+    /// same record can't both succeed.
+    fn replace(&self, current: &UserRecord, record: UserRecord) -> Result<bool, StoreError>;
+}
+
+/// Keeps the users in memory, they are lost when the server stops.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    users: Mutex<HashMap<String, UserRecord>>,
+}
+
+impl UserStore for MemoryStore {
+    fn get(&self, user: &str) -> Result<Option<UserRecord>, StoreError> {
+        Ok(self.users.lock().unwrap().get(user).cloned())
+    }
+
+    fn insert(&self, record: UserRecord) -> Result<bool, StoreError> {
+        match self.users.lock().unwrap().entry(record.user.clone()) {
+            Entry::Occupied(_) => Ok(false),
+            Entry::Vacant(entry) => {
+                entry.insert(record);
+                Ok(true)
+            }
+        }
+    }
+
+    fn replace(&self, current: &UserRecord, record: UserRecord) -> Result<bool, StoreError> {
+        let users = &mut *self.users.lock().unwrap();
+        if users.get(&record.user) != Some(current) {
+            return Ok(false);
+        }
+        users.insert(record.user.clone(), record);
+        Ok(true)
+    }
+}
+
+/// Appends every record to a text file, one line per record with the hex
+/// encoded fields `user y1 y2 salt`. The last record of a user wins. The file
+/// is replayed into memory when the store is opened.
+#[derive(Debug)]
+pub struct FileStore {
+    file: Mutex<File>,
+    users: Mutex<HashMap<String, UserRecord>>,
+}
+
+impl FileStore {
+    /// Opens the file at `path`, creating it if it doesn't exist.
+    ///
+    /// A last line without its newline is the trace of a write interrupted
+    /// by a crash, it is discarded.
+    pub fn open(path: &Path) -> Result<Self, StoreError> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+
+        let mut users = HashMap::new();
+        let mut valid_len = 0;
+
+        let mut reader = BufReader::new(&file);
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 0 {
+            if !line.ends_with('\n') {
+                break;
+            }
+            let record = parse_line(&line)?;
+            users.insert(record.user.clone(), record);
+            valid_len += line.len() as u64;
+            line.clear();
+        }
+
+        if valid_len < file.seek(SeekFrom::End(0))? {
+            warn!("Discarding an incomplete record at the end of the user store");
+            file.set_len(valid_len)?;
+        }
+
+        Ok(FileStore {
+            file: Mutex::new(file),
+            users: Mutex::new(users),
+        })
+    }
+
+    /// Appends `record` to the file and makes it visible.
+    fn append(&self, file: &mut File, record: UserRecord) -> Result<(), StoreError> {
+        let line = format!(
+            "{} {} {} {}\n",
+            hex::encode(&record.user),
+            hex::encode(&record.y1),
+            hex::encode(&record.y2),
+            hex::encode(&record.salt)
+        );
+
+        // the record is on disk before it is visible
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+
+        self.users
+            .lock()
+            .unwrap()
+            .insert(record.user.clone(), record);
+        Ok(())
+    }
+}
+
+fn parse_line(line: &str) -> Result<UserRecord, StoreError> {
+    let fields = line
+        .trim_end_matches('\n')
+        .split(' ')
+        .map(hex::decode)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| StoreError::Corrupted(format!("invalid hex in line {:?}", line)))?;
+
+    match <[Vec<u8>; 4]>::try_from(fields) {
+        Ok([user, y1, y2, salt]) => Ok(UserRecord {
+            user: String::from_utf8(user)
+                .map_err(|_| StoreError::Corrupted(format!("invalid user in line {:?}", line)))?,
+            y1,
+            y2,
+            salt,
+        }),
+        Err(_) => Err(StoreError::Corrupted(format!(
+            "expected 4 fields in line {:?}",
+            line
+        ))),
+    }
+}
+
+impl UserStore for FileStore {
+    fn get(&self, user: &str) -> Result<Option<UserRecord>, StoreError> {
+        Ok(self.users.lock().unwrap().get(user).cloned())
+    }
+
+    fn insert(&self, record: UserRecord) -> Result<bool, StoreError> {
+        // the writers are serialized by the lock of the file
+        let file = &mut *self.file.lock().unwrap();
+        if self.users.lock().unwrap().contains_key(&record.user) {
+            return Ok(false);
+        }
+        self.append(file, record)?;
+        Ok(true)
+    }
+
+    fn replace(&self, current: &UserRecord, record: UserRecord) -> Result<bool, StoreError> {
+        let file = &mut *self.file.lock().unwrap();
+        if self.users.lock().unwrap().get(&record.user) != Some(current) {
+            return Ok(false);
+        }
+        self.append(file, record)?;
+        Ok(true)
+    }
+}
+
+/// Keeps the users in an embedded SQLite database.
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Opens the database at `path`, creating it if it doesn't exist.
+    pub fn open(path: &Path) -> Result<Self, StoreError> {
+        let connection = Connection::open(path)?;
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS users (
+                user TEXT PRIMARY KEY,
+                y1 BLOB NOT NULL,
+                y2 BLOB NOT NULL,
+                salt BLOB NOT NULL
+            )",
+            [],
+        )?;
+
+        Ok(SqliteStore {
+            connection: Mutex::new(connection),
+        })
+    }
+}
+
+impl UserStore for SqliteStore {
+    fn get(&self, user: &str) -> Result<Option<UserRecord>, StoreError> {
+        let connection = self.connection.lock().unwrap();
+        let record = connection
+            .query_row(
+                "SELECT user, y1, y2, salt FROM users WHERE user = ?1",
+                params![user],
+                |row| {
+                    Ok(UserRecord {
+                        user: row.get(0)?,
+                        y1: row.get(1)?,
+                        y2: row.get(2)?,
+                        salt: row.get(3)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(record)
+    }
+
+    fn insert(&self, record: UserRecord) -> Result<bool, StoreError> {
+        let connection = self.connection.lock().unwrap();
+        let inserted = connection.execute(
+            "INSERT OR IGNORE INTO users (user, y1, y2, salt) VALUES (?1, ?2, ?3, ?4)",
+            params![record.user, record.y1, record.y2, record.salt],
+        )?;
+        Ok(inserted == 1)
+    }
+
+    fn replace(&self, current: &UserRecord, record: UserRecord) -> Result<bool, StoreError> {
+        let connection = self.connection.lock().unwrap();
+        let replaced = connection.execute(
+            "UPDATE users SET y1 = ?2, y2 = ?3, salt = ?4
+                WHERE user = ?1 AND y1 = ?5 AND y2 = ?6 AND salt = ?7",
+            params![
+                record.user,
+                record.y1,
+                record.y2,
+                record.salt,
+                current.y1,
+                current.y2,
+                current.salt
+            ],
+        )?;
+        Ok(replaced == 1)
+    }
+}
+
+/// The user store selected in the configuration.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum StoreKind {
+    #[default]
+    Memory,
+    File(PathBuf),
+    Sqlite(PathBuf),
+}
+
+impl StoreKind {
+    /// Opens the selected store.
+    pub fn open(&self) -> Result<Box<dyn UserStore>, StoreError> {
+        Ok(match self {
+            StoreKind::Memory => Box::<MemoryStore>::default(),
+            StoreKind::File(path) => Box::new(FileStore::open(path)?),
+            StoreKind::Sqlite(path) => Box::new(SqliteStore::open(path)?),
+        })
+    }
+}
+
+impl FromStr for StoreKind {
+    type Err = String;
+
+    /// Parses `memory`, `file:<path>` or `sqlite:<path>`.
+    fn from_str(store: &str) -> Result<Self, Self::Err> {
+        match store.split_once(':') {
+            None if store == "memory" => Ok(StoreKind::Memory),
+            Some(("file", path)) if !path.is_empty() => Ok(StoreKind::File(path.into())),
+            Some(("sqlite", path)) if !path.is_empty() => Ok(StoreKind::Sqlite(path.into())),
+            _ => Err(format!(
+                "invalid store `{}`, expected memory, file:<path> or sqlite:<path>",
+                store
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn record(user: &str, key: u8) -> UserRecord {
+        UserRecord {
+            user: user.to_string(),
+            y1: vec![2, key],
+            y2: vec![3, key],
+            salt: vec![key; 16],
+        }
+    }
+
+    fn check_store(store: &dyn UserStore) {
+        assert_eq!(store.get("alice").unwrap(), None);
+
+        assert!(store.insert(record("alice", 1)).unwrap());
+        assert!(store.insert(record("bob smith", 2)).unwrap());
+        assert!(store.insert(record("", 4)).unwrap());
+        assert_eq!(store.get("alice").unwrap(), Some(record("alice", 1)));
+        assert_eq!(
+            store.get("bob smith").unwrap(),
+            Some(record("bob smith", 2))
+        );
+
+        assert!(store
+            .replace(&record("alice", 1), record("alice", 3))
+            .unwrap());
+        assert_eq!(store.get("alice").unwrap(), Some(record("alice", 3)));
+
+        // insert never replaces a registered user
+        assert!(!store.insert(record("alice", 5)).unwrap());
+        assert_eq!(store.get("alice").unwrap(), Some(record("alice", 3)));
+        assert!(store.insert(record("carol", 6)).unwrap());
+        assert_eq!(store.get("carol").unwrap(), Some(record("carol", 6)));
+
+        // replace only succeeds once from the same record
+        assert!(store
+            .replace(&record("carol", 6), record("carol", 7))
+            .unwrap());
+        assert!(!store
+            .replace(&record("carol", 6), record("carol", 8))
+            .unwrap());
+        assert_eq!(store.get("carol").unwrap(), Some(record("carol", 7)));
+        assert!(!store
+            .replace(&record("dave", 1), record("dave", 2))
+            .unwrap());
+        assert_eq!(store.get("dave").unwrap(), None);
+    }
+
+    #[test]
+    fn test_memory_store() {
+        check_store(&MemoryStore::default());
+    }
+
+    #[test]
+    fn test_file_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.log");
+
+        check_store(&FileStore::open(&path).unwrap());
+
+        // the users survive a restart
+        let store = FileStore::open(&path).unwrap();
+        assert_eq!(store.get("alice").unwrap(), Some(record("alice", 3)));
+        assert_eq!(
+            store.get("bob smith").unwrap(),
+            Some(record("bob smith", 2))
+        );
+        assert_eq!(store.get("carol").unwrap(), Some(record("carol", 7)));
+    }
+
+    #[test]
+    fn test_file_store_incomplete_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.log");
+
+        FileStore::open(&path)
+            .unwrap()
+            .insert(record("alice", 1))
+            .unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"626f62 0201").unwrap();
+
+        let store = FileStore::open(&path).unwrap();
+        assert_eq!(store.get("alice").unwrap(), Some(record("alice", 1)));
+        assert_eq!(store.get("bob").unwrap(), None);
+
+        assert!(store.insert(record("bob", 2)).unwrap());
+        let store = FileStore::open(&path).unwrap();
+        assert_eq!(store.get("bob").unwrap(), Some(record("bob", 2)));
+    }
+
+    #[test]
+    fn test_file_store_corrupted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.log");
+
+        fs::write(&path, "616c696365 0201\n").unwrap();
+        assert!(matches!(
+            FileStore::open(&path),
+            Err(StoreError::Corrupted(_))
+        ));
+
+        fs::write(&path, "not hex\n").unwrap();
+        assert!(matches!(
+            FileStore::open(&path),
+            Err(StoreError::Corrupted(_))
+        ));
+    }
+
+    #[test]
+    fn test_sqlite_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.db");
+
+        check_store(&SqliteStore::open(&path).unwrap());
+
+        // the users survive a restart
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.get("alice").unwrap(), Some(record("alice", 3)));
+        assert_eq!(
+            store.get("bob smith").unwrap(),
+            Some(record("bob smith", 2))
+        );
+    }
+
+    #[test]
+    fn test_parse_store_kind() {
+        assert_eq!("memory".parse(), Ok(StoreKind::Memory));
+        assert_eq!(
+            "file:users.log".parse(),
+            Ok(StoreKind::File("users.log".into()))
+        );
+        assert_eq!(
+            "sqlite:/var/lib/zkp/users.db".parse(),
+            Ok(StoreKind::Sqlite("/var/lib/zkp/users.db".into()))
+        );
+        assert!("file:".parse::<StoreKind>().is_err());
+        assert!("redis:localhost".parse::<StoreKind>().is_err());
+    }
+}
@@ -0,0 +1,226 @@
+//! Protection against brute force: failed attempts are counted per user and
+//! per peer address, and after a few of them the user or the peer is locked
+//! out for a time that doubles with every new failure.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+/// Default number of failures allowed before the first lockout.
+pub const DEFAULT_MAX_FAILURES: u32 = 5;
+
+/// Default duration of the first lockout.
+pub const DEFAULT_LOCKOUT_BASE: Duration = Duration::from_secs(1);
+
+/// Default maximum duration of a lockout.
+pub const DEFAULT_LOCKOUT_MAX: Duration = Duration::from_secs(15 * 60);
+
+/// Limits of the lockout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThrottleConfig {
+    /// Number of consecutive failures allowed before the first lockout.
+    pub max_failures: u32,
+    /// Duration of the first lockout, every new failure doubles it.
+    pub base_delay: Duration,
+    /// Maximum duration of a lockout. The failures are forgotten after this
+    /// time without a new one.
+    pub max_delay: Duration,
+}
+
+impl Default for ThrottleConfig {
+    fn default() -> Self {
+        ThrottleConfig {
+            max_failures: DEFAULT_MAX_FAILURES,
+            base_delay: DEFAULT_LOCKOUT_BASE,
+            max_delay: DEFAULT_LOCKOUT_MAX,
+        }
+    }
+}
+
+/// Who the failures are counted for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ThrottleKey {
+    User(String),
+    Peer(IpAddr),
+}
+
+#[derive(Debug)]
+struct Failures {
+    count: u32,
+    last_failure: Instant,
+    locked_until: Instant,
+}
+
+/// Failed attempts per user and per peer address.
+#[derive(Debug)]
+pub struct Throttle {
+    config: ThrottleConfig,
+    failures: HashMap<ThrottleKey, Failures>,
+}
+
+impl Throttle {
+    pub fn new(config: ThrottleConfig) -> Self {
+        Throttle {
+            config,
+            failures: HashMap::new(),
+        }
+    }
+
+    /// Returns the time to wait if any of the keys is locked out.
+    pub fn check(&self, keys: &[ThrottleKey], now: Instant) -> Result<(), Duration> {
+        let retry_after = keys
+            .iter()
+            .filter_map(|key| self.failures.get(key))
+            .map(|failures| failures.locked_until.saturating_duration_since(now))
+            .max()
+            .unwrap_or_default();
+
+        if retry_after.is_zero() {
+            Ok(())
+        } else {
+            Err(retry_after)
+        }
+    }
+
+    /// Counts a failure for every key and locks out the keys with too many
+    /// failures.
+    pub fn record_failure(&mut self, keys: &[ThrottleKey], now: Instant) {
+        for key in keys {
+            let failures = self.failures.entry(key.clone()).or_insert(Failures {
+                count: 0,
+                last_failure: now,
+                locked_until: now,
+            });
+
+            if self.config.is_stale(failures, now) {
+                failures.count = 0;
+            }
+            failures.count += 1;
+            failures.last_failure = now;
+
+            if failures.count >= self.config.max_failures {
+                failures.locked_until = now + self.config.delay(failures.count);
+            }
+        }
+    }
+
+    /// Forgets the failures of a user after a successful login. The failures
+    /// of the peer are kept so an attacker can't reset them with its own
+    /// account.
+    pub fn record_success(&mut self, user: &str) {
+        self.failures.remove(&ThrottleKey::User(user.to_string()));
+    }
+
+    /// Forgets the failures that are too old.
+    pub fn sweep(&mut self, now: Instant) {
+        let config = self.config;
+        self.failures
+            .retain(|_, failures| !config.is_stale(failures, now));
+    }
+}
+
+impl ThrottleConfig {
+    /// base * 2^(count - max_failures) capped to the maximum delay.
+    fn delay(&self, count: u32) -> Duration {
+        let exponent = count.saturating_sub(self.max_failures).min(31);
+        self.base_delay
+            .checked_mul(1 << exponent)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+
+    fn is_stale(&self, failures: &Failures, now: Instant) -> bool {
+        failures.locked_until <= now && failures.last_failure + self.max_delay <= now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    const CONFIG: ThrottleConfig = ThrottleConfig {
+        max_failures: 3,
+        base_delay: Duration::from_secs(1),
+        max_delay: Duration::from_secs(60),
+    };
+
+    fn user(name: &str) -> ThrottleKey {
+        ThrottleKey::User(name.to_string())
+    }
+
+    #[test]
+    fn test_lockout_backoff() {
+        let now = Instant::now();
+        let mut throttle = Throttle::new(CONFIG);
+        let keys = [user("alice")];
+
+        throttle.record_failure(&keys, now);
+        throttle.record_failure(&keys, now);
+        assert_eq!(throttle.check(&keys, now), Ok(()));
+
+        throttle.record_failure(&keys, now);
+        assert_eq!(throttle.check(&keys, now), Err(Duration::from_secs(1)));
+        assert_eq!(throttle.check(&keys, now + Duration::from_secs(1)), Ok(()));
+
+        // every new failure doubles the lockout up to the maximum
+        let mut expected = 2;
+        for _ in 0..10 {
+            throttle.record_failure(&keys, now);
+            assert_eq!(
+                throttle.check(&keys, now),
+                Err(Duration::from_secs(expected))
+            );
+            expected = (expected * 2).min(60);
+        }
+
+        assert_eq!(throttle.check(&[user("bob")], now), Ok(()));
+    }
+
+    #[test]
+    fn test_user_and_peer() {
+        let now = Instant::now();
+        let mut throttle = Throttle::new(CONFIG);
+        let peer = ThrottleKey::Peer(IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        // the peer is locked out even if it tries a different user every time
+        for name in ["alice", "bob", "carol"] {
+            throttle.record_failure(&[user(name), peer.clone()], now);
+        }
+        assert!(throttle.check(&[user("dave"), peer.clone()], now).is_err());
+        assert_eq!(throttle.check(&[user("alice")], now), Ok(()));
+
+        // a success only resets the user
+        throttle.record_failure(&[user("alice")], now);
+        throttle.record_failure(&[user("alice")], now);
+        throttle.record_success("alice");
+        throttle.record_failure(&[user("alice")], now);
+        assert_eq!(throttle.check(&[user("alice")], now), Ok(()));
+        assert!(throttle.check(&[peer], now).is_err());
+    }
+
+    #[test]
+    fn test_sweep() {
+        let now = Instant::now();
+        let mut throttle = Throttle::new(CONFIG);
+        let keys = [user("alice")];
+
+        for _ in 0..3 {
+            throttle.record_failure(&keys, now);
+        }
+
+        throttle.sweep(now + Duration::from_secs(59));
+        assert_eq!(throttle.failures.len(), 1);
+
+        throttle.sweep(now + Duration::from_secs(60));
+        assert!(throttle.failures.is_empty());
+
+        // old failures are not counted
+        let later = now + Duration::from_secs(120);
+        for _ in 0..2 {
+            throttle.record_failure(&keys, now);
+        }
+        throttle.record_failure(&keys, later);
+        assert_eq!(throttle.check(&keys, later), Ok(()));
+    }
+}
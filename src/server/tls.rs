@@ -0,0 +1,53 @@
+//! TLS of the gRPC server.
+//!
+//! The server speaks plaintext HTTP/2 unless it is given a certificate and its
+//! private key. With a client CA it also asks every client for a certificate
+//! signed by that CA (mutual TLS) and rejects the clients without one.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use tonic::transport::{Certificate, Identity, ServerTlsConfig};
+
+/// The PEM files of the TLS configuration of the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsOptions {
+    /// Certificate chain of the server.
+    pub cert: PathBuf,
+    /// Private key of the server certificate.
+    pub key: PathBuf,
+    /// CA the client certificates must be signed by, if mutual TLS is
+    /// required.
+    pub client_ca: Option<PathBuf>,
+}
+
+impl TlsOptions {
+    /// Reads the PEM files.
+    pub fn load(&self) -> io::Result<ServerTlsConfig> {
+        let cert = fs::read(&self.cert)?;
+        let key = fs::read(&self.key)?;
+        let mut config = ServerTlsConfig::new().identity(Identity::from_pem(cert, key));
+
+        if let Some(client_ca) = &self.client_ca {
+            config = config.client_ca_root(Certificate::from_pem(fs::read(client_ca)?));
+        }
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_missing_file() {
+        let options = TlsOptions {
+            cert: PathBuf::from("/nonexistent/server.pem"),
+            key: PathBuf::from("/nonexistent/server.key"),
+            client_ca: None,
+        };
+        assert!(options.load().is_err());
+    }
+}
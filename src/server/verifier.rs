@@ -0,0 +1,223 @@
+//! Batched verification of the answers to the challenges.
+//!
+//! The answers received concurrently are queued and verified together with
+//! [`verify_batch`], which costs much less than verifying them one by one
+//! during a login storm. A single task drains the queue: it takes every
+//! pending answer, up to a maximum, verifies them on a blocking thread and
+//! replies to each request. The answers queued meanwhile form the next batch.
+//!
+//! The queue is bounded: when it is full the answers are refused instead of
+//! growing the memory of the server without limit.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+
+use log::error;
+use rand::rngs::OsRng;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::oneshot;
+
+use chaum_pedersen_zkp::batch::{verify_batch, Transcript};
+use chaum_pedersen_zkp::{verify, CyclicGroup};
+
+/// Default maximum number of answers verified together.
+pub const DEFAULT_MAX_BATCH: usize = 64;
+
+/// Default maximum number of answers waiting to be verified.
+pub const DEFAULT_QUEUE_CAPACITY: usize = 4096;
+
+/// A queued transcript and where to send whether it is valid.
+type Job<G> = (Transcript<G>, oneshot::Sender<bool>);
+
+/// The queue already holds its maximum number of answers.
+#[derive(Debug, PartialEq)]
+pub struct QueueFull;
+
+/// Handle to the task verifying the queued transcripts.
+pub struct VerifyQueue<G: CyclicGroup> {
+    sender: mpsc::Sender<Job<G>>,
+}
+
+impl<G> VerifyQueue<G>
+where
+    G: CyclicGroup + Send + Sync + 'static,
+{
+    /// Starts the task verifying the queued transcripts in batches of at most
+    /// `max_batch`, with at most `capacity` transcripts waiting.
+    pub fn start(group: Arc<G>, max_batch: usize, capacity: usize) -> Self {
+        let (sender, mut receiver) = mpsc::channel::<Job<G>>(capacity);
+
+        tokio::spawn(async move {
+            while let Some(job) = receiver.recv().await {
+                let mut jobs = vec![job];
+                while jobs.len() < max_batch {
+                    match receiver.try_recv() {
+                        Ok(job) => jobs.push(job),
+                        Err(_) => break,
+                    }
+                }
+
+                let (transcripts, replies): (Vec<_>, Vec<_>) = jobs.into_iter().unzip();
+                let valid = verify_transcripts(Arc::clone(&group), Arc::new(transcripts)).await;
+
+                for (reply, valid) in replies.into_iter().zip(valid) {
+                    // the request may have been cancelled meanwhile
+                    let _ = reply.send(valid);
+                }
+            }
+        });
+
+        VerifyQueue { sender }
+    }
+
+    /// Queues `transcript` and waits until it is verified.
+    pub async fn verify(&self, transcript: Transcript<G>) -> Result<bool, QueueFull> {
+        let (reply, valid) = oneshot::channel();
+        match self.sender.try_send((transcript, reply)) {
+            Ok(()) => Ok(valid.await.unwrap_or(false)),
+            Err(TrySendError::Full(_)) => Err(QueueFull),
+            Err(TrySendError::Closed(_)) => Ok(false),
+        }
+    }
+}
+
+/// Whether each transcript is valid, verifying them together on a blocking
+/// thread. If the batch verification panics the transcripts are verified one
+/// by one, so a single bad batch doesn't stop the queue.
+async fn verify_transcripts<G>(group: Arc<G>, transcripts: Arc<Vec<Transcript<G>>>) -> Vec<bool>
+where
+    G: CyclicGroup + Send + Sync + 'static,
+{
+    let batch = {
+        let (group, transcripts) = (Arc::clone(&group), Arc::clone(&transcripts));
+        // the weights only have to be unpredictable, they don't change the
+        // result so they never come from the seeded generator
+        tokio::task::spawn_blocking(move || {
+            let invalid = verify_batch(&*group, &transcripts, &mut OsRng)
+                .err()
+                .unwrap_or_default();
+            (0..transcripts.len())
+                .map(|index| !invalid.contains(&index))
+                .collect()
+        })
+        .await
+    };
+
+    match batch {
+        Ok(valid) => valid,
+        Err(error) => {
+            error!("Batch verification failed, verifying one by one: {}", error);
+            let len = transcripts.len();
+            tokio::task::spawn_blocking(move || verify_each(&*group, &transcripts))
+                .await
+                .unwrap_or_else(|_| vec![false; len])
+        }
+    }
+}
+
+/// Verifies the transcripts one by one, rejecting the ones whose verification
+/// panics.
+fn verify_each<G: CyclicGroup>(group: &G, transcripts: &[Transcript<G>]) -> Vec<bool> {
+    transcripts
+        .iter()
+        .map(|t| {
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                verify(group, &t.r1, &t.r2, &t.y1, &t.y2, &t.c, &t.s)
+            }))
+            .unwrap_or(false)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chaum_pedersen_zkp::{exponentiates_points, solve_zk_challenge_s, Ristretto255Group};
+    use rand::thread_rng;
+
+    #[tokio::test]
+    async fn test_verify_queue() {
+        let group = Arc::new(Ristretto255Group::default());
+        let queue = Arc::new(VerifyQueue::start(Arc::clone(&group), 4, 16));
+
+        let rng = &mut thread_rng();
+        let mut tasks = Vec::new();
+        for i in 0..10 {
+            let x = group.random_scalar(rng);
+            let k = group.random_scalar(rng);
+            let c = group.random_scalar(rng);
+            let (y1, y2) = exponentiates_points(&*group, &x);
+            let (r1, r2) = exponentiates_points(&*group, &k);
+            let mut s = solve_zk_challenge_s(&*group, &x, &k, &c);
+            if i % 3 == 0 {
+                s = group.scalar_add(&s, &1u64.into());
+            }
+
+            let queue = Arc::clone(&queue);
+            let transcript = Transcript {
+                y1,
+                y2,
+                r1,
+                r2,
+                c,
+                s,
+            };
+            tasks.push(tokio::spawn(async move { queue.verify(transcript).await }));
+        }
+
+        for (i, task) in tasks.into_iter().enumerate() {
+            assert_eq!(task.await.unwrap(), Ok(i % 3 != 0));
+        }
+    }
+
+    fn transcript(group: &Ristretto255Group, valid: bool) -> Transcript<Ristretto255Group> {
+        let rng = &mut thread_rng();
+        let (x, k, c) = (
+            group.random_scalar(rng),
+            group.random_scalar(rng),
+            group.random_scalar(rng),
+        );
+        let (y1, y2) = exponentiates_points(group, &x);
+        let (r1, r2) = exponentiates_points(group, &k);
+        let mut s = solve_zk_challenge_s(group, &x, &k, &c);
+        if !valid {
+            s = group.scalar_add(&s, &1u64.into());
+        }
+        Transcript {
+            y1,
+            y2,
+            r1,
+            r2,
+            c,
+            s,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queue_full() {
+        let group = Arc::new(Ristretto255Group::default());
+        let queue = VerifyQueue::start(Arc::clone(&group), 4, 1);
+
+        // the worker can't run before this task yields, the first answer
+        // fills the queue
+        let (first, second) = tokio::join!(
+            queue.verify(transcript(&group, true)),
+            queue.verify(transcript(&group, true))
+        );
+        assert_eq!(first, Ok(true));
+        assert_eq!(second, Err(QueueFull));
+
+        assert_eq!(queue.verify(transcript(&group, false)).await, Ok(false));
+    }
+
+    #[test]
+    fn test_verify_each() {
+        let group = Ristretto255Group::default();
+        let transcripts = [
+            transcript(&group, true),
+            transcript(&group, false),
+            transcript(&group, true),
+        ];
+        assert_eq!(verify_each(&group, &transcripts), vec![true, false, true]);
+    }
+}